@@ -0,0 +1,421 @@
+//! Rule-based alerts over proxy events.
+//!
+//! Rules are evaluated every few seconds against recent requests in the store
+//! (error rate, 401 bursts and p95 latency per provider), unexpected sidecar
+//! exits, and tunnel statuses. A rule that matches raises an alert through a
+//! desktop notification and an `alert` event, then stays quiet for its cooldown
+//! for that provider or tunnel. Raised alerts are kept in `alerts.json` (most
+//! recent first) until they're acknowledged and age out. Past the cap, the
+//! oldest acknowledged alerts make room first; unacknowledged ones only go when
+//! nothing else is left.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::cloudflare_manager::CloudflareManager;
+use crate::config::{get_alerts_path, AppConfig};
+use crate::request_store::{ProviderOutcomes, RequestStore};
+use crate::ssh_manager::SshManager;
+use crate::state::AppState;
+use crate::timezone::now_ms;
+use crate::types::{Alert, AlertRule};
+
+pub const KIND_ERROR_RATE: &str = "error_rate";
+pub const KIND_AUTH_FAILURES: &str = "auth_failures";
+pub const KIND_LATENCY_P95: &str = "latency_p95";
+pub const KIND_SIDECAR_RESTART: &str = "sidecar_restart";
+pub const KIND_TUNNEL_DOWN: &str = "tunnel_down";
+
+const EVAL_INTERVAL: Duration = Duration::from_secs(15);
+/// Longest window a rule may use; bounds the request query and the exit history
+const MAX_WINDOW_SECS: u64 = 24 * 60 * 60;
+/// Alerts kept in alerts.json
+const MAX_ALERTS: usize = 200;
+/// How long acknowledged alerts are kept
+const ACKNOWLEDGED_TTL_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AlertState {
+    /// Most recent first
+    #[serde(default)]
+    alerts: Vec<Alert>,
+    /// When each rule last fired, keyed by "<rule id>|<subject>"
+    #[serde(default)]
+    last_fired: HashMap<String, u64>,
+}
+
+lazy_static::lazy_static! {
+    /// Serializes read-modify-write of alerts.json between the evaluator and commands
+    static ref STATE_LOCK: Mutex<()> = Mutex::new(());
+    /// When the sidecar exited without being stopped
+    static ref SIDECAR_EXITS: Mutex<VecDeque<u64>> = Mutex::new(VecDeque::new());
+    /// Since when each tunnel ("ssh:<id>" / "cloudflare:<id>") has been in "error" or "reconnecting"
+    static ref TUNNEL_DOWN_SINCE: Mutex<HashMap<String, u64>> = Mutex::new(HashMap::new());
+}
+
+fn load_state() -> AlertState {
+    std::fs::read_to_string(get_alerts_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(state: &AlertState) -> Result<(), String> {
+    let path = get_alerts_path();
+    let temp_path = path.with_extension("json.tmp");
+    let data = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+    std::fs::write(&temp_path, data).map_err(|e| e.to_string())?;
+    std::fs::rename(&temp_path, &path).map_err(|e| e.to_string())
+}
+
+/// Check a rule before saving it
+pub fn validate(rule: &AlertRule) -> Result<(), String> {
+    let kinds = [
+        KIND_ERROR_RATE,
+        KIND_AUTH_FAILURES,
+        KIND_LATENCY_P95,
+        KIND_SIDECAR_RESTART,
+        KIND_TUNNEL_DOWN,
+    ];
+    if !kinds.contains(&rule.kind.as_str()) {
+        return Err(format!("Unsupported alert rule kind: {}", rule.kind));
+    }
+    if !["info", "warning", "critical"].contains(&rule.severity.as_str()) {
+        return Err(format!("Unsupported alert severity: {}", rule.severity));
+    }
+    if rule.window_secs == 0 || rule.window_secs > MAX_WINDOW_SECS {
+        return Err("Alert window must be between 1 second and 24 hours".to_string());
+    }
+    if rule.kind != KIND_TUNNEL_DOWN && (!rule.threshold.is_finite() || rule.threshold <= 0.0) {
+        return Err("Alert threshold must be a positive number".to_string());
+    }
+    if rule.kind == KIND_ERROR_RATE && rule.threshold > 100.0 {
+        return Err("Error rate threshold is a percentage (at most 100)".to_string());
+    }
+    Ok(())
+}
+
+/// Note that the sidecar exited without being stopped
+pub fn record_sidecar_exit() {
+    let now = now_ms();
+    let mut exits = SIDECAR_EXITS.lock().unwrap();
+    exits.push_back(now);
+    while exits.front().is_some_and(|t| *t < now.saturating_sub(MAX_WINDOW_SECS * 1000)) {
+        exits.pop_front();
+    }
+}
+
+fn window_label(secs: u64) -> String {
+    if secs % 3600 == 0 {
+        format!("{} h", secs / 3600)
+    } else if secs % 60 == 0 {
+        format!("{} min", secs / 60)
+    } else {
+        format!("{} s", secs)
+    }
+}
+
+/// A tunnel's name and how long it has been down
+struct DownTunnel {
+    key: String,
+    label: String,
+    status: String,
+    down_secs: u64,
+}
+
+/// Update when each tunnel went down and return the ones that are down now
+fn track_tunnels(app: &AppHandle, config: &AppConfig, now: u64) -> Vec<DownTunnel> {
+    let ssh = app.try_state::<SshManager>().map(|s| s.statuses()).unwrap_or_default();
+    let cloudflare = app.try_state::<CloudflareManager>().map(|s| s.statuses()).unwrap_or_default();
+
+    let mut tunnels: Vec<(String, String, Option<String>)> = Vec::new();
+    for c in &config.ssh_configs {
+        let label = format!("SSH tunnel {}@{}:{}", c.username, c.host, c.port);
+        tunnels.push((format!("ssh:{}", c.id), label, ssh.get(&c.id).cloned()));
+    }
+    for c in &config.cloudflare_configs {
+        let label = format!("Cloudflare tunnel {}", c.name);
+        tunnels.push((format!("cloudflare:{}", c.id), label, cloudflare.get(&c.id).cloned()));
+    }
+
+    let mut since = TUNNEL_DOWN_SINCE.lock().unwrap();
+    let mut down = Vec::new();
+    for (key, label, status) in tunnels {
+        match status.filter(|s| s == "error" || s == "reconnecting") {
+            Some(status) => {
+                let went_down = *since.entry(key.clone()).or_insert(now);
+                down.push(DownTunnel {
+                    key,
+                    label,
+                    status,
+                    down_secs: now.saturating_sub(went_down) / 1000,
+                });
+            }
+            None => {
+                since.remove(&key);
+            }
+        }
+    }
+    down
+}
+
+/// What a matching rule found: the provider or tunnel, the measured value and a description
+struct Finding {
+    subject: String,
+    value: f64,
+    message: String,
+}
+
+fn check_rule(
+    rule: &AlertRule,
+    outcomes: &[ProviderOutcomes],
+    tunnels: &[DownTunnel],
+    sidecar_exits: &[u64],
+    now: u64,
+) -> Vec<Finding> {
+    let since = now.saturating_sub(rule.window_secs * 1000);
+    let window = window_label(rule.window_secs);
+
+    match rule.kind.as_str() {
+        KIND_SIDECAR_RESTART => {
+            let exits = sidecar_exits.iter().filter(|t| **t >= since).count();
+            if exits > 0 && exits as f64 >= rule.threshold {
+                return vec![Finding {
+                    subject: String::new(),
+                    value: exits as f64,
+                    message: format!(
+                        "CLIProxyAPI exited unexpectedly {} time{} in the last {}",
+                        exits,
+                        if exits == 1 { "" } else { "s" },
+                        window
+                    ),
+                }];
+            }
+            Vec::new()
+        }
+        KIND_TUNNEL_DOWN => tunnels
+            .iter()
+            .filter(|t| t.down_secs >= rule.window_secs)
+            .map(|t| Finding {
+                subject: t.key.clone(),
+                value: t.down_secs as f64,
+                message: format!(
+                    "{} has been {} for {}",
+                    t.label,
+                    if t.status == "error" { "failing" } else { "reconnecting" },
+                    window_label(if t.down_secs >= 60 { t.down_secs / 60 * 60 } else { t.down_secs })
+                ),
+            })
+            .collect(),
+        _ => {
+            let mut findings = Vec::new();
+            let matching = outcomes
+                .iter()
+                .filter(|o| rule.provider.is_empty() || o.provider.eq_ignore_ascii_case(rule.provider.trim()));
+            for o in matching {
+                let provider = &o.provider;
+                let finding = match rule.kind.as_str() {
+                    KIND_ERROR_RATE if o.requests >= rule.min_requests.max(1) => {
+                        let rate = o.failed as f64 / o.requests as f64 * 100.0;
+                        (rate >= rule.threshold).then(|| {
+                            (
+                                rate,
+                                format!("{}: {:.0}% of {} requests failed in the last {}", provider, rate, o.requests, window),
+                            )
+                        })
+                    }
+                    KIND_AUTH_FAILURES => (o.unauthorized > 0 && o.unauthorized as f64 >= rule.threshold).then(|| {
+                        (
+                            o.unauthorized as f64,
+                            format!(
+                                "{}: {} requests rejected with 401 in the last {}; a credential may have expired",
+                                provider, o.unauthorized, window
+                            ),
+                        )
+                    }),
+                    KIND_LATENCY_P95 if o.succeeded >= rule.min_requests.max(1) => {
+                        let p95 = o.p95_ms.unwrap_or(0) as f64;
+                        (p95 >= rule.threshold).then(|| {
+                            (
+                                p95,
+                                format!(
+                                    "{}: p95 latency {:.1} s over {} requests in the last {}",
+                                    provider,
+                                    p95 / 1000.0,
+                                    o.succeeded,
+                                    window
+                                ),
+                            )
+                        })
+                    }
+                    _ => None,
+                };
+                if let Some((value, message)) = finding {
+                    findings.push(Finding {
+                        subject: provider.to_string(),
+                        value,
+                        message,
+                    });
+                }
+            }
+            findings
+        }
+    }
+}
+
+fn notify(app: &AppHandle, alert: &Alert) {
+    let title = match alert.severity.as_str() {
+        "critical" => format!("Critical: {}", alert.rule_name),
+        "info" => alert.rule_name.clone(),
+        _ => format!("Warning: {}", alert.rule_name),
+    };
+    let _ = app.emit("alert", alert.clone());
+    if let Err(e) = app.notification().builder().title(title).body(&alert.message).show() {
+        eprintln!("[Alerts] Failed to show notification: {}", e);
+    }
+}
+
+/// Evaluate every enabled rule once, raising alerts for those that match
+pub fn evaluate(app: &AppHandle) -> Result<(), String> {
+    let config = app.state::<AppState>().config.lock().unwrap().clone();
+    let now = now_ms();
+    // Tunnels are tracked even without rules, so a new rule sees how long they've been down
+    let tunnels = track_tunnels(app, &config, now);
+
+    let rules: Vec<&AlertRule> = config.alert_rules.iter().filter(|r| r.enabled).collect();
+    if rules.is_empty() {
+        return Ok(());
+    }
+
+    // Request rules are counted in SQL, once per distinct window
+    let mut outcomes: HashMap<u64, Vec<ProviderOutcomes>> = HashMap::new();
+    if let Some(store) = app.try_state::<RequestStore>() {
+        for rule in &rules {
+            let secs = rule.window_secs.min(MAX_WINDOW_SECS);
+            if [KIND_ERROR_RATE, KIND_AUTH_FAILURES, KIND_LATENCY_P95].contains(&rule.kind.as_str())
+                && !outcomes.contains_key(&secs)
+            {
+                // A failed query only skips the request rules for this window
+                let window = store.outcomes_since(now.saturating_sub(secs * 1000)).unwrap_or_else(|e| {
+                    eprintln!("[Alerts] Failed to count requests in the last {}s: {}", secs, e);
+                    Vec::new()
+                });
+                outcomes.insert(secs, window);
+            }
+        }
+    }
+    let sidecar_exits: Vec<u64> = SIDECAR_EXITS.lock().unwrap().iter().copied().collect();
+
+    let _guard = STATE_LOCK.lock().unwrap();
+    let mut state = load_state();
+    let mut raised = Vec::new();
+    for rule in rules {
+        let rule_outcomes = outcomes
+            .get(&rule.window_secs.min(MAX_WINDOW_SECS))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for finding in check_rule(rule, rule_outcomes, &tunnels, &sidecar_exits, now) {
+            let key = format!("{}|{}", rule.id, finding.subject);
+            let cooling = state
+                .last_fired
+                .get(&key)
+                .is_some_and(|fired| now < fired + rule.cooldown_secs * 1000);
+            if cooling {
+                continue;
+            }
+            state.last_fired.insert(key, now);
+            println!("[Alerts] {}: {}", rule.name, finding.message);
+            raised.push(Alert {
+                id: uuid::Uuid::new_v4().to_string(),
+                rule_id: rule.id.clone(),
+                rule_name: rule.name.clone(),
+                kind: rule.kind.clone(),
+                severity: rule.severity.clone(),
+                subject: finding.subject,
+                message: finding.message,
+                value: finding.value,
+                threshold: rule.threshold,
+                triggered_at: now,
+                acknowledged_at: None,
+            });
+        }
+    }
+    if raised.is_empty() {
+        return Ok(());
+    }
+
+    for alert in raised.iter().rev() {
+        state.alerts.insert(0, alert.clone());
+    }
+    prune(&mut state.alerts, now);
+    save_state(&state)?;
+    for alert in &raised {
+        notify(app, alert);
+    }
+    Ok(())
+}
+
+/// Drop acknowledged alerts that aged out, then trim to the cap: the oldest
+/// acknowledged alerts first, unacknowledged ones only when none are left
+fn prune(alerts: &mut Vec<Alert>, now: u64) {
+    alerts.retain(|a| a.acknowledged_at.is_none_or(|at| now < at + ACKNOWLEDGED_TTL_MS));
+    while alerts.len() > MAX_ALERTS {
+        let oldest = alerts
+            .iter()
+            .rposition(|a| a.acknowledged_at.is_some())
+            .unwrap_or(alerts.len() - 1);
+        alerts.remove(oldest);
+    }
+}
+
+/// Start the background evaluator
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(EVAL_INTERVAL).await;
+            if let Err(e) = evaluate(&app) {
+                eprintln!("[Alerts] Evaluation failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Raised alerts, most recent first
+pub fn recent() -> Vec<Alert> {
+    let _guard = STATE_LOCK.lock().unwrap();
+    load_state().alerts
+}
+
+/// Acknowledge one alert, or every unacknowledged alert when `id` is None
+pub fn acknowledge(id: Option<&str>) -> Result<Vec<Alert>, String> {
+    let _guard = STATE_LOCK.lock().unwrap();
+    let mut state = load_state();
+    let now = now_ms();
+    let mut found = false;
+    for alert in state.alerts.iter_mut() {
+        if id.is_none_or(|id| alert.id == id) {
+            found = true;
+            alert.acknowledged_at.get_or_insert(now);
+        }
+    }
+    if let Some(id) = id.filter(|_| !found) {
+        return Err(format!("Alert not found: {}", id));
+    }
+    save_state(&state)?;
+    Ok(state.alerts)
+}
+
+/// Forget cooldowns of a deleted rule
+pub fn forget_rule(rule_id: &str) -> Result<(), String> {
+    let _guard = STATE_LOCK.lock().unwrap();
+    let mut state = load_state();
+    let prefix = format!("{}|", rule_id);
+    state.last_fired.retain(|key, _| !key.starts_with(&prefix));
+    save_state(&state)
+}
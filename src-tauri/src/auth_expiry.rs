@@ -0,0 +1,383 @@
+//! Expiry tracking and proactive refresh of the OAuth credentials in
+//! `~/.cli-proxy-api`.
+//!
+//! Providers store the token expiry in different shapes (`expired` as RFC 3339,
+//! `expiry_date` in ms, `expires_in` plus a `timestamp`, or nested under a
+//! `token` object for Gemini), so they're normalized to Unix ms here. Every
+//! minute the scheduler looks for tokens expiring within the lead time.
+//! While the proxy runs it owns the credential files, so refreshable ones are
+//! re-registered through the management API for CLIProxyAPI to reload rather
+//! than rewritten here. CLIProxyAPI has no refresh endpoint, so the token only
+//! counts as refreshed once the file holds a later expiry; otherwise a
+//! warning is raised, as it is for credentials without a refresh token. Only
+//! with the proxy stopped are Antigravity tokens refreshed directly against
+//! Google. A failed refresh or re-register is retried with backoff until it
+//! succeeds or the token expires, and each outcome is reported once per token
+//! through an `auth-expiring` event; a token that still expires unrefreshed
+//! raises a final notification.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::state::AppState;
+use crate::timezone::now_ms;
+use crate::types::{AuthExpiring, AuthFile};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Lifetime assumed for a refreshed Antigravity token
+const ANTIGRAVITY_TOKEN_SECS: i64 = 60 * 60;
+/// Delay before retrying a failed refresh, doubled per failure up to `RETRY_MAX_MS`
+const RETRY_BASE_MS: u64 = 60_000;
+const RETRY_MAX_MS: u64 = 15 * 60_000;
+
+/// Fields holding an absolute expiry, in the order they're tried
+const EXPIRY_FIELDS: &[&str] = &["expired", "expiry", "expires_at", "expiry_date", "expire"];
+
+lazy_static::lazy_static! {
+    /// Tokens already reported, keyed by "<file>|<expires_at>|<stage>"
+    static ref REPORTED: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+    /// Failed refreshes awaiting a retry, keyed by "<file>|<expires_at>"
+    static ref RETRIES: Mutex<HashMap<String, Retry>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Retry {
+    failures: u32,
+    next_at: u64,
+}
+
+/// Token expiry and refresh capability of one credential file
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenInfo {
+    pub expires_at: Option<u64>,
+    pub refreshable: bool,
+}
+
+fn auth_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".cli-proxy-api"))
+}
+
+/// Seconds or milliseconds since the epoch as ms; non-positive values mean "unset"
+fn epoch_ms(value: f64) -> Option<u64> {
+    if !value.is_finite() || value <= 0.0 {
+        None
+    } else if value < 1e12 {
+        Some((value * 1000.0) as u64)
+    } else {
+        Some(value as u64)
+    }
+}
+
+fn parse_time(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_f64().and_then(epoch_ms),
+        Value::String(s) => {
+            let s = s.trim();
+            match s.parse::<f64>() {
+                Ok(n) => epoch_ms(n),
+                // Go's zero time ("0001-01-01T00:00:00Z") is before the epoch and dropped
+                Err(_) => chrono::DateTime::parse_from_rfc3339(s)
+                    .ok()
+                    .and_then(|dt| u64::try_from(dt.timestamp_millis()).ok())
+                    .filter(|ms| *ms > 0),
+            }
+        }
+        _ => None,
+    }
+}
+
+fn expiry_of(obj: &Value) -> Option<u64> {
+    EXPIRY_FIELDS
+        .iter()
+        .find_map(|field| obj.get(*field).and_then(parse_time))
+        .or_else(|| {
+            // Relative lifetime from when the token was issued
+            let expires_in = obj.get("expires_in")?.as_f64()?;
+            let issued = obj.get("timestamp").and_then(parse_time)?;
+            Some(issued.saturating_add((expires_in.max(0.0) * 1000.0) as u64))
+        })
+}
+
+/// Read the expiry and refresh token of a credential file's JSON
+pub fn token_info(json: &Value) -> TokenInfo {
+    let nested = json.get("token").filter(|t| t.is_object());
+    let objects = std::iter::once(json).chain(nested);
+    let mut info = TokenInfo::default();
+    for obj in objects {
+        info.expires_at = info.expires_at.or_else(|| expiry_of(obj));
+        info.refreshable |= obj
+            .get("refresh_token")
+            .and_then(|t| t.as_str())
+            .is_some_and(|t| !t.trim().is_empty());
+    }
+    info
+}
+
+pub fn read_token_info(path: &Path) -> Option<TokenInfo> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let json = serde_json::from_str::<Value>(&content).ok()?;
+    Some(token_info(&json))
+}
+
+/// Fill `expires_at`/`refreshable` of auth files from their credential files
+pub fn annotate(files: &mut [AuthFile]) {
+    let dir = auth_dir();
+    for file in files.iter_mut() {
+        let path = match (&file.path, &dir) {
+            (Some(path), _) => PathBuf::from(path),
+            (None, Some(dir)) => dir.join(&file.name),
+            (None, None) => continue,
+        };
+        if let Some(info) = read_token_info(&path) {
+            file.expires_at = info.expires_at;
+            file.refreshable = info.refreshable;
+        }
+    }
+}
+
+/// Refresh an Antigravity credential against Google and write the new token back.
+/// Only used while the proxy is stopped; the file is left alone if it changed meanwhile.
+async fn refresh_antigravity(client: &reqwest::Client, path: &Path) -> Result<u64, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("Failed to read auth file: {}", e))?;
+    let mut json: Value = serde_json::from_str(&content).map_err(|e| format!("Invalid JSON: {}", e))?;
+    let refresh_token = json
+        .get("refresh_token")
+        .and_then(|t| t.as_str())
+        .ok_or("No refresh_token in auth file")?
+        .to_string();
+
+    let access_token = crate::refresh_antigravity_token(client, &refresh_token).await?;
+    let now = chrono::Local::now();
+    let expires = now + chrono::Duration::seconds(ANTIGRAVITY_TOKEN_SECS);
+    let obj = json.as_object_mut().ok_or("Auth file is not a JSON object")?;
+    obj.insert("access_token".to_string(), Value::String(access_token));
+    obj.insert("expired".to_string(), Value::String(expires.to_rfc3339()));
+    if obj.contains_key("timestamp") {
+        obj.insert("timestamp".to_string(), Value::from(now.timestamp_millis()));
+        obj.insert("expires_in".to_string(), Value::from(ANTIGRAVITY_TOKEN_SECS));
+    }
+
+    let data = serde_json::to_string_pretty(&json).map_err(|e| e.to_string())?;
+    if std::fs::read_to_string(path).ok().as_deref() != Some(content.as_str()) {
+        return Err("Auth file changed during the refresh".to_string());
+    }
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, data).map_err(|e| format!("Failed to write auth file: {}", e))?;
+    std::fs::rename(&temp_path, path).map_err(|e| format!("Failed to write auth file: {}", e))?;
+    Ok(expires.timestamp_millis() as u64)
+}
+
+/// Re-register a credential with the running proxy so it reloads it
+async fn reregister(port: u16, path: &Path, name: &str, provider: &str) -> Result<(), String> {
+    let content = std::fs::read(path).map_err(|e| format!("Failed to read auth file: {}", e))?;
+    let part = reqwest::multipart::Part::bytes(content)
+        .file_name(name.to_string())
+        .mime_str("application/json")
+        .map_err(|e| e.to_string())?;
+    let form = reqwest::multipart::Form::new()
+        .text("provider", provider.to_string())
+        .text("filename", name.to_string())
+        .part("file", part);
+
+    let response = crate::build_management_client()
+        .post(crate::get_management_url(port, "auth-files"))
+        .header("X-Management-Key", &crate::get_management_key())
+        .multipart(form)
+        .send()
+        .await
+        .map_err(|e| format!("Management API unreachable: {}", e))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(format!("Management API returned {}: {}", status, text));
+    }
+    Ok(())
+}
+
+fn is_reported(name: &str, expires_at: u64, stage: &str) -> bool {
+    REPORTED
+        .lock()
+        .unwrap()
+        .contains(&format!("{}|{}|{}", name, expires_at, stage))
+}
+
+/// Whether this stage of this token hasn't been reported yet; marks it reported
+fn first_report(name: &str, expires_at: u64, stage: &str) -> bool {
+    REPORTED
+        .lock()
+        .unwrap()
+        .insert(format!("{}|{}|{}", name, expires_at, stage))
+}
+
+fn report(app: &AppHandle, event: AuthExpiring) {
+    match event.action.as_str() {
+        "refreshed" => println!("[Auth] {}", event.message),
+        _ => eprintln!("[Auth] {}", event.message),
+    }
+    let _ = app.emit("auth-expiring", event.clone());
+    if event.action != "refreshed" {
+        let title = if event.action == "expired" { "Account token expired" } else { "Account token expiring" };
+        if let Err(e) = app.notification().builder().title(title).body(&event.message).show() {
+            eprintln!("[Auth] Failed to show notification: {}", e);
+        }
+    }
+}
+
+fn minutes_until(expires_at: u64, now: u64) -> u64 {
+    (expires_at.saturating_sub(now) + 59_999) / 60_000
+}
+
+/// Check every enabled credential once, refreshing or warning about expiring tokens
+pub async fn check(app: &AppHandle) {
+    let (settings, port, proxy_running) = {
+        let state = app.state::<AppState>();
+        let config = state.config.lock().unwrap();
+        let running = state.proxy_status.lock().unwrap().running;
+        (config.token_refresh.clone(), config.port, running)
+    };
+    if !settings.enabled {
+        return;
+    }
+
+    let Some(dir) = auth_dir() else {
+        return;
+    };
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return;
+    };
+    let now = now_ms();
+    // lead_minutes comes from the webview unchecked
+    let lead_ms = settings.lead_minutes.saturating_mul(60_000);
+    let client = reqwest::Client::new();
+    let mut pending = HashSet::new();
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        // Disabled credentials end in ".json.disabled" and aren't used by the proxy
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|n| n.to_str()).map(str::to_string) else {
+            continue;
+        };
+        let Some(TokenInfo { expires_at: Some(expires_at), refreshable }) = read_token_info(&path) else {
+            continue;
+        };
+        if expires_at > now.saturating_add(lead_ms) {
+            continue;
+        }
+        let provider = crate::providers::for_auth_file(&name);
+        let provider_id = provider.map(|p| p.id).unwrap_or("unknown").to_string();
+        let label = provider.map(|p| p.display_name).unwrap_or("Unknown provider");
+        let event = |action: &str, message: String| AuthExpiring {
+            name: name.clone(),
+            provider: provider_id.clone(),
+            expires_at,
+            refreshable,
+            action: action.to_string(),
+            message,
+        };
+
+        if expires_at <= now {
+            // Still unrefreshed after the lead window; requests with it will fail
+            if first_report(&name, expires_at, "expired") {
+                let message = format!("{} token in {} has expired; reconnect the account", label, name);
+                report(app, event("expired", message));
+            }
+            continue;
+        }
+        if is_reported(&name, expires_at, "lead") {
+            continue;
+        }
+
+        let minutes = minutes_until(expires_at, now);
+        if !refreshable {
+            first_report(&name, expires_at, "lead");
+            let message =
+                format!("{} token in {} expires in {} min and can't be refreshed; reconnect the account", label, name, minutes);
+            report(app, event("manual", message));
+            continue;
+        }
+
+        let key = format!("{}|{}", name, expires_at);
+        pending.insert(key.clone());
+        let retry = RETRIES.lock().unwrap().get(&key).copied();
+        if retry.is_some_and(|r| now < r.next_at) {
+            continue;
+        }
+
+        let result = if proxy_running {
+            // CLIProxyAPI has no refresh endpoint: re-registering makes it reload the
+            // file, and only a later expiry read back from the file counts as refreshed
+            reregister(port, &path, &name, &provider_id).await.map(|()| {
+                match read_token_info(&path).and_then(|info| info.expires_at).filter(|e| *e > expires_at) {
+                    Some(new_expiry) => AuthExpiring {
+                        expires_at: new_expiry,
+                        ..event("refreshed", format!("{} token in {} was refreshed by the proxy", label, name))
+                    },
+                    None => event(
+                        "manual",
+                        format!(
+                            "{} token in {} expires in {} min and the proxy hasn't refreshed it; reconnect the account if it expires",
+                            label, name, minutes
+                        ),
+                    ),
+                }
+            })
+        } else if provider_id == "antigravity" {
+            refresh_antigravity(&client, &path).await.map(|new_expiry| AuthExpiring {
+                expires_at: new_expiry,
+                ..event("refreshed", format!("Refreshed {} token in {}", label, name))
+            })
+        } else {
+            Err("the proxy isn't running".to_string())
+        };
+
+        match result {
+            Ok(reported) => {
+                first_report(&name, expires_at, "lead");
+                RETRIES.lock().unwrap().remove(&key);
+                pending.remove(&key);
+                report(app, reported);
+            }
+            Err(e) => {
+                let failures = retry.map_or(1, |r| r.failures + 1);
+                let delay = RETRY_BASE_MS.saturating_mul(1 << (failures - 1).min(16)).min(RETRY_MAX_MS);
+                RETRIES.lock().unwrap().insert(key, Retry { failures, next_at: now.saturating_add(delay) });
+                let message = format!(
+                    "{} token in {} expires in {} min and couldn't be refreshed: {}; retrying in {} min",
+                    label,
+                    name,
+                    minutes,
+                    e,
+                    delay / 60_000
+                );
+                // Notify on the first failure only; later ones are logged
+                if failures == 1 {
+                    report(app, event("refresh_failed", message));
+                } else {
+                    eprintln!("[Auth] {}", message);
+                }
+            }
+        }
+    }
+
+    // Forget retries of tokens that were refreshed, removed or have expired
+    RETRIES.lock().unwrap().retain(|key, _| pending.contains(key));
+}
+
+/// Check credentials for expiring tokens every minute in the background
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            check(&app).await;
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
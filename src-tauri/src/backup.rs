@@ -0,0 +1,244 @@
+//! ProxyPal usage backups: the request log, the aggregate and the user pricing
+//! catalog in one JSON file, so analytics survive a move to a new machine.
+//! Requests are streamed from the store into the file, and client API keys
+//! are only included as fingerprints.
+//!
+//! Imports merge rather than overwrite. Requests are matched by ID and
+//! aggregate series and stats by label or key, keeping the larger value (as
+//! `usage_merge` does for the proxy's numbers). Importing the same backup twice,
+//! or a backup of this machine, therefore changes nothing. The catch is that usage
+//! from two machines that both counted the same day isn't summed; only the
+//! request log holds both, and `rebuild_aggregate` can recount from it.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::pricing;
+use crate::request_store::{api_key_fingerprint, RequestStore};
+use crate::timezone::{now_ms, DisplayTimezone};
+use crate::types::{
+    Aggregate, LatencyHistogram, LatencyStats, ModelStats, PricingCatalog, RequestHistory, RequestLog, RequestQuery,
+    SourceTotals, UsageBackup, UsageBackupMetadata, UsageRetention, USAGE_BACKUP_FORMAT, USAGE_BACKUP_VERSION,
+};
+use crate::usage_merge::{merge_model_stats_max, merge_series_max, merge_sources, sources_mut};
+
+/// Write a backup of everything stored locally to `path`, streaming the request
+/// log from the store; replaces `path` only once fully written. Client API keys
+/// are only written as fingerprints.
+pub fn write(store: &RequestStore, aggregate: &Aggregate, path: &Path) -> Result<UsageBackupMetadata, String> {
+    let temp_path = path.with_extension("partial");
+    let file = File::create(&temp_path).map_err(|e| format!("Failed to create {}: {}", temp_path.display(), e))?;
+    let mut out = BufWriter::new(file);
+    match write_to(&mut out, store, aggregate).and_then(|metadata| {
+        out.flush().map_err(|e| e.to_string())?;
+        Ok(metadata)
+    }) {
+        Ok(metadata) => {
+            drop(out);
+            std::fs::rename(&temp_path, path).map_err(|e| format!("Failed to save backup: {}", e))?;
+            Ok(metadata)
+        }
+        Err(e) => {
+            drop(out);
+            let _ = std::fs::remove_file(&temp_path);
+            Err(format!("Failed to write backup: {}", e))
+        }
+    }
+}
+
+/// The fields of `UsageBackup`, with requests written one by one and the metadata
+/// (which counts them) last
+fn write_to(out: &mut impl Write, store: &RequestStore, aggregate: &Aggregate) -> Result<UsageBackupMetadata, String> {
+    let io = |e: std::io::Error| e.to_string();
+    let json = |e: serde_json::Error| e.to_string();
+
+    out.write_all(b"{\"requests\":[").map_err(io)?;
+    let mut first = true;
+    let count = store.for_each_request(&RequestQuery::default(), |req| {
+        if !std::mem::take(&mut first) {
+            out.write_all(b",").map_err(io)?;
+        }
+        let mut req = req.clone();
+        req.api_key = req.api_key.as_deref().map(api_key_fingerprint);
+        serde_json::to_writer(&mut *out, &req).map_err(json)
+    })?;
+    out.write_all(b"],\"aggregate\":").map_err(io)?;
+    serde_json::to_writer(&mut *out, aggregate).map_err(json)?;
+    out.write_all(b",\"pricing\":").map_err(io)?;
+    serde_json::to_writer(&mut *out, &pricing::catalogs().user).map_err(json)?;
+
+    let metadata = UsageBackupMetadata {
+        format: USAGE_BACKUP_FORMAT.to_string(),
+        version: USAGE_BACKUP_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: now_ms(),
+        request_count: count,
+    };
+    out.write_all(b",\"metadata\":").map_err(io)?;
+    serde_json::to_writer(&mut *out, &metadata).map_err(json)?;
+    out.write_all(b"}").map_err(io)?;
+    Ok(metadata)
+}
+
+/// Top level of a backup file as read. Metadata stays untyped until its format is
+/// checked, so other JSON files (like CLIProxyAPI usage exports) get a clear error.
+#[derive(Deserialize)]
+struct BackupFile {
+    #[serde(default)]
+    metadata: Option<serde_json::Value>,
+    #[serde(default)]
+    requests: Vec<RequestLog>,
+    #[serde(default)]
+    aggregate: Option<Aggregate>,
+    #[serde(default)]
+    pricing: PricingCatalog,
+}
+
+/// Read and check a backup written by `write`, parsing it in one typed pass
+pub fn read(path: &Path) -> Result<UsageBackup, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let backup: BackupFile =
+        serde_json::from_reader(BufReader::new(file)).map_err(|e| format!("Invalid backup file: {}", e))?;
+
+    let metadata = backup.metadata.as_ref();
+    let format = metadata.and_then(|m| m.get("format")).and_then(|f| f.as_str());
+    if format != Some(USAGE_BACKUP_FORMAT) {
+        return Err("Not a ProxyPal usage backup (use the CLIProxyAPI import for proxy usage exports)".to_string());
+    }
+    let version = metadata.and_then(|m| m.get("version")).and_then(|v| v.as_u64()).unwrap_or(0);
+    if version == 0 || version > USAGE_BACKUP_VERSION as u64 {
+        return Err(format!(
+            "Backup version {} isn't supported by this version of ProxyPal (up to {})",
+            version, USAGE_BACKUP_VERSION
+        ));
+    }
+    let metadata: UsageBackupMetadata = serde_json::from_value(backup.metadata.unwrap_or_default())
+        .map_err(|e| format!("Invalid backup metadata: {}", e))?;
+    Ok(UsageBackup {
+        metadata,
+        requests: backup.requests,
+        aggregate: backup.aggregate.ok_or("Invalid backup file: missing aggregate")?,
+        pricing: backup.pricing,
+    })
+}
+
+fn max_totals(existing: &mut SourceTotals, imported: &SourceTotals) {
+    existing.requests = existing.requests.max(imported.requests);
+    existing.success_count = existing.success_count.max(imported.success_count);
+    existing.failure_count = existing.failure_count.max(imported.failure_count);
+    existing.tokens_in = existing.tokens_in.max(imported.tokens_in);
+    existing.tokens_out = existing.tokens_out.max(imported.tokens_out);
+    existing.tokens_cached = existing.tokens_cached.max(imported.tokens_cached);
+    existing.cost_usd = existing.cost_usd.max(imported.cost_usd);
+}
+
+fn merge_stats_max(
+    existing: &mut std::collections::HashMap<String, ModelStats>,
+    imported: &std::collections::HashMap<String, ModelStats>,
+) {
+    for (key, stats) in imported {
+        merge_model_stats_max(existing.entry(key.clone()).or_default(), stats);
+    }
+}
+
+/// Keep whichever histogram saw more requests, per key
+fn merge_latency_max(existing: &mut LatencyStats, imported: &LatencyStats) {
+    let pick = |mine: &mut std::collections::HashMap<String, LatencyHistogram>,
+                theirs: &std::collections::HashMap<String, LatencyHistogram>| {
+        for (key, histogram) in theirs {
+            let entry = mine.entry(key.clone()).or_default();
+            if histogram.count > entry.count {
+                *entry = histogram.clone();
+            }
+        }
+    };
+    pick(&mut existing.by_model, &imported.by_model);
+    pick(&mut existing.by_provider, &imported.by_provider);
+    pick(&mut existing.by_route, &imported.by_route);
+}
+
+/// Merge an imported aggregate into the local one, keeping the larger value per
+/// series label, stats key and source total
+pub fn merge_aggregate(
+    agg: &mut Aggregate,
+    imported: &Aggregate,
+    retention: &UsageRetention,
+    now: u64,
+    tz: &DisplayTimezone,
+) {
+    // Shape both series the same way first, so a day rolled into its month on one
+    // side is compared with the same month on the other
+    let mut imported = imported.clone();
+    crate::retention::apply_series_retention(agg, retention, now, tz);
+    crate::retention::apply_series_retention(&mut imported, retention, now, tz);
+
+    agg.created_at = agg.created_at.min(imported.created_at);
+    merge_series_max(&mut agg.requests_by_day, &imported.requests_by_day);
+    merge_series_max(&mut agg.tokens_by_day, &imported.tokens_by_day);
+    merge_series_max(&mut agg.requests_by_hour, &imported.requests_by_hour);
+    merge_series_max(&mut agg.tokens_by_hour, &imported.tokens_by_hour);
+    merge_series_max(&mut agg.requests_by_month, &imported.requests_by_month);
+    merge_series_max(&mut agg.tokens_by_month, &imported.tokens_by_month);
+
+    merge_stats_max(&mut agg.model_stats, &imported.model_stats);
+    merge_stats_max(&mut agg.provider_stats, &imported.provider_stats);
+    merge_stats_max(&mut agg.endpoint_stats, &imported.endpoint_stats);
+    merge_stats_max(&mut agg.account_stats, &imported.account_stats);
+    for (category, count) in &imported.error_stats {
+        let entry = agg.error_stats.entry(category.clone()).or_insert(0);
+        *entry = (*entry).max(*count);
+    }
+
+    merge_latency_max(&mut agg.latency, &imported.latency);
+    for (day, stats) in &imported.latency_by_day {
+        merge_latency_max(agg.latency_by_day.entry(day.clone()).or_default(), stats);
+    }
+
+    let theirs = sources_mut(&mut imported).clone();
+    let mine = sources_mut(agg);
+    max_totals(&mut mine.watcher, &theirs.watcher);
+    max_totals(&mut mine.proxy, &theirs.proxy);
+    merge_sources(agg);
+}
+
+fn rule_key(rule: &crate::types::PriceRule) -> (String, String, String) {
+    let lower = |s: &Option<String>| s.as_deref().unwrap_or("").to_lowercase();
+    (lower(&rule.model), lower(&rule.provider), lower(&rule.effective_from))
+}
+
+/// Add imported pricing rules for models/providers/dates the local catalog doesn't
+/// cover; local rules win. Returns the number of rules added.
+pub fn merge_pricing(local: &mut PricingCatalog, imported: &PricingCatalog) -> u64 {
+    let mut known: HashSet<_> = local.rules.iter().map(rule_key).collect();
+    let mut added = 0;
+    for rule in &imported.rules {
+        if known.insert(rule_key(rule)) {
+            local.rules.push(rule.clone());
+            added += 1;
+        }
+    }
+    added
+}
+
+/// Add imported requests to the recent history shown in the UI and mirror the
+/// merged aggregate's totals into it
+pub fn merge_history(history: &mut RequestHistory, imported: &[crate::types::RequestLog], agg: &Aggregate) {
+    let known: HashSet<String> = history.requests.iter().map(|r| r.id.clone()).collect();
+    history
+        .requests
+        .extend(imported.iter().filter(|r| !known.contains(&r.id)).cloned());
+    history.requests.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+
+    history.total_request_count = agg.total_requests;
+    history.total_success_count = agg.total_success_count;
+    history.total_tokens_in = agg.total_tokens_in;
+    history.total_tokens_out = agg.total_tokens_out;
+    history.total_tokens_cached = agg.total_tokens_cached;
+    history.total_cost_usd = agg.total_cost_usd;
+    history.tokens_by_day = agg.tokens_by_day.iter().rev().take(14).rev().cloned().collect();
+    history.tokens_by_hour = agg.tokens_by_hour.iter().rev().take(168).rev().cloned().collect();
+}
@@ -0,0 +1,717 @@
+//! Token and cost budgets.
+//!
+//! Budgets are evaluated against the request store whenever the log watcher
+//! ingests requests (and after token backfills). Crossing 80% and 100% of a
+//! budget's limit raises a desktop notification and a `budget-alert` event,
+//! once per level and period. An enforcing budget that reaches 100% suspends
+//! the credentials that served its matching requests this period: OAuth auth
+//! files are renamed to `.json.disabled` (as the auth file toggle does) and API
+//! keys are removed from the running proxy. Everything it suspended is
+//! restored when the period resets, or when the budget stops enforcing.
+//!
+//! Suspended API keys are referred to by an opaque id in `budget-state.json`
+//! and the budget status; the keys and their management entries are kept in
+//! the owner-only `budget-keys.json`, which never leaves the backend.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::config::{get_budget_keys_path, get_budget_state_path, save_config_to_file, AppConfig};
+use crate::export::usd;
+use crate::pricing::{self, glob_match};
+use crate::proxy::correlation::mask_api_key;
+use crate::request_store::{api_key_fingerprint, RequestStore, RouteUsage};
+use crate::state::AppState;
+use crate::timezone::{now_ms, period_bounds, DisplayTimezone};
+use crate::types::{
+    Budget, BudgetAlert, BudgetStatus, BudgetSuspension, ClaudeApiKey, CodexApiKey, GeminiApiKey,
+};
+use crate::{build_management_client, convert_api_key_response, get_management_key, get_management_url};
+
+pub const KIND_AUTH_FILE: &str = "auth_file";
+pub const KIND_API_KEY: &str = "api_key";
+
+/// Alert levels, in percent of the limit
+const LEVEL_WARNING: u8 = 80;
+const LEVEL_EXCEEDED: u8 = 100;
+
+/// Highest alert level raised for a budget, and the period it was raised in
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AlertMark {
+    period_start: u64,
+    level: u8,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BudgetState {
+    #[serde(default)]
+    alerts: HashMap<String, AlertMark>,
+    #[serde(default)]
+    suspensions: Vec<BudgetSuspension>,
+}
+
+/// A suspended API key and what's needed to put it back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SuspendedKey {
+    /// Management API endpoint the key was removed from
+    endpoint: String,
+    api_key: String,
+    /// The key's management API entry, so it can be re-added as it was
+    entry: Value,
+}
+
+lazy_static::lazy_static! {
+    /// Evaluations come from the log watcher and the snapshot task; run one at a time
+    static ref EVALUATING: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+fn load_state() -> BudgetState {
+    let mut state: BudgetState = std::fs::read_to_string(get_budget_state_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default();
+
+    // Older versions kept the key and its entry in the state file; move them out
+    let legacy: Vec<usize> = (0..state.suspensions.len())
+        .filter(|&i| state.suspensions[i].kind == KIND_API_KEY && state.suspensions[i].entry.is_some())
+        .collect();
+    if legacy.is_empty() {
+        return state;
+    }
+    let mut keys = load_keys();
+    for i in legacy {
+        let suspension = &mut state.suspensions[i];
+        let Ok(endpoint) = key_endpoint(&suspension.provider) else {
+            continue;
+        };
+        let id = uuid::Uuid::new_v4().to_string();
+        keys.insert(
+            id.clone(),
+            SuspendedKey {
+                endpoint: endpoint.to_string(),
+                api_key: std::mem::replace(&mut suspension.target, id),
+                entry: suspension.entry.take().unwrap_or_default(),
+            },
+        );
+    }
+    if let Err(e) = save_keys(&keys).and_then(|_| save_state(&state)) {
+        eprintln!("[Budgets] Failed to move suspended keys out of the state file: {}", e);
+    }
+    state
+}
+
+fn save_state(state: &BudgetState) -> Result<(), String> {
+    let path = get_budget_state_path();
+    let temp_path = path.with_extension("json.tmp");
+    let data = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+    std::fs::write(&temp_path, data).map_err(|e| e.to_string())?;
+    std::fs::rename(&temp_path, &path).map_err(|e| e.to_string())
+}
+
+/// Suspended API keys by suspension id
+fn load_keys() -> HashMap<String, SuspendedKey> {
+    std::fs::read_to_string(get_budget_keys_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Write the suspended keys readable by the owner only
+fn save_keys(keys: &HashMap<String, SuspendedKey>) -> Result<(), String> {
+    use std::io::Write;
+
+    let path = get_budget_keys_path();
+    let temp_path = path.with_extension("json.tmp");
+    let data = serde_json::to_string_pretty(keys).map_err(|e| e.to_string())?;
+    // The mode only applies when the file is created
+    let _ = std::fs::remove_file(&temp_path);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&temp_path).map_err(|e| e.to_string())?;
+    file.write_all(data.as_bytes()).map_err(|e| e.to_string())?;
+    drop(file);
+    std::fs::rename(&temp_path, &path).map_err(|e| e.to_string())
+}
+
+/// Check a budget before saving it
+pub fn validate(budget: &Budget) -> Result<(), String> {
+    if !["provider", "model", "client_key"].contains(&budget.scope.as_str()) {
+        return Err(format!("Unsupported budget scope: {}", budget.scope));
+    }
+    if budget.target.trim().is_empty() {
+        return Err("Budget target is required".to_string());
+    }
+    if !["daily", "weekly", "monthly"].contains(&budget.period.as_str()) {
+        return Err(format!("Unsupported budget period: {}", budget.period));
+    }
+    if !["tokens", "cost"].contains(&budget.metric.as_str()) {
+        return Err(format!("Unsupported budget metric: {}", budget.metric));
+    }
+    if !budget.limit.is_finite() || budget.limit <= 0.0 {
+        return Err("Budget limit must be a positive number".to_string());
+    }
+    Ok(())
+}
+
+fn matches(budget: &Budget, row: &RouteUsage) -> bool {
+    let target = budget.target.trim();
+    match budget.scope.as_str() {
+        "provider" => row.provider.eq_ignore_ascii_case(target),
+        "model" => glob_match(target, &row.model),
+        "client_key" => row.api_key.as_deref() == Some(api_key_fingerprint(target).as_str()),
+        _ => false,
+    }
+}
+
+/// Tokens (input + output) or estimated cost of the rows
+fn measure(budget: &Budget, rows: &[&RouteUsage]) -> f64 {
+    rows.iter()
+        .map(|row| match budget.metric.as_str() {
+            "cost" => pricing::cost(&row.model, &row.provider, row.day, row.usage),
+            _ => (row.usage.input + row.usage.output) as f64,
+        })
+        .sum()
+}
+
+fn fraction(used: f64, limit: f64) -> f64 {
+    if limit > 0.0 {
+        used / limit
+    } else {
+        0.0
+    }
+}
+
+/// Route usage per period start, so budgets sharing a period share one query
+struct UsageCache<'a> {
+    store: &'a RequestStore,
+    rows: HashMap<u64, Vec<RouteUsage>>,
+}
+
+impl UsageCache<'_> {
+    fn rows(&mut self, start: u64, end: u64) -> Result<&[RouteUsage], String> {
+        if !self.rows.contains_key(&start) {
+            let rows = self.store.route_usage(start, end)?;
+            self.rows.insert(start, rows);
+        }
+        Ok(&self.rows[&start])
+    }
+}
+
+/// Every budget with its usage in the current period
+pub fn statuses(budgets: &[Budget], store: &RequestStore, tz: &DisplayTimezone) -> Result<Vec<BudgetStatus>, String> {
+    let now = now_ms();
+    let state = load_state();
+    let mut cache = UsageCache {
+        store,
+        rows: HashMap::new(),
+    };
+
+    let mut statuses = Vec::new();
+    for budget in budgets {
+        let (period_start, period_end) = period_bounds(&budget.period, now, tz);
+        let rows: Vec<&RouteUsage> = cache
+            .rows(period_start, period_end)?
+            .iter()
+            .filter(|row| matches(budget, row))
+            .collect();
+        let used = measure(budget, &rows);
+        statuses.push(BudgetStatus {
+            budget: budget.clone(),
+            period_start,
+            period_end,
+            used,
+            fraction: fraction(used, budget.limit),
+            alert_level: state
+                .alerts
+                .get(&budget.id)
+                .filter(|mark| mark.period_start == period_start)
+                .map(|mark| mark.level)
+                .unwrap_or(0),
+            suspensions: state
+                .suspensions
+                .iter()
+                .filter(|s| s.budget_id == budget.id)
+                .cloned()
+                .collect(),
+        });
+    }
+    Ok(statuses)
+}
+
+/// API keys currently suspended as (management endpoint, key), left out of the
+/// config the proxy is started with
+pub fn suspended_api_keys() -> HashSet<(String, String)> {
+    let state = load_state();
+    let keys = load_keys();
+    state
+        .suspensions
+        .iter()
+        .filter(|s| s.kind == KIND_API_KEY)
+        .filter_map(|s| keys.get(&s.target))
+        .map(|k| (k.endpoint.clone(), k.api_key.clone()))
+        .collect()
+}
+
+fn auth_dir() -> Option<std::path::PathBuf> {
+    dirs::home_dir().map(|home| home.join(".cli-proxy-api"))
+}
+
+/// Disable the auth files logged in as `email`; returns their file names
+fn suspend_auth_files(email: &str) -> Vec<String> {
+    let Some(dir) = auth_dir() else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+
+    let mut suspended = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let file_email = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str::<Value>(&data).ok())
+            .and_then(|json| json.get("email").and_then(|v| v.as_str()).map(str::to_string));
+        if !file_email.is_some_and(|e| e.eq_ignore_ascii_case(email)) {
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|n| n.to_str()).map(str::to_string) else {
+            continue;
+        };
+        match std::fs::rename(&path, dir.join(format!("{}.disabled", name))) {
+            Ok(()) => suspended.push(name),
+            Err(e) => eprintln!("[Budgets] Failed to disable auth file {}: {}", name, e),
+        }
+    }
+    suspended
+}
+
+fn restore_auth_file(name: &str) -> Result<(), String> {
+    let dir = auth_dir().ok_or("Could not find home directory")?;
+    let enabled_path = dir.join(name);
+    let disabled_path = dir.join(format!("{}.disabled", name));
+    if disabled_path.exists() && !enabled_path.exists() {
+        std::fs::rename(&disabled_path, &enabled_path).map_err(|e| format!("Failed to enable {}: {}", name, e))?;
+    }
+    Ok(())
+}
+
+/// Management API endpoint holding the API keys of a provider (as stored in suspensions)
+fn key_endpoint(provider: &str) -> Result<&'static str, String> {
+    match provider {
+        "claude" => Ok("claude-api-key"),
+        "gemini" => Ok("gemini-api-key"),
+        "codex" | "openai" => Ok("codex-api-key"),
+        "vertex" => Ok("vertex-api-key"),
+        other => Err(format!("Suspending {} API keys is not supported", other)),
+    }
+}
+
+/// The configured key whose masked form is `masked`, preferring keys of `provider`;
+/// returns the key and its management API endpoint. Vertex keys aren't in the config
+/// (see `find_vertex_key`); OpenAI-compatible provider keys can't be suspended.
+fn find_api_key(config: &AppConfig, masked: &str, provider: &str) -> Result<(String, &'static str), String> {
+    let lists: [(&str, &str, Vec<&String>); 3] = [
+        ("claude", "claude-api-key", config.claude_api_keys.iter().map(|k| &k.api_key).collect()),
+        ("gemini", "gemini-api-key", config.gemini_api_keys.iter().map(|k| &k.api_key).collect()),
+        ("openai", "codex-api-key", config.codex_api_keys.iter().map(|k| &k.api_key).collect()),
+    ];
+    let mut candidates = lists
+        .iter()
+        .flat_map(|(p, endpoint, keys)| keys.iter().map(move |k| (*p, *endpoint, *k)))
+        .filter(|(_, _, key)| mask_api_key(key) == masked);
+    if let Some(first) = candidates.clone().next() {
+        let (_, endpoint, key) = candidates.find(|(p, _, _)| *p == provider).unwrap_or(first);
+        return Ok((key.clone(), endpoint));
+    }
+    match config.amp_openai_providers.iter().find(|p| mask_api_key(&p.api_key) == masked) {
+        Some(p) => Err(format!("Suspending keys of OpenAI-compatible provider {} is not supported", p.name)),
+        None => Err("API key is not in ProxyPal's key lists".to_string()),
+    }
+}
+
+/// Vertex keys are added through the management API and only kept by the proxy;
+/// find the loaded one whose masked form is `masked`
+async fn find_vertex_key(port: u16, masked: &str) -> Option<String> {
+    fetch_key_list(port, "vertex-api-key")
+        .await
+        .ok()?
+        .iter()
+        .filter_map(entry_key)
+        .find(|key| mask_api_key(key) == masked)
+        .map(str::to_string)
+}
+
+async fn fetch_key_list(port: u16, endpoint: &str) -> Result<Vec<Value>, String> {
+    let response = build_management_client()
+        .get(get_management_url(port, endpoint))
+        .header("X-Management-Key", &get_management_key())
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch {}: {}", endpoint, e))?;
+    if !response.status().is_success() {
+        return Err(format!("Failed to fetch {}: {}", endpoint, response.status()));
+    }
+    let json: Value = response.json().await.map_err(|e| e.to_string())?;
+    Ok(json
+        .get(endpoint)
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default())
+}
+
+async fn put_key_list(port: u16, endpoint: &str, keys: &[Value]) -> Result<(), String> {
+    let response = build_management_client()
+        .put(get_management_url(port, endpoint))
+        .header("X-Management-Key", &get_management_key())
+        .json(keys)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to update {}: {}", endpoint, e))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(format!("Failed to update {}: {} - {}", endpoint, status, text));
+    }
+    Ok(())
+}
+
+fn entry_key(entry: &Value) -> Option<&str> {
+    entry.get("api-key").and_then(|v| v.as_str())
+}
+
+/// Remove a key from the running proxy (ProxyPal's config keeps it); returns its entry
+async fn suspend_api_key(port: u16, endpoint: &str, key: &str) -> Result<Value, String> {
+    let mut keys = fetch_key_list(port, endpoint).await?;
+    let index = keys
+        .iter()
+        .position(|entry| entry_key(entry) == Some(key))
+        .ok_or("API key is not loaded in the proxy")?;
+    let entry = keys.remove(index);
+    put_key_list(port, endpoint, &keys).await?;
+    Ok(entry)
+}
+
+/// Put a key back into the running proxy, and into ProxyPal's config if it was
+/// dropped there while suspended (e.g. the key list was edited in the meantime)
+async fn restore_api_key(app: &tauri::AppHandle, suspension: &BudgetSuspension) -> Result<(), String> {
+    let mut keys = load_keys();
+    let Some(SuspendedKey { endpoint, api_key, entry }) = keys.get(&suspension.target).cloned() else {
+        return Ok(());
+    };
+    let (running, port) = {
+        let state = app.state::<AppState>();
+        let status = state.proxy_status.lock().unwrap();
+        (status.running, status.port)
+    };
+
+    if running {
+        let mut loaded = fetch_key_list(port, &endpoint).await?;
+        if !loaded.iter().any(|k| entry_key(k) == Some(&api_key)) {
+            loaded.push(entry.clone());
+            put_key_list(port, &endpoint, &loaded).await?;
+        }
+    }
+
+    {
+        let state = app.state::<AppState>();
+        let mut config = state.config.lock().unwrap();
+        if restore_key_to_config(&mut config, &endpoint, &entry, &api_key)? {
+            save_config_to_file(&config)?;
+        }
+    }
+
+    keys.remove(&suspension.target);
+    save_keys(&keys)
+}
+
+fn restore_key_to_config(config: &mut AppConfig, endpoint: &str, entry: &Value, key: &str) -> Result<bool, String> {
+    let entries = Value::Array(vec![entry.clone()]);
+    macro_rules! restore {
+        ($list:expr, $ty:ty) => {{
+            if $list.iter().any(|k| k.api_key == key) {
+                return Ok(false);
+            }
+            $list.extend(convert_api_key_response::<$ty>(entries, endpoint)?);
+        }};
+    }
+    match endpoint {
+        "claude-api-key" => restore!(config.claude_api_keys, ClaudeApiKey),
+        "gemini-api-key" => restore!(config.gemini_api_keys, GeminiApiKey),
+        "codex-api-key" => restore!(config.codex_api_keys, CodexApiKey),
+        // Vertex keys are kept by the proxy only; putting them back there is enough
+        "vertex-api-key" => return Ok(false),
+        other => return Err(format!("Unsupported API key endpoint: {}", other)),
+    }
+    Ok(true)
+}
+
+async fn restore(app: &tauri::AppHandle, suspension: &BudgetSuspension) {
+    let result = match suspension.kind.as_str() {
+        KIND_AUTH_FILE => restore_auth_file(&suspension.target),
+        KIND_API_KEY => restore_api_key(app, suspension).await,
+        other => Err(format!("Unknown suspension kind: {}", other)),
+    };
+    match result {
+        Ok(()) => println!("[Budgets] Restored {} ({})", suspension.label, suspension.kind),
+        Err(e) => eprintln!("[Budgets] Failed to restore {}: {}", suspension.label, e),
+    }
+}
+
+/// Suspend the credentials behind `rows` that aren't suspended yet
+async fn enforce(
+    config: &AppConfig,
+    state: &mut BudgetState,
+    budget: &Budget,
+    rows: &[RouteUsage],
+    period_end: u64,
+    running_port: Option<u16>,
+) -> u32 {
+    let mut accounts: Vec<(&str, &str, bool)> = rows
+        .iter()
+        .filter_map(|row| {
+            let account = row.account.as_deref()?;
+            Some((account, row.provider.as_str(), row.api_key_index.is_some()))
+        })
+        .collect();
+    accounts.sort();
+    accounts.dedup();
+
+    let mut suspended = 0;
+    for (account, provider, is_api_key) in accounts {
+        let already = state
+            .suspensions
+            .iter()
+            .any(|s| s.budget_id == budget.id && s.label == account);
+        if already {
+            continue;
+        }
+
+        if !is_api_key {
+            let names = suspend_auth_files(account);
+            let found = !names.is_empty();
+            for name in names {
+                println!("[Budgets] '{}' exceeded: disabled auth file {}", budget.name, name);
+                state.suspensions.push(BudgetSuspension {
+                    budget_id: budget.id.clone(),
+                    kind: KIND_AUTH_FILE.to_string(),
+                    provider: provider.to_string(),
+                    target: name,
+                    label: account.to_string(),
+                    until: period_end,
+                    entry: None,
+                });
+                suspended += 1;
+            }
+            // Only keys outside ProxyPal's key lists (Vertex, OpenAI-compatible) aren't indexed
+            if found || account.contains('@') {
+                continue;
+            }
+        }
+
+        let Some(port) = running_port else {
+            continue;
+        };
+        let found = match find_api_key(config, account, provider) {
+            Ok(found) => Ok(found),
+            Err(e) => find_vertex_key(port, account)
+                .await
+                .map(|key| (key, "vertex-api-key"))
+                .ok_or(e),
+        };
+        let (key, endpoint) = match found {
+            Ok(found) => found,
+            Err(e) => {
+                eprintln!("[Budgets] Can't suspend API key {}: {}", account, e);
+                continue;
+            }
+        };
+        let entry = match suspend_api_key(port, endpoint, &key).await {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("[Budgets] Failed to suspend API key {}: {}", account, e);
+                continue;
+            }
+        };
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut keys = load_keys();
+        keys.insert(
+            id.clone(),
+            SuspendedKey {
+                endpoint: endpoint.to_string(),
+                api_key: key,
+                entry,
+            },
+        );
+        if let Err(e) = save_keys(&keys) {
+            eprintln!("[Budgets] Failed to save suspended API key {}: {}", account, e);
+        }
+        println!("[Budgets] '{}' exceeded: suspended API key {}", budget.name, account);
+        state.suspensions.push(BudgetSuspension {
+            budget_id: budget.id.clone(),
+            kind: KIND_API_KEY.to_string(),
+            provider: endpoint.trim_end_matches("-api-key").to_string(),
+            target: id,
+            label: account.to_string(),
+            until: period_end,
+            entry: None,
+        });
+        suspended += 1;
+    }
+    suspended
+}
+
+fn notify(app: &tauri::AppHandle, alert: &BudgetAlert) {
+    let unit = |value: f64| match alert.metric.as_str() {
+        "cost" => usd(value),
+        _ => format!("{} tokens", value.round() as u64),
+    };
+    let title = match alert.level {
+        LEVEL_EXCEEDED => format!("Budget exceeded: {}", alert.name),
+        _ => format!("Budget at {}%: {}", alert.level, alert.name),
+    };
+    let mut body = format!("{} of {} used this period", unit(alert.used), unit(alert.limit));
+    if alert.suspended > 0 {
+        body.push_str(&format!(
+            "; {} credential{} paused until the period resets",
+            alert.suspended,
+            if alert.suspended == 1 { "" } else { "s" }
+        ));
+    }
+
+    let _ = app.emit("budget-alert", alert.clone());
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        eprintln!("[Budgets] Failed to show notification: {}", e);
+    }
+}
+
+/// Evaluate every budget: raise alerts, suspend credentials for exceeded enforcing
+/// budgets, and restore suspensions whose period has reset
+pub async fn evaluate(app: tauri::AppHandle) {
+    let _guard = EVALUATING.lock().await;
+    let Some(store) = app.try_state::<RequestStore>() else {
+        return;
+    };
+    let (config, running_port) = {
+        let state = app.state::<AppState>();
+        let config = state.config.lock().unwrap().clone();
+        let status = state.proxy_status.lock().unwrap();
+        (config, status.running.then_some(status.port))
+    };
+    let tz = DisplayTimezone::from_setting(&config.display_timezone);
+    let now = now_ms();
+    let mut state = load_state();
+    let mut changed = false;
+
+    // Restore what expired, or what belongs to budgets that no longer enforce
+    let enforcing: HashSet<&str> = config
+        .budgets
+        .iter()
+        .filter(|b| b.enabled && b.enforce)
+        .map(|b| b.id.as_str())
+        .collect();
+    let (expired, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut state.suspensions)
+        .into_iter()
+        .partition(|s| s.until <= now || !enforcing.contains(s.budget_id.as_str()));
+    state.suspensions = kept;
+    for suspension in &expired {
+        restore(&app, suspension).await;
+        changed = true;
+    }
+
+    let mut cache = UsageCache {
+        store: store.inner(),
+        rows: HashMap::new(),
+    };
+    for budget in config.budgets.iter().filter(|b| b.enabled) {
+        let (period_start, period_end) = period_bounds(&budget.period, now, &tz);
+        let rows: Vec<RouteUsage> = match cache.rows(period_start, period_end) {
+            Ok(rows) => rows.iter().filter(|row| matches(budget, row)).cloned().collect(),
+            Err(e) => {
+                eprintln!("[Budgets] Failed to read usage: {}", e);
+                return;
+            }
+        };
+        let used = measure(budget, &rows.iter().collect::<Vec<_>>());
+        let fraction = fraction(used, budget.limit);
+        let level = if fraction >= 1.0 {
+            LEVEL_EXCEEDED
+        } else if fraction >= 0.8 {
+            LEVEL_WARNING
+        } else {
+            0
+        };
+
+        // A mark from an earlier period doesn't count; the new period starts from zero
+        let previous = state.alerts.get(&budget.id).copied();
+        let mark = previous
+            .filter(|mark| mark.period_start == period_start)
+            .unwrap_or(AlertMark {
+                period_start,
+                level: 0,
+            });
+
+        // Keep enforcing while exceeded, so credentials rotated in later are paused too
+        let suspended = if level == LEVEL_EXCEEDED && budget.enforce {
+            enforce(&config, &mut state, budget, &rows, period_end, running_port).await
+        } else {
+            0
+        };
+        changed |= suspended > 0;
+
+        if level > mark.level {
+            notify(
+                &app,
+                &BudgetAlert {
+                    budget_id: budget.id.clone(),
+                    name: budget.name.clone(),
+                    level,
+                    metric: budget.metric.clone(),
+                    used,
+                    limit: budget.limit,
+                    period_end,
+                    suspended,
+                },
+            );
+        }
+        if level > mark.level || previous.map(|m| m.period_start) != Some(period_start) {
+            state.alerts.insert(
+                budget.id.clone(),
+                AlertMark {
+                    period_start,
+                    level: level.max(mark.level),
+                },
+            );
+            changed = true;
+        }
+    }
+
+    // Forget alert marks of deleted budgets
+    let before = state.alerts.len();
+    state
+        .alerts
+        .retain(|id, _| config.budgets.iter().any(|b| &b.id == id));
+    changed |= state.alerts.len() != before;
+
+    if changed {
+        if let Err(e) = save_state(&state) {
+            eprintln!("[Budgets] Failed to save budget state: {}", e);
+        }
+    }
+}
@@ -90,12 +90,15 @@ struct RunningTunnel {
 
 pub struct CloudflareManager {
     tunnels: Arc<Mutex<HashMap<String, RunningTunnel>>>,
+    /// Last status emitted per config id
+    statuses: Arc<Mutex<HashMap<String, String>>>,
 }
 
 impl CloudflareManager {
     pub fn new() -> Self {
         Self {
             tunnels: Arc::new(Mutex::new(HashMap::new())),
+            statuses: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -110,7 +113,9 @@ impl CloudflareManager {
         let notify_clone = notify_stop.clone();
         let config_clone = config.clone();
         
+        let statuses = self.statuses.clone();
         let emit_status = move |status: &str, msg: Option<String>, url: Option<String>| {
+            statuses.lock().unwrap().insert(config_clone.id.clone(), status.to_string());
             let _ = app.emit("cloudflare-status-changed", CloudflareStatusUpdate {
                 id: config_clone.id.clone(),
                 status: status.to_string(),
@@ -329,6 +334,12 @@ impl CloudflareManager {
         tunnels.clear();
     }
 
+    /// Last status reported by each tunnel started this session
+    /// ("connecting", "connected", "reconnecting", "error" or "disconnected")
+    pub fn statuses(&self) -> HashMap<String, String> {
+        self.statuses.lock().unwrap().clone()
+    }
+
     #[allow(dead_code)]
     pub fn get_status(&self, id: &str) -> String {
        let tunnels = self.tunnels.lock().unwrap();
@@ -0,0 +1,64 @@
+use tauri::{command, AppHandle, State};
+use crate::alerts;
+use crate::config::save_config_to_file;
+use crate::state::AppState;
+use crate::types::{Alert, AlertRule};
+
+#[command]
+pub fn get_alert_rules(state: State<'_, AppState>) -> Vec<AlertRule> {
+    state.config.lock().unwrap().alert_rules.clone()
+}
+
+#[command]
+pub fn save_alert_rule(app: AppHandle, state: State<'_, AppState>, mut rule: AlertRule) -> Result<Vec<AlertRule>, String> {
+    alerts::validate(&rule)?;
+    if rule.id.is_empty() {
+        rule.id = uuid::Uuid::new_v4().to_string();
+    }
+    rule.provider = rule.provider.trim().to_string();
+
+    let saved = {
+        let mut config = state.config.lock().unwrap();
+        if let Some(idx) = config.alert_rules.iter().position(|r| r.id == rule.id) {
+            config.alert_rules[idx] = rule;
+        } else {
+            config.alert_rules.push(rule);
+        }
+        save_config_to_file(&config)?;
+        config.alert_rules.clone()
+    };
+
+    // Check the new rule right away rather than on the next tick
+    if let Err(e) = alerts::evaluate(&app) {
+        eprintln!("[Alerts] Evaluation failed: {}", e);
+    }
+    Ok(saved)
+}
+
+#[command]
+pub fn delete_alert_rule(state: State<'_, AppState>, id: String) -> Result<Vec<AlertRule>, String> {
+    let saved = {
+        let mut config = state.config.lock().unwrap();
+        config.alert_rules.retain(|r| r.id != id);
+        save_config_to_file(&config)?;
+        config.alert_rules.clone()
+    };
+    alerts::forget_rule(&id)?;
+    Ok(saved)
+}
+
+/// Recently raised alerts, most recent first
+#[command]
+pub fn get_alerts() -> Vec<Alert> {
+    alerts::recent()
+}
+
+#[command]
+pub fn acknowledge_alert(id: String) -> Result<Vec<Alert>, String> {
+    alerts::acknowledge(Some(&id))
+}
+
+#[command]
+pub fn acknowledge_all_alerts() -> Result<Vec<Alert>, String> {
+    alerts::acknowledge(None)
+}
@@ -0,0 +1,57 @@
+use tauri::{command, AppHandle, State};
+use crate::budgets;
+use crate::config::save_config_to_file;
+use crate::request_store::RequestStore;
+use crate::state::AppState;
+use crate::timezone::DisplayTimezone;
+use crate::types::{Budget, BudgetStatus};
+
+#[command]
+pub fn get_budgets(state: State<'_, AppState>) -> Vec<Budget> {
+    state.config.lock().unwrap().budgets.clone()
+}
+
+#[command]
+pub fn save_budget(app: AppHandle, state: State<'_, AppState>, mut budget: Budget) -> Result<Vec<Budget>, String> {
+    budgets::validate(&budget)?;
+    if budget.id.is_empty() {
+        budget.id = uuid::Uuid::new_v4().to_string();
+    }
+    budget.target = budget.target.trim().to_string();
+
+    let saved = {
+        let mut config = state.config.lock().unwrap();
+        if let Some(idx) = config.budgets.iter().position(|b| b.id == budget.id) {
+            config.budgets[idx] = budget;
+        } else {
+            config.budgets.push(budget);
+        }
+        save_config_to_file(&config)?;
+        config.budgets.clone()
+    };
+
+    // Apply the change now (alerts for a lowered limit, restores for a lifted one)
+    tauri::async_runtime::spawn(budgets::evaluate(app));
+    Ok(saved)
+}
+
+/// Delete a budget; anything it suspended is restored
+#[command]
+pub fn delete_budget(app: AppHandle, state: State<'_, AppState>, id: String) -> Result<Vec<Budget>, String> {
+    let saved = {
+        let mut config = state.config.lock().unwrap();
+        config.budgets.retain(|b| b.id != id);
+        save_config_to_file(&config)?;
+        config.budgets.clone()
+    };
+    tauri::async_runtime::spawn(budgets::evaluate(app));
+    Ok(saved)
+}
+
+/// Usage of every budget in its current period, with raised alerts and suspensions
+#[command]
+pub fn get_budget_status(state: State<'_, AppState>, store: State<'_, RequestStore>) -> Result<Vec<BudgetStatus>, String> {
+    let config = state.config.lock().unwrap().clone();
+    let tz = DisplayTimezone::from_setting(&config.display_timezone);
+    budgets::statuses(&config.budgets, &store, &tz)
+}
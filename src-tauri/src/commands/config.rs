@@ -29,6 +29,8 @@ pub fn save_config(state: State<AppState>, config: AppConfig) -> Result<(), Stri
         }
     }
 
+    crate::otlp::validate(&config.otlp)?;
+
     let mut current_config = state.config.lock().unwrap();
     *current_config = config.clone();
     save_config_to_file(&config)?;
@@ -0,0 +1,30 @@
+use tauri::{command, AppHandle, Manager, State};
+use crate::digest;
+use crate::request_store::RequestStore;
+use crate::state::AppState;
+use crate::timezone::{now_ms, DisplayTimezone};
+use crate::types::{DigestFiles, ExportRange};
+
+/// Write a usage digest for `range` (default: the last 7 days) to the reports folder
+#[command]
+pub async fn generate_usage_report(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    range: ExportRange,
+) -> Result<DigestFiles, String> {
+    const WEEK_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+
+    let config = state.config.lock().unwrap().clone();
+    let tz = DisplayTimezone::from_setting(&config.display_timezone);
+    let to = range.to.unwrap_or_else(now_ms);
+    let from = range.from.unwrap_or(to.saturating_sub(WEEK_MS));
+
+    // Queries the request store and writes files; keep it off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        let store = app.state::<RequestStore>();
+        let report = digest::build(&store, &crate::load_aggregate(), &config.subscriptions, from, to, &tz)?;
+        digest::write(report, &digest::reports_dir(&config.digest), &tz)
+    })
+    .await
+    .map_err(|e| format!("Failed to generate report: {}", e))?
+}
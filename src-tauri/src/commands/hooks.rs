@@ -0,0 +1,49 @@
+use tauri::{command, State};
+use crate::config::save_config_to_file;
+use crate::hooks;
+use crate::state::AppState;
+use crate::types::{Hook, HookDelivery};
+
+#[command]
+pub fn get_hooks(state: State<'_, AppState>) -> Vec<Hook> {
+    state.config.lock().unwrap().hooks.clone()
+}
+
+#[command]
+pub fn save_hook(state: State<'_, AppState>, mut hook: Hook) -> Result<Vec<Hook>, String> {
+    hooks::validate(&hook)?;
+    if hook.id.is_empty() {
+        hook.id = uuid::Uuid::new_v4().to_string();
+    }
+    hook.url = hook.url.trim().to_string();
+
+    let mut config = state.config.lock().unwrap();
+    if let Some(idx) = config.hooks.iter().position(|h| h.id == hook.id) {
+        config.hooks[idx] = hook;
+    } else {
+        config.hooks.push(hook);
+    }
+    save_config_to_file(&config)?;
+    Ok(config.hooks.clone())
+}
+
+#[command]
+pub fn delete_hook(state: State<'_, AppState>, id: String) -> Result<Vec<Hook>, String> {
+    let mut config = state.config.lock().unwrap();
+    config.hooks.retain(|h| h.id != id);
+    save_config_to_file(&config)?;
+    Ok(config.hooks.clone())
+}
+
+/// Recent delivery results, most recent first; all hooks when `hook_id` is None
+#[command]
+pub fn get_hook_deliveries(hook_id: Option<String>) -> Vec<HookDelivery> {
+    hooks::deliveries(hook_id.as_deref())
+}
+
+/// Run a hook (saved or not) once with a "test" event
+#[command]
+pub async fn test_hook(hook: Hook) -> Result<HookDelivery, String> {
+    hooks::validate(&hook)?;
+    Ok(hooks::test(&hook).await)
+}
@@ -3,3 +3,8 @@
 pub mod config;
 pub mod ssh;
 pub mod cloudflare;
+pub mod subscriptions;
+pub mod budgets;
+pub mod digest;
+pub mod alerts;
+pub mod hooks;
@@ -0,0 +1,72 @@
+use tauri::{command, State};
+use crate::config::save_config_to_file;
+use crate::request_store::RequestStore;
+use crate::state::AppState;
+use crate::subscriptions;
+use crate::timezone::{now_ms, DisplayTimezone};
+use crate::types::{Subscription, SubscriptionReport};
+
+#[command]
+pub fn get_subscriptions(state: State<'_, AppState>) -> Vec<Subscription> {
+    state.config.lock().unwrap().subscriptions.clone()
+}
+
+#[command]
+pub fn save_subscription(state: State<'_, AppState>, mut subscription: Subscription) -> Result<Vec<Subscription>, String> {
+    subscriptions::validate(&subscription)?;
+    if subscription.id.is_empty() {
+        subscription.id = uuid::Uuid::new_v4().to_string();
+    }
+    subscription.accounts.retain(|a| !a.trim().is_empty());
+
+    let mut config = state.config.lock().unwrap();
+    if let Some(idx) = config.subscriptions.iter().position(|s| s.id == subscription.id) {
+        config.subscriptions[idx] = subscription;
+    } else {
+        config.subscriptions.push(subscription);
+    }
+
+    save_config_to_file(&config)?;
+    Ok(config.subscriptions.clone())
+}
+
+#[command]
+pub fn delete_subscription(state: State<'_, AppState>, id: String) -> Result<Vec<Subscription>, String> {
+    let mut config = state.config.lock().unwrap();
+    config.subscriptions.retain(|s| s.id != id);
+    save_config_to_file(&config)?;
+    Ok(config.subscriptions.clone())
+}
+
+fn report_for(state: &AppState, store: &RequestStore, month: Option<String>) -> Result<SubscriptionReport, String> {
+    let config = state.config.lock().unwrap().clone();
+    let tz = DisplayTimezone::from_setting(&config.display_timezone);
+    let month = month.unwrap_or_else(|| tz.month_label(now_ms()));
+    subscriptions::build_report(&config.subscriptions, store, &month, &tz)
+}
+
+/// Savings report for the billing periods starting in `month` ("YYYY-MM"; default: this month)
+#[command]
+pub fn get_subscription_report(
+    state: State<'_, AppState>,
+    store: State<'_, RequestStore>,
+    month: Option<String>,
+) -> Result<SubscriptionReport, String> {
+    report_for(&state, &store, month)
+}
+
+/// The savings report rendered as "markdown" or "csv"
+#[command]
+pub fn export_subscription_report(
+    state: State<'_, AppState>,
+    store: State<'_, RequestStore>,
+    month: Option<String>,
+    format: String,
+) -> Result<String, String> {
+    let report = report_for(&state, &store, month)?;
+    match format.to_ascii_lowercase().as_str() {
+        "markdown" | "md" => Ok(subscriptions::to_markdown(&report)),
+        "csv" => Ok(subscriptions::to_csv(&report)),
+        other => Err(format!("Unsupported report format: {}", other)),
+    }
+}
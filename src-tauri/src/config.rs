@@ -1,8 +1,12 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use serde::{Deserialize, Serialize};
 
 use crate::types::{
-    amp::generate_uuid, cloudflare::CloudflareConfig, AmpModelMapping, AmpOpenAIProvider,
-    ClaudeApiKey, CodexApiKey, CopilotConfig, GeminiApiKey, SshConfig, VertexApiKey,
+    amp::generate_uuid, cloudflare::CloudflareConfig, AlertRule, AmpModelMapping, AmpOpenAIProvider,
+    Budget, ClaudeApiKey, CodexApiKey, CopilotConfig, DigestSettings, GeminiApiKey, Hook,
+    MetricsSettings, OtlpSettings, SshConfig, Subscription, TokenRefreshSettings, TrackedEndpoint,
+    UsageRetention, VertexApiKey,
 };
 
 /// App configuration persisted to config.json
@@ -82,6 +86,32 @@ pub struct AppConfig {
     pub cloudflare_configs: Vec<CloudflareConfig>,
     #[serde(default = "default_disable_control_panel")]
     pub disable_control_panel: bool,
+    #[serde(default)]
+    pub custom_tracked_endpoints: Vec<TrackedEndpoint>,
+    #[serde(default)]
+    pub usage_retention: UsageRetention,
+    /// IANA time zone for day/hour buckets (e.g. "Europe/Berlin"); empty = system time zone
+    #[serde(default)]
+    pub display_timezone: String,
+    /// Stop recording requests entirely (nothing is written to history or the request store)
+    #[serde(default)]
+    pub privacy_mode: bool,
+    #[serde(default)]
+    pub subscriptions: Vec<Subscription>,
+    #[serde(default)]
+    pub budgets: Vec<Budget>,
+    #[serde(default)]
+    pub metrics: MetricsSettings,
+    #[serde(default)]
+    pub otlp: OtlpSettings,
+    #[serde(default)]
+    pub digest: DigestSettings,
+    #[serde(default)]
+    pub alert_rules: Vec<AlertRule>,
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
+    #[serde(default)]
+    pub token_refresh: TokenRefreshSettings,
 }
 
 fn default_disable_control_panel() -> bool {
@@ -161,6 +191,18 @@ impl Default for AppConfig {
             ssh_configs: Vec::new(),
             cloudflare_configs: Vec::new(),
             disable_control_panel: true,
+            custom_tracked_endpoints: Vec::new(),
+            usage_retention: UsageRetention::default(),
+            display_timezone: String::new(),
+            privacy_mode: false,
+            subscriptions: Vec::new(),
+            budgets: Vec::new(),
+            metrics: MetricsSettings::default(),
+            otlp: OtlpSettings::default(),
+            digest: DigestSettings::default(),
+            alert_rules: Vec::new(),
+            hooks: Vec::new(),
+            token_refresh: TokenRefreshSettings::default(),
         }
     }
 }
@@ -207,6 +249,46 @@ pub fn get_aggregate_path() -> std::path::PathBuf {
     get_proxypal_config_dir().join("aggregate.json")
 }
 
+/// SQLite request log database path
+pub fn get_requests_db_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("requests.db")
+}
+
+/// Last usage export taken from CLIProxyAPI, re-imported when the proxy starts
+pub fn get_usage_snapshot_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("usage-snapshot.json")
+}
+
+/// User pricing catalog path (overrides the bundled model prices)
+pub fn get_pricing_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("pricing.json")
+}
+
+/// Budget alert levels and suspended credentials
+pub fn get_budget_state_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("budget-state.json")
+}
+
+/// API keys suspended by budgets, with the entries needed to restore them (owner-only)
+pub fn get_budget_keys_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("budget-keys.json")
+}
+
+/// Raised alerts and when each rule last fired
+pub fn get_alerts_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("alerts.json")
+}
+
+/// Default folder for scheduled usage digests
+pub fn get_reports_dir() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("reports")
+}
+
+/// Log watcher cursor file path (position in CLIProxyAPI's main.log)
+pub fn get_log_cursor_path() -> std::path::PathBuf {
+    get_proxypal_config_dir().join("log-cursor.json")
+}
+
 /// Load config from file
 pub fn load_config() -> AppConfig {
     let path = get_config_path();
@@ -245,6 +327,15 @@ pub fn load_config() -> AppConfig {
     AppConfig::default()
 }
 
+/// Bumped on every successful `save_config_to_file`
+static CONFIG_REVISION: AtomicU64 = AtomicU64::new(0);
+
+/// Changes whenever the config file is saved, so long-running tasks that built
+/// state from it (like the log watcher) know to rebuild
+pub fn config_revision() -> u64 {
+    CONFIG_REVISION.load(Ordering::SeqCst)
+}
+
 /// Save config to file
 /// Uses atomic write (write to temp file then rename) to prevent corruption
 pub fn save_config_to_file(config: &AppConfig) -> Result<(), String> {
@@ -299,6 +390,7 @@ pub fn save_config_to_file(config: &AppConfig) -> Result<(), String> {
         .map_err(|e| format!("Failed to rename temp file to config: {}", e))?;
 
     eprintln!("[ProxyPal] Config saved successfully to: {:?}", path);
+    CONFIG_REVISION.fetch_add(1, Ordering::SeqCst);
 
     Ok(())
 }
@@ -0,0 +1,516 @@
+//! Usage digests: a summary of a day or week of usage written as Markdown and
+//! self-contained HTML.
+//!
+//! A digest covers `[from, to)` and compares it with the period of equal length
+//! right before it. Totals, models, accounts and errors come from the request
+//! store; latency from the aggregate's per-day histograms; savings from the
+//! subscriptions, priced like the savings report with each monthly fee
+//! prorated to the period's length.
+//!
+//! When enabled, the scheduler writes a digest for each completed day or week
+//! (in the display time zone) once, skipping periods whose file already exists.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+use crate::config::get_reports_dir;
+use crate::request_store::RequestStore;
+use crate::state::AppState;
+use crate::export::{markdown_cell, usd};
+use crate::timezone::{now_ms, period_bounds, DisplayTimezone};
+use crate::types::{
+    Aggregate, DigestAccount, DigestErrorCount, DigestFiles, DigestModel, DigestSavings, DigestSettings, DigestTotals,
+    ErrorSpike, LatencyChange, LatencyHistogram, Subscription, UsageDigest, UsageSeriesPoint,
+};
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+/// Average month length, for prorating monthly fees
+const MONTH_MS: f64 = 365.25 / 12.0 * DAY_MS as f64;
+const TOP_MODELS: usize = 10;
+const TOP_ACCOUNTS: usize = 20;
+/// A day is a spike when a category fails at least this often...
+const SPIKE_MIN_FAILURES: u64 = 5;
+/// ...and this many times its average daily count in the prior period
+const SPIKE_FACTOR: f64 = 3.0;
+
+fn totals(points: &[UsageSeriesPoint]) -> DigestTotals {
+    let mut totals = DigestTotals::default();
+    for p in points {
+        totals.requests += p.requests;
+        totals.success_count += p.success_count;
+        totals.input_tokens += p.input_tokens;
+        totals.output_tokens += p.output_tokens;
+        totals.cached_tokens += p.cached_tokens;
+        totals.cost_usd += p.cost_usd;
+    }
+    totals.failure_count = totals.requests.saturating_sub(totals.success_count);
+    totals
+}
+
+/// Per-provider latency over the aggregate's days in `[first_day, last_day]`
+fn latency_between(aggregate: &Aggregate, first_day: &str, last_day: &str) -> HashMap<String, LatencyHistogram> {
+    let mut merged: HashMap<String, LatencyHistogram> = HashMap::new();
+    for (_, stats) in aggregate
+        .latency_by_day
+        .range(first_day.to_string()..=last_day.to_string())
+    {
+        for (provider, histogram) in &stats.by_provider {
+            merged.entry(provider.clone()).or_default().merge(histogram);
+        }
+    }
+    merged
+}
+
+/// Build the digest for `[from, to)`
+pub fn build(
+    store: &RequestStore,
+    aggregate: &Aggregate,
+    subscriptions: &[Subscription],
+    from: u64,
+    to: u64,
+    tz: &DisplayTimezone,
+) -> Result<UsageDigest, String> {
+    if from >= to {
+        return Err("The report range is empty".to_string());
+    }
+    let prev_from = from.saturating_sub(to - from);
+    let first_day = tz.day_label(from);
+    let last_day = tz.day_label(to - 1);
+
+    // Totals and models
+    let current = totals(&store.usage_series("all", "all", from, to, tz)?);
+    let previous = totals(&store.usage_series("all", "all", prev_from, from, tz)?);
+
+    let previous_models: HashMap<String, u64> = store
+        .usage_series("model", "all", prev_from, from, tz)?
+        .into_iter()
+        .map(|p| (p.group, p.requests))
+        .collect();
+    let mut top_models: Vec<DigestModel> = store
+        .usage_series("model", "all", from, to, tz)?
+        .into_iter()
+        .map(|p| DigestModel {
+            previous_requests: previous_models.get(&p.group).copied().unwrap_or(0),
+            model: p.group,
+            requests: p.requests,
+            input_tokens: p.input_tokens,
+            output_tokens: p.output_tokens,
+            cost_usd: p.cost_usd,
+        })
+        .collect();
+    top_models.sort_by(|a, b| b.requests.cmp(&a.requests).then_with(|| a.model.cmp(&b.model)));
+    top_models.truncate(TOP_MODELS);
+
+    // Accounts
+    let costs = store.account_costs(from, to)?;
+    let mut accounts: Vec<DigestAccount> = store
+        .account_usage(Some(from), Some(to))?
+        .into_iter()
+        .map(|a| DigestAccount {
+            cost_usd: costs.get(&a.account.to_lowercase()).map(|c| c.cost_usd).unwrap_or(0.0),
+            account: a.account,
+            provider: a.provider,
+            requests: a.requests,
+            failure_count: a.failure_count,
+            input_tokens: a.input_tokens,
+            output_tokens: a.output_tokens,
+        })
+        .collect();
+    accounts.sort_by(|a, b| b.requests.cmp(&a.requests));
+    accounts.truncate(TOP_ACCOUNTS);
+
+    // Errors by category, and days on which a category spiked
+    let count_by_category = |from: u64, to: u64| -> Result<BTreeMap<String, u64>, String> {
+        let mut counts = BTreeMap::new();
+        for p in store.error_series("provider", "all", Some(from), Some(to), tz)? {
+            *counts.entry(p.category).or_insert(0) += p.count;
+        }
+        Ok(counts)
+    };
+    let current_errors = count_by_category(from, to)?;
+    let previous_errors = count_by_category(prev_from, from)?;
+    let mut errors: Vec<DigestErrorCount> = current_errors
+        .iter()
+        .map(|(category, count)| DigestErrorCount {
+            category: category.clone(),
+            count: *count,
+            previous_count: previous_errors.get(category).copied().unwrap_or(0),
+        })
+        .collect();
+    errors.sort_by(|a, b| b.count.cmp(&a.count));
+
+    let period_days = ((to - from) as f64 / DAY_MS as f64).max(1.0);
+    let mut daily: BTreeMap<(String, String), u64> = BTreeMap::new();
+    for p in store.error_series("provider", "day", Some(from), Some(to), tz)? {
+        *daily.entry((p.label, p.category)).or_insert(0) += p.count;
+    }
+    let mut error_spikes: Vec<ErrorSpike> = daily
+        .into_iter()
+        .filter_map(|((day, category), count)| {
+            let baseline = previous_errors.get(&category).copied().unwrap_or(0) as f64 / period_days;
+            (count >= SPIKE_MIN_FAILURES && count as f64 >= SPIKE_FACTOR * baseline.max(1.0)).then_some(ErrorSpike {
+                day,
+                category,
+                count,
+                baseline_per_day: baseline,
+            })
+        })
+        .collect();
+    error_spikes.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.day.cmp(&b.day)));
+
+    // Latency vs. the prior period
+    let current_latency = latency_between(aggregate, &first_day, &last_day);
+    let previous_latency = latency_between(aggregate, &tz.day_label(prev_from), &tz.day_label(from - 1));
+    let mut latency: Vec<LatencyChange> = current_latency
+        .iter()
+        .filter(|(provider, h)| h.count > 0 && !provider.is_empty() && *provider != "unknown")
+        .map(|(provider, h)| {
+            let before = previous_latency.get(provider).filter(|h| h.count > 0);
+            LatencyChange {
+                provider: provider.clone(),
+                count: h.count,
+                p50_ms: h.percentile(50.0),
+                p90_ms: h.percentile(90.0),
+                previous_p50_ms: before.map(|h| h.percentile(50.0)),
+                previous_p90_ms: before.map(|h| h.percentile(90.0)),
+            }
+        })
+        .collect();
+    latency.sort_by(|a, b| b.count.cmp(&a.count));
+
+    // Savings
+    let prorate = (to - from) as f64 / MONTH_MS;
+    let mut savings: Vec<DigestSavings> = Vec::new();
+    for sub in subscriptions {
+        // Priced like the savings report, so the two can't disagree
+        let api_cost_usd: f64 = crate::subscriptions::covered_costs(sub, subscriptions, store, from, to)?
+            .iter()
+            .map(|(_, c)| c.cost_usd)
+            .sum();
+        let fee_usd = sub.monthly_fee_usd * prorate;
+        savings.push(DigestSavings {
+            provider: sub.provider.clone(),
+            plan_name: sub.plan_name.clone(),
+            api_cost_usd,
+            fee_usd,
+            savings_usd: api_cost_usd - fee_usd,
+        });
+    }
+    let total_savings_usd = savings.iter().map(|s| s.savings_usd).sum();
+
+    Ok(UsageDigest {
+        from,
+        to,
+        label: if first_day == last_day {
+            first_day
+        } else {
+            format!("{} – {}", first_day, last_day)
+        },
+        generated_at: now_ms(),
+        totals: current,
+        previous,
+        top_models,
+        accounts,
+        errors,
+        error_spikes,
+        latency,
+        savings,
+        total_savings_usd,
+    })
+}
+
+/// Relative change, e.g. "+12%"; "new" when there was nothing before
+fn change(current: f64, previous: f64) -> String {
+    if previous == 0.0 {
+        if current == 0.0 { "–".to_string() } else { "new".to_string() }
+    } else {
+        format!("{:+.0}%", (current - previous) / previous * 100.0)
+    }
+}
+
+fn opt_ms(value: Option<u64>) -> String {
+    value.map(|v| format!("{} ms", v)).unwrap_or_else(|| "–".to_string())
+}
+
+/// The digest as rows of cells per section, shared by the Markdown and HTML renderers
+struct Section {
+    title: &'static str,
+    header: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+    empty: &'static str,
+}
+
+fn sections(d: &UsageDigest) -> Vec<Section> {
+    let (t, p) = (&d.totals, &d.previous);
+    let tokens = |t: &DigestTotals| t.input_tokens + t.output_tokens;
+    let failure_rate = |t: &DigestTotals| {
+        if t.requests == 0 { 0.0 } else { t.failure_count as f64 / t.requests as f64 * 100.0 }
+    };
+
+    let mut sections = vec![Section {
+        title: "Totals",
+        header: vec!["", "This period", "Prior period", "Change"],
+        rows: vec![
+            vec!["Requests".into(), t.requests.to_string(), p.requests.to_string(), change(t.requests as f64, p.requests as f64)],
+            vec!["Failed requests".into(), t.failure_count.to_string(), p.failure_count.to_string(), change(t.failure_count as f64, p.failure_count as f64)],
+            vec!["Failure rate".into(), format!("{:.1}%", failure_rate(t)), format!("{:.1}%", failure_rate(p)), String::new()],
+            vec!["Tokens (in + out)".into(), tokens(t).to_string(), tokens(p).to_string(), change(tokens(t) as f64, tokens(p) as f64)],
+            vec!["Cached tokens".into(), t.cached_tokens.to_string(), p.cached_tokens.to_string(), change(t.cached_tokens as f64, p.cached_tokens as f64)],
+            vec!["Estimated cost".into(), usd(t.cost_usd), usd(p.cost_usd), change(t.cost_usd, p.cost_usd)],
+        ],
+        empty: "",
+    }];
+
+    sections.push(Section {
+        title: "Top models",
+        header: vec!["Model", "Requests", "Change", "Tokens in", "Tokens out", "Estimated cost"],
+        rows: d
+            .top_models
+            .iter()
+            .map(|m| {
+                vec![
+                    m.model.clone(),
+                    m.requests.to_string(),
+                    change(m.requests as f64, m.previous_requests as f64),
+                    m.input_tokens.to_string(),
+                    m.output_tokens.to_string(),
+                    usd(m.cost_usd),
+                ]
+            })
+            .collect(),
+        empty: "No requests in this period.",
+    });
+
+    sections.push(Section {
+        title: "Accounts",
+        header: vec!["Account", "Provider", "Requests", "Failed", "Tokens in", "Tokens out", "Estimated cost"],
+        rows: d
+            .accounts
+            .iter()
+            .map(|a| {
+                vec![
+                    a.account.clone(),
+                    a.provider.clone(),
+                    a.requests.to_string(),
+                    a.failure_count.to_string(),
+                    a.input_tokens.to_string(),
+                    a.output_tokens.to_string(),
+                    usd(a.cost_usd),
+                ]
+            })
+            .collect(),
+        empty: "No account usage recorded.",
+    });
+
+    sections.push(Section {
+        title: "Errors",
+        header: vec!["Category", "Count", "Prior period", "Change"],
+        rows: d
+            .errors
+            .iter()
+            .map(|e| {
+                vec![
+                    e.category.clone(),
+                    e.count.to_string(),
+                    e.previous_count.to_string(),
+                    change(e.count as f64, e.previous_count as f64),
+                ]
+            })
+            .collect(),
+        empty: "No errors.",
+    });
+
+    sections.push(Section {
+        title: "Error spikes",
+        header: vec!["Day", "Category", "Failures", "Usual per day"],
+        rows: d
+            .error_spikes
+            .iter()
+            .map(|s| vec![s.day.clone(), s.category.clone(), s.count.to_string(), format!("{:.1}", s.baseline_per_day)])
+            .collect(),
+        empty: "No error spikes.",
+    });
+
+    sections.push(Section {
+        title: "Latency",
+        header: vec!["Provider", "Requests", "p50", "Prior p50", "p90", "Prior p90"],
+        rows: d
+            .latency
+            .iter()
+            .map(|l| {
+                vec![
+                    l.provider.clone(),
+                    l.count.to_string(),
+                    format!("{} ms", l.p50_ms),
+                    opt_ms(l.previous_p50_ms),
+                    format!("{} ms", l.p90_ms),
+                    opt_ms(l.previous_p90_ms),
+                ]
+            })
+            .collect(),
+        empty: "No latency data for this period.",
+    });
+
+    sections.push(Section {
+        title: "Estimated savings",
+        header: vec!["Provider", "Plan", "API-equivalent cost", "Fee (prorated)", "Savings"],
+        rows: d
+            .savings
+            .iter()
+            .map(|s| {
+                vec![s.provider.clone(), s.plan_name.clone(), usd(s.api_cost_usd), usd(s.fee_usd), usd(s.savings_usd)]
+            })
+            .collect(),
+        empty: "No subscriptions recorded.",
+    });
+
+    sections
+}
+
+/// Render a digest as Markdown
+pub fn to_markdown(d: &UsageDigest) -> String {
+    let mut out = format!("# ProxyPal usage digest — {}\n\n", d.label);
+    if !d.savings.is_empty() {
+        out.push_str(&format!("**Estimated savings from subscriptions:** {}\n\n", usd(d.total_savings_usd)));
+    }
+    for section in sections(d) {
+        out.push_str(&format!("## {}\n\n", section.title));
+        if section.rows.is_empty() {
+            out.push_str(&format!("_{}_\n\n", section.empty));
+            continue;
+        }
+        out.push_str(&format!("| {} |\n", section.header.join(" | ")));
+        let align: Vec<&str> = (0..section.header.len()).map(|i| if i == 0 { "---" } else { "---:" }).collect();
+        out.push_str(&format!("|{}|\n", align.join("|")));
+        for row in &section.rows {
+            let cells: Vec<String> = row.iter().map(|c| markdown_cell(c)).collect();
+            out.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const HTML_STYLE: &str = "body{font-family:-apple-system,BlinkMacSystemFont,'Segoe UI',sans-serif;max-width:960px;margin:2rem auto;padding:0 1rem;color:#1f2937}\
+h1{font-size:1.5rem}h2{font-size:1.1rem;margin-top:2rem;border-bottom:1px solid #e5e7eb;padding-bottom:.25rem}\
+table{border-collapse:collapse;width:100%;font-size:.9rem}th,td{padding:.35rem .6rem;border-bottom:1px solid #f3f4f6;text-align:right}\
+th:first-child,td:first-child{text-align:left}th{color:#6b7280;font-weight:600}.empty{color:#6b7280;font-style:italic}\
+.savings{background:#ecfdf5;border:1px solid #a7f3d0;border-radius:6px;padding:.6rem .9rem}";
+
+/// Render a digest as a self-contained HTML page (inline styles, no external assets)
+pub fn to_html(d: &UsageDigest) -> String {
+    let title = format!("ProxyPal usage digest — {}", d.label);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        escape_html(&title),
+        HTML_STYLE,
+        escape_html(&title)
+    );
+    if !d.savings.is_empty() {
+        out.push_str(&format!(
+            "<p class=\"savings\">Estimated savings from subscriptions: <strong>{}</strong></p>\n",
+            escape_html(&usd(d.total_savings_usd))
+        ));
+    }
+    for section in sections(d) {
+        out.push_str(&format!("<h2>{}</h2>\n", escape_html(section.title)));
+        if section.rows.is_empty() {
+            out.push_str(&format!("<p class=\"empty\">{}</p>\n", escape_html(section.empty)));
+            continue;
+        }
+        out.push_str("<table>\n<tr>");
+        for h in &section.header {
+            out.push_str(&format!("<th>{}</th>", escape_html(h)));
+        }
+        out.push_str("</tr>\n");
+        for row in &section.rows {
+            out.push_str("<tr>");
+            for c in row {
+                out.push_str(&format!("<td>{}</td>", escape_html(c)));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Folder digests are written to
+pub fn reports_dir(settings: &DigestSettings) -> PathBuf {
+    match settings.folder.trim() {
+        "" => get_reports_dir(),
+        folder => PathBuf::from(folder),
+    }
+}
+
+/// File name without extension of the digest for `[from, to)`
+fn base_name(from: u64, to: u64, tz: &DisplayTimezone) -> String {
+    format!("usage-digest-{}_{}", tz.day_label(from), tz.day_label(to - 1))
+}
+
+/// Write a digest to `dir` as `usage-digest-<first day>_<last day>.md` and `.html`
+pub fn write(d: UsageDigest, dir: &Path, tz: &DisplayTimezone) -> Result<DigestFiles, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let base = base_name(d.from, d.to, tz);
+    let markdown_path = dir.join(format!("{}.md", base));
+    let html_path = dir.join(format!("{}.html", base));
+    std::fs::write(&markdown_path, to_markdown(&d)).map_err(|e| format!("Failed to write digest: {}", e))?;
+    std::fs::write(&html_path, to_html(&d)).map_err(|e| format!("Failed to write digest: {}", e))?;
+    Ok(DigestFiles {
+        markdown_path: markdown_path.display().to_string(),
+        html_path: html_path.display().to_string(),
+        digest: d,
+    })
+}
+
+/// The last completed day or week (Monday–Sunday) before `now`
+pub fn last_complete_period(frequency: &str, now: u64, tz: &DisplayTimezone) -> (u64, u64) {
+    let period = if frequency == "daily" { "daily" } else { "weekly" };
+    let (current_start, _) = period_bounds(period, now, tz);
+    period_bounds(period, current_start.saturating_sub(1), tz)
+}
+
+/// Write the digest for the last completed period if it hasn't been written yet
+fn run_scheduled(app: &AppHandle) -> Result<(), String> {
+    let config = app.state::<AppState>().config.lock().unwrap().clone();
+    if !config.digest.enabled {
+        return Ok(());
+    }
+    let tz = DisplayTimezone::from_setting(&config.display_timezone);
+    let (from, to) = last_complete_period(&config.digest.frequency, now_ms(), &tz);
+    let dir = reports_dir(&config.digest);
+    let expected = dir.join(format!("{}.md", base_name(from, to, &tz)));
+    if expected.exists() {
+        return Ok(());
+    }
+
+    let store = app.state::<RequestStore>();
+    let digest = build(&store, &crate::load_aggregate(), &config.subscriptions, from, to, &tz)?;
+    let files = write(digest, &dir, &tz)?;
+    println!("[Digest] Wrote {}", files.markdown_path);
+    Ok(())
+}
+
+/// Check every 15 minutes whether a scheduled digest is due
+pub fn start(app: AppHandle) {
+    const INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+    // Queries the request store and writes files, so it runs on its own thread
+    std::thread::spawn(move || loop {
+        if let Err(e) = run_scheduled(&app) {
+            eprintln!("[Digest] Failed to write scheduled digest: {}", e);
+        }
+        std::thread::sleep(INTERVAL);
+    });
+}
@@ -0,0 +1,431 @@
+//! Local analytics exports (CSV and JSON Lines).
+//!
+//! Requests are streamed from the request store in chunks and written row by
+//! row, so exporting a large history never holds it all in memory. Files are
+//! written next to the destination and renamed into place once complete.
+//!
+//! Rolled-up tables come from the request store for a given range, priced with
+//! the pricing catalog, and are written one time bucket at a time. All-time
+//! tables grouped one way (by time bucket, or by provider/model/account/
+//! endpoint) come from `aggregate.json` instead, which still counts requests
+//! whose rows retention has compacted away.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::pricing;
+use crate::request_store::RequestStore;
+use crate::timezone::{now_ms, DisplayTimezone};
+use crate::types::{Aggregate, AggregateGrouping, ExportRange, ExportSummary, ModelStats, RequestLog, RequestQuery, TimeSeriesPoint};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    JsonLines,
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "jsonl" | "ndjson" => Ok(Self::JsonLines),
+            other => Err(format!("Unsupported export format: {}", other)),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::JsonLines => "jsonl",
+        }
+    }
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break
+pub fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Escape a Markdown table cell
+pub fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+/// Dollar amount with cents, e.g. "$1.50" or "-$0.25"
+pub fn usd(value: f64) -> String {
+    if value < 0.0 {
+        format!("-${:.2}", -value)
+    } else {
+        format!("${:.2}", value)
+    }
+}
+
+fn opt<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// A row that can be written as CSV (with a fixed header) or as a JSON line
+trait ExportRow: Serialize {
+    const COLUMNS: &'static [&'static str];
+    fn csv_values(&self) -> Vec<String>;
+}
+
+/// Writes rows to a temporary file that replaces the destination on `finish`
+struct ExportWriter {
+    format: ExportFormat,
+    out: BufWriter<File>,
+    temp_path: PathBuf,
+    path: PathBuf,
+    rows: u64,
+}
+
+impl ExportWriter {
+    fn create<R: ExportRow>(path: &Path, format: ExportFormat) -> Result<Self, String> {
+        let mut temp_name = path.as_os_str().to_owned();
+        temp_name.push(".partial");
+        let temp_path = PathBuf::from(temp_name);
+        let file = File::create(&temp_path).map_err(|e| format!("Failed to create {}: {}", temp_path.display(), e))?;
+
+        let mut writer = Self {
+            format,
+            out: BufWriter::new(file),
+            temp_path,
+            path: path.to_path_buf(),
+            rows: 0,
+        };
+        if format == ExportFormat::Csv {
+            let header = R::COLUMNS.join(",");
+            writeln!(writer.out, "{}", header).map_err(|e| e.to_string())?;
+        }
+        Ok(writer)
+    }
+
+    fn write<R: ExportRow>(&mut self, row: &R) -> Result<(), String> {
+        match self.format {
+            ExportFormat::Csv => {
+                let line: Vec<String> = row.csv_values().iter().map(|v| csv_field(v)).collect();
+                writeln!(self.out, "{}", line.join(","))
+            }
+            ExportFormat::JsonLines => serde_json::to_writer(&mut self.out, row)
+                .map_err(std::io::Error::from)
+                .and_then(|_| self.out.write_all(b"\n")),
+        }
+        .map_err(|e| format!("Failed to write export: {}", e))?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<ExportSummary, String> {
+        self.out.flush().map_err(|e| format!("Failed to write export: {}", e))?;
+        std::fs::rename(&self.temp_path, &self.path).map_err(|e| format!("Failed to save export: {}", e))?;
+        Ok(ExportSummary {
+            path: self.path.display().to_string(),
+            format: self.format.name().to_string(),
+            rows: self.rows,
+        })
+    }
+
+    fn abort(self) {
+        drop(self.out);
+        let _ = std::fs::remove_file(&self.temp_path);
+    }
+}
+
+#[derive(Serialize)]
+struct RequestRow<'a> {
+    /// Request time in the display time zone (RFC 3339)
+    time: String,
+    timestamp_ms: u64,
+    id: &'a str,
+    request_id: Option<&'a str>,
+    provider: &'a str,
+    model: &'a str,
+    endpoint_kind: &'a str,
+    account: Option<&'a str>,
+    status: u16,
+    error_category: Option<&'a str>,
+    duration_ms: u64,
+    tokens_in: Option<u32>,
+    tokens_out: Option<u32>,
+    tokens_cached: Option<u32>,
+    /// Estimated at current prices; empty until the request's tokens are known
+    cost_usd: Option<f64>,
+}
+
+impl<'a> RequestRow<'a> {
+    fn new(req: &'a RequestLog, tz: &DisplayTimezone) -> Self {
+        let has_tokens = req.tokens_in.is_some() || req.tokens_out.is_some();
+        Self {
+            time: tz.format(req.timestamp, "%Y-%m-%dT%H:%M:%S%.3f%:z"),
+            timestamp_ms: req.timestamp,
+            id: &req.id,
+            request_id: req.request_id.as_deref(),
+            provider: &req.provider,
+            model: &req.model,
+            endpoint_kind: &req.endpoint_kind,
+            account: req.account.as_deref(),
+            status: req.status,
+            error_category: req.error_category.as_deref(),
+            duration_ms: req.duration_ms,
+            tokens_in: req.tokens_in,
+            tokens_out: req.tokens_out,
+            tokens_cached: req.tokens_cached,
+            cost_usd: has_tokens.then(|| pricing::request_cost(req)),
+        }
+    }
+}
+
+impl ExportRow for RequestRow<'_> {
+    const COLUMNS: &'static [&'static str] = &[
+        "time",
+        "timestamp_ms",
+        "id",
+        "request_id",
+        "provider",
+        "model",
+        "endpoint_kind",
+        "account",
+        "status",
+        "error_category",
+        "duration_ms",
+        "tokens_in",
+        "tokens_out",
+        "tokens_cached",
+        "cost_usd",
+    ];
+
+    fn csv_values(&self) -> Vec<String> {
+        vec![
+            self.time.clone(),
+            self.timestamp_ms.to_string(),
+            self.id.to_string(),
+            opt(self.request_id),
+            self.provider.to_string(),
+            self.model.to_string(),
+            self.endpoint_kind.to_string(),
+            opt(self.account),
+            self.status.to_string(),
+            opt(self.error_category),
+            self.duration_ms.to_string(),
+            opt(self.tokens_in),
+            opt(self.tokens_out),
+            opt(self.tokens_cached),
+            opt(self.cost_usd.map(|c| format!("{:.6}", c))),
+        ]
+    }
+}
+
+/// Write the requests matching `filter` (oldest first) to `path`
+pub fn export_requests(
+    store: &RequestStore,
+    filter: &RequestQuery,
+    format: ExportFormat,
+    path: &Path,
+    tz: &DisplayTimezone,
+) -> Result<ExportSummary, String> {
+    let mut writer = ExportWriter::create::<RequestRow>(path, format)?;
+    let result = store.for_each_request(filter, |req| writer.write(&RequestRow::new(req, tz)));
+    match result {
+        Ok(_) => writer.finish(),
+        Err(e) => {
+            writer.abort();
+            Err(e)
+        }
+    }
+}
+
+#[derive(Serialize, Default)]
+struct AggregateRow {
+    /// Time bucket label, when grouped by time
+    period: Option<String>,
+    /// Provider, model, account or endpoint kind, when grouped by one
+    group: Option<String>,
+    requests: u64,
+    success_count: Option<u64>,
+    failure_count: Option<u64>,
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+    cached_tokens: Option<u64>,
+    tokens: u64,
+    cost_usd: Option<f64>,
+}
+
+impl AggregateRow {
+    fn from_stats(group: &str, stats: &ModelStats) -> Self {
+        let tokens = match stats.tokens {
+            0 => stats.input_tokens + stats.output_tokens,
+            tokens => tokens,
+        };
+        Self {
+            group: Some(group.to_string()),
+            requests: stats.requests,
+            success_count: Some(stats.success_count),
+            failure_count: Some(stats.requests.saturating_sub(stats.success_count)),
+            input_tokens: Some(stats.input_tokens),
+            output_tokens: Some(stats.output_tokens),
+            cached_tokens: Some(stats.cached_tokens),
+            tokens,
+            ..Default::default()
+        }
+    }
+}
+
+impl ExportRow for AggregateRow {
+    const COLUMNS: &'static [&'static str] = &[
+        "period",
+        "group",
+        "requests",
+        "success_count",
+        "failure_count",
+        "input_tokens",
+        "output_tokens",
+        "cached_tokens",
+        "tokens",
+        "cost_usd",
+    ];
+
+    fn csv_values(&self) -> Vec<String> {
+        vec![
+            opt(self.period.as_deref()),
+            opt(self.group.as_deref()),
+            self.requests.to_string(),
+            opt(self.success_count),
+            opt(self.failure_count),
+            opt(self.input_tokens),
+            opt(self.output_tokens),
+            opt(self.cached_tokens),
+            self.tokens.to_string(),
+            opt(self.cost_usd.map(|c| format!("{:.6}", c))),
+        ]
+    }
+}
+
+/// All-time series from the aggregate, merging request and token points by label
+fn write_series_rows(
+    agg: &Aggregate,
+    bucket: &str,
+    mut write: impl FnMut(&AggregateRow) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut totals: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    let mut add = |points: &[TimeSeriesPoint], tokens: bool, label_len: usize| {
+        for point in points {
+            let total = totals.entry(point.label.chars().take(label_len).collect()).or_default();
+            if tokens {
+                total.1 += point.value;
+            } else {
+                total.0 += point.value;
+            }
+        }
+    };
+    match bucket {
+        "hour" => {
+            add(&agg.requests_by_hour, false, usize::MAX);
+            add(&agg.tokens_by_hour, true, usize::MAX);
+        }
+        "day" => {
+            add(&agg.requests_by_day, false, usize::MAX);
+            add(&agg.tokens_by_day, true, usize::MAX);
+        }
+        // Months already rolled up, plus the days that haven't been yet
+        "month" => {
+            add(&agg.requests_by_month, false, usize::MAX);
+            add(&agg.tokens_by_month, true, usize::MAX);
+            add(&agg.requests_by_day, false, 7);
+            add(&agg.tokens_by_day, true, 7);
+        }
+        other => return Err(format!("Unsupported bucket: {}", other)),
+    }
+
+    for (label, (requests, tokens)) in totals {
+        write(&AggregateRow {
+            period: Some(label),
+            requests,
+            tokens,
+            ..Default::default()
+        })?;
+    }
+    Ok(())
+}
+
+/// All-time totals from the aggregate grouped one way, busiest first
+fn write_group_rows(
+    agg: &Aggregate,
+    by: &str,
+    mut write: impl FnMut(&AggregateRow) -> Result<(), String>,
+) -> Result<(), String> {
+    let stats = match by {
+        "provider" => &agg.provider_stats,
+        "model" => &agg.model_stats,
+        "account" => &agg.account_stats,
+        "endpoint" => &agg.endpoint_stats,
+        other => return Err(format!("Unsupported grouping: {}", other)),
+    };
+    let mut groups: Vec<(&String, &ModelStats)> = stats.iter().collect();
+    groups.sort_by(|a, b| b.1.requests.cmp(&a.1.requests).then_with(|| a.0.cmp(b.0)));
+    for (group, stats) in groups {
+        write(&AggregateRow::from_stats(group, stats))?;
+    }
+    Ok(())
+}
+
+/// Write a rolled-up usage table to `path`, row by row as it's read
+pub fn export_aggregate(
+    store: &RequestStore,
+    aggregate: &Aggregate,
+    range: &ExportRange,
+    grouping: &AggregateGrouping,
+    format: ExportFormat,
+    path: &Path,
+    tz: &DisplayTimezone,
+) -> Result<ExportSummary, String> {
+    let bucket = grouping.bucket.as_deref().filter(|b| !b.is_empty());
+    let by = grouping.by.as_deref().filter(|b| !b.is_empty());
+    let all_time = range.from.is_none() && range.to.is_none();
+    if bucket.is_none() && by.is_none() {
+        return Err("Choose a time bucket, a grouping, or both".to_string());
+    }
+
+    let mut writer = ExportWriter::create::<AggregateRow>(path, format)?;
+    let result = match (bucket, by) {
+        (Some(bucket), None) if all_time => write_series_rows(aggregate, bucket, |row| writer.write(row)),
+        (None, Some(by)) if all_time => write_group_rows(aggregate, by, |row| writer.write(row)),
+        (bucket, by) => {
+            let from = range.from.unwrap_or(0);
+            let to = range.to.unwrap_or_else(|| now_ms() + 1);
+            // One period at a time, its groups busiest first
+            store.for_each_usage_period(by.unwrap_or("all"), bucket.unwrap_or("all"), from, to, tz, |mut points| {
+                points.sort_by(|a, b| b.requests.cmp(&a.requests));
+                for p in points {
+                    writer.write(&AggregateRow {
+                        period: bucket.map(|_| p.label.clone()),
+                        group: by.map(|_| p.group.clone()),
+                        requests: p.requests,
+                        success_count: Some(p.success_count),
+                        failure_count: Some(p.requests.saturating_sub(p.success_count)),
+                        input_tokens: Some(p.input_tokens),
+                        output_tokens: Some(p.output_tokens),
+                        cached_tokens: Some(p.cached_tokens),
+                        tokens: p.input_tokens + p.output_tokens,
+                        cost_usd: Some(p.cost_usd),
+                    })?;
+                }
+                Ok(())
+            })
+        }
+    };
+    match result {
+        Ok(()) => writer.finish(),
+        Err(e) => {
+            writer.abort();
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,439 @@
+//! Webhooks and local command hooks for backend events.
+//!
+//! The lifecycle events the backend emits to the webview are also delivered
+//! here, through Rust-side listeners registered at startup, so no emit site
+//! needs to know about hooks. Each enabled hook subscribed to an event runs in
+//! its own task:
+//!
+//! - Webhooks POST a JSON envelope, a Slack-compatible `{"text": ...}` body or
+//!   a custom template. With a secret, `X-ProxyPal-Signature` carries
+//!   `sha256=<hex HMAC of "<timestamp>.<body>">`, the timestamp being sent in
+//!   `X-ProxyPal-Timestamp`. Network errors, 429 and 5xx are retried with
+//!   exponential backoff, at most 10 times and at most 5 minutes apart.
+//! - Commands run an executable (absolute path) with its arguments, without a
+//!   shell, with the event in `PROXYPAL_EVENT` and the payload JSON in
+//!   `PROXYPAL_PAYLOAD` and on stdin, and are killed after 30s.
+//!
+//! `request-log` fires for every proxied request, so its deliveries share a
+//! few concurrent slots behind a bounded queue; when the queue is full further
+//! deliveries are dropped and recorded as failed.
+//!
+//! The most recent delivery results are kept in memory for the hooks page.
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tauri::{AppHandle, Listener, Manager};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Semaphore;
+
+use crate::state::AppState;
+use crate::timezone::now_ms;
+use crate::types::{Hook, HookDelivery};
+
+/// Events hooks can subscribe to
+pub const HOOK_EVENTS: &[&str] = &[
+    "proxy-status-changed",
+    "auth-status-changed",
+    "copilot-status-changed",
+    "ssh-status-changed",
+    "cloudflare-status-changed",
+    "request-log",
+    "auth-expiring",
+];
+
+/// Event used by `test_hook`
+const TEST_EVENT: &str = "test";
+/// Event emitted once per proxied request
+const REQUEST_LOG_EVENT: &str = "request-log";
+
+const MAX_DELIVERIES: usize = 100;
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_RETRIES: u32 = 10;
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+/// `request-log` deliveries running at once
+const REQUEST_LOG_CONCURRENCY: usize = 4;
+/// `request-log` deliveries running or waiting before new ones are dropped
+const REQUEST_LOG_QUEUE: usize = 200;
+/// Response or output kept in a delivery result
+const MAX_MESSAGE_CHARS: usize = 500;
+
+lazy_static::lazy_static! {
+    /// Most recent first
+    static ref DELIVERIES: Mutex<VecDeque<HookDelivery>> = Mutex::new(VecDeque::new());
+    static ref REQUEST_LOG_SLOTS: Arc<Semaphore> = Arc::new(Semaphore::new(REQUEST_LOG_CONCURRENCY));
+}
+
+static REQUEST_LOG_PENDING: AtomicUsize = AtomicUsize::new(0);
+
+/// Check a hook before saving it
+pub fn validate(hook: &Hook) -> Result<(), String> {
+    if hook.events.is_empty() {
+        return Err("Select at least one event".to_string());
+    }
+    if let Some(event) = hook.events.iter().find(|e| !HOOK_EVENTS.contains(&e.as_str())) {
+        return Err(format!("Unsupported event: {}", event));
+    }
+    if hook.max_retries > MAX_RETRIES {
+        return Err(format!("At most {} retries are allowed", MAX_RETRIES));
+    }
+    match hook.kind.as_str() {
+        "webhook" => {
+            let url = url::Url::parse(hook.url.trim()).map_err(|e| format!("Invalid webhook URL: {}", e))?;
+            if url.scheme() != "http" && url.scheme() != "https" {
+                return Err("Webhook URL must use http or https".to_string());
+            }
+            if !["json", "slack", "template"].contains(&hook.format.as_str()) {
+                return Err(format!("Unsupported webhook format: {}", hook.format));
+            }
+            if hook.format == "template" && hook.template.trim().is_empty() {
+                return Err("Template is required for the template format".to_string());
+            }
+        }
+        "command" => {
+            if hook.command.trim().is_empty() {
+                return Err("Executable is required".to_string());
+            }
+            check_executable(&hook.command)?;
+        }
+        other => return Err(format!("Unsupported hook kind: {}", other)),
+    }
+    Ok(())
+}
+
+/// Commands run without a shell, so the executable must be an absolute path to a file
+fn check_executable(command: &str) -> Result<(), String> {
+    let path = Path::new(command);
+    if !path.is_absolute() {
+        return Err("Executable must be an absolute path; pass arguments separately".to_string());
+    }
+    if !path.is_file() {
+        return Err(format!("Executable not found: {}", command));
+    }
+    Ok(())
+}
+
+/// Register listeners for every hookable event
+pub fn start(app: AppHandle) {
+    for event in HOOK_EVENTS {
+        let handle = app.clone();
+        app.listen_any(*event, move |e| {
+            let payload: Value = serde_json::from_str(e.payload()).unwrap_or(Value::Null);
+            dispatch(&handle, event, payload);
+        });
+    }
+}
+
+/// Run every enabled hook subscribed to `event`
+fn dispatch(app: &AppHandle, event: &str, payload: Value) {
+    let hooks: Vec<Hook> = app
+        .state::<AppState>()
+        .config
+        .lock()
+        .unwrap()
+        .hooks
+        .iter()
+        .filter(|h| h.enabled && h.events.iter().any(|e| e == event))
+        .cloned()
+        .collect();
+
+    for hook in hooks {
+        let event = event.to_string();
+        let payload = payload.clone();
+        if event != REQUEST_LOG_EVENT {
+            tauri::async_runtime::spawn(async move {
+                run(&hook, &event, &payload).await;
+            });
+            continue;
+        }
+
+        if REQUEST_LOG_PENDING.fetch_add(1, Ordering::SeqCst) >= REQUEST_LOG_QUEUE {
+            REQUEST_LOG_PENDING.fetch_sub(1, Ordering::SeqCst);
+            let message = format!("Dropped: more than {} request-log deliveries queued", REQUEST_LOG_QUEUE);
+            eprintln!("[Hooks] '{}' {}", hook.name, message);
+            record(&hook, &event, now_ms(), (false, 0, None, message), Duration::ZERO);
+            continue;
+        }
+        tauri::async_runtime::spawn(async move {
+            // The semaphore is never closed
+            if let Ok(_permit) = REQUEST_LOG_SLOTS.acquire().await {
+                run(&hook, &event, &payload).await;
+            }
+            REQUEST_LOG_PENDING.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
+
+/// Run a hook once with a sample payload and return the result
+pub async fn test(hook: &Hook) -> HookDelivery {
+    let payload = json!({ "message": "Test event from ProxyPal" });
+    run(hook, TEST_EVENT, &payload).await
+}
+
+async fn run(hook: &Hook, event: &str, payload: &Value) -> HookDelivery {
+    let started = Instant::now();
+    let timestamp = now_ms();
+    let (success, attempts, status, message) = if hook.kind == "command" {
+        run_command(hook, event, payload).await
+    } else {
+        send_webhook(hook, event, payload, timestamp).await
+    };
+
+    if !success {
+        eprintln!("[Hooks] '{}' failed for {}: {}", hook.name, event, message);
+    }
+    record(hook, event, timestamp, (success, attempts, status, message), started.elapsed())
+}
+
+/// Keep a delivery result for the hooks page
+fn record(
+    hook: &Hook,
+    event: &str,
+    timestamp: u64,
+    (success, attempts, status, message): (bool, u32, Option<i32>, String),
+    duration: Duration,
+) -> HookDelivery {
+    let delivery = HookDelivery {
+        id: uuid::Uuid::new_v4().to_string(),
+        hook_id: hook.id.clone(),
+        hook_name: hook.name.clone(),
+        event: event.to_string(),
+        timestamp,
+        success,
+        attempts,
+        status,
+        message,
+        duration_ms: duration.as_millis() as u64,
+    };
+    let mut deliveries = DELIVERIES.lock().unwrap();
+    deliveries.push_front(delivery.clone());
+    deliveries.truncate(MAX_DELIVERIES);
+    delivery
+}
+
+/// Recent delivery results, most recent first (optionally for one hook)
+pub fn deliveries(hook_id: Option<&str>) -> Vec<HookDelivery> {
+    DELIVERIES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|d| hook_id.is_none_or(|id| d.hook_id == id))
+        .cloned()
+        .collect()
+}
+
+fn truncate(text: &str) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(MAX_MESSAGE_CHARS) {
+        Some((idx, _)) => format!("{}…", &text[..idx]),
+        None => text.to_string(),
+    }
+}
+
+fn str_field<'a>(payload: &'a Value, key: &str) -> &'a str {
+    payload.get(key).and_then(|v| v.as_str()).unwrap_or("")
+}
+
+/// One-line description of an event, for chat messages
+pub fn summary(event: &str, payload: &Value) -> String {
+    let running = payload.get("running").and_then(|v| v.as_bool()).unwrap_or(false);
+    let tunnel = |kind: &str| {
+        let mut text = format!("{} tunnel {} is {}", kind, str_field(payload, "id"), str_field(payload, "status"));
+        if let Some(message) = payload.get("message").and_then(|v| v.as_str()) {
+            text.push_str(&format!(": {}", message));
+        }
+        text
+    };
+    match event {
+        "proxy-status-changed" if running => {
+            format!("ProxyPal proxy is running on port {}", payload.get("port").unwrap_or(&Value::Null))
+        }
+        "proxy-status-changed" => "ProxyPal proxy stopped".to_string(),
+        "copilot-status-changed" if running => format!(
+            "Copilot bridge is running{}",
+            if payload.get("authenticated").and_then(|v| v.as_bool()) == Some(true) { " (authenticated)" } else { "" }
+        ),
+        "copilot-status-changed" => "Copilot bridge stopped".to_string(),
+        "auth-status-changed" => {
+            let accounts: Vec<String> = payload
+                .as_object()
+                .map(|providers| {
+                    providers
+                        .iter()
+                        .filter(|(_, count)| count.as_u64().unwrap_or(0) > 0)
+                        .map(|(provider, count)| format!("{} {}", provider, count))
+                        .collect()
+                })
+                .unwrap_or_default();
+            if accounts.is_empty() {
+                "Connected accounts changed: none connected".to_string()
+            } else {
+                format!("Connected accounts changed: {}", accounts.join(", "))
+            }
+        }
+        "ssh-status-changed" => tunnel("SSH"),
+        "cloudflare-status-changed" => tunnel("Cloudflare"),
+        "request-log" => format!(
+            "{} {} {} → {} in {} ms",
+            str_field(payload, "method"),
+            str_field(payload, "path"),
+            str_field(payload, "model"),
+            payload.get("status").unwrap_or(&Value::Null),
+            payload.get("durationMs").unwrap_or(&Value::Null)
+        ),
+        "auth-expiring" => str_field(payload, "message").to_string(),
+        TEST_EVENT => "Test event from ProxyPal".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// A JSON string literal's contents
+fn json_escape(text: &str) -> String {
+    let quoted = Value::String(text.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+/// Fill in `{{event}}`, `{{summary}}`, `{{timestamp}}`, `{{payload}}` and `{{payload.<field>}}`
+fn render_template(template: &str, event: &str, payload: &Value, timestamp: u64) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = rest[start + 2..start + end].trim();
+        let value = match name {
+            "event" => json_escape(event),
+            "summary" => json_escape(&summary(event, payload)),
+            "timestamp" => timestamp.to_string(),
+            "payload" => payload.to_string(),
+            _ => match name.strip_prefix("payload.") {
+                Some(field) => match payload.get(field) {
+                    Some(Value::String(s)) => json_escape(s),
+                    Some(value) => value.to_string(),
+                    None => String::new(),
+                },
+                None => rest[start..start + end + 2].to_string(),
+            },
+        };
+        out.push_str(&value);
+        rest = &rest[start + end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn webhook_body(hook: &Hook, event: &str, payload: &Value, timestamp: u64) -> String {
+    match hook.format.as_str() {
+        "slack" => json!({ "text": summary(event, payload) }).to_string(),
+        "template" => render_template(&hook.template, event, payload, timestamp),
+        _ => json!({ "event": event, "timestamp": timestamp, "payload": payload }).to_string(),
+    }
+}
+
+/// Hex HMAC-SHA256 of `<timestamp>.<body>`
+fn signature(secret: &str, timestamp: u64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// POST the event, retrying transient failures; returns (success, attempts, status, message)
+async fn send_webhook(hook: &Hook, event: &str, payload: &Value, timestamp: u64) -> (bool, u32, Option<i32>, String) {
+    let body = webhook_body(hook, event, payload, timestamp);
+    let delivery_id = uuid::Uuid::new_v4().to_string();
+    let client = reqwest::Client::new();
+    let mut backoff = Duration::from_secs(1);
+    let mut attempts = 0;
+    // Hooks saved through `save_config` skip `validate`
+    let max_retries = hook.max_retries.min(MAX_RETRIES);
+
+    loop {
+        attempts += 1;
+        let mut request = client
+            .post(hook.url.trim())
+            .timeout(WEBHOOK_TIMEOUT)
+            .header("Content-Type", "application/json")
+            .header("User-Agent", concat!("ProxyPal/", env!("CARGO_PKG_VERSION")))
+            .header("X-ProxyPal-Event", event)
+            .header("X-ProxyPal-Delivery", &delivery_id)
+            .header("X-ProxyPal-Timestamp", timestamp.to_string())
+            .body(body.clone());
+        if !hook.secret.is_empty() {
+            request = request.header(
+                "X-ProxyPal-Signature",
+                format!("sha256={}", signature(&hook.secret, timestamp, &body)),
+            );
+        }
+
+        let (retry, result) = match request.send().await {
+            Ok(response) => {
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                let retry = status.is_server_error() || status.as_u16() == 429 || status.as_u16() == 408;
+                let message = if text.trim().is_empty() { status.to_string() } else { truncate(&text) };
+                (retry, (status.is_success(), attempts, Some(status.as_u16() as i32), message))
+            }
+            Err(e) => (true, (false, attempts, None, e.to_string())),
+        };
+        if result.0 || !retry || attempts > max_retries {
+            return result;
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Run the hook's executable; returns (success, attempts, exit code, output)
+async fn run_command(hook: &Hook, event: &str, payload: &Value) -> (bool, u32, Option<i32>, String) {
+    // Hooks saved through `save_config` skip `validate`
+    if let Err(e) = check_executable(&hook.command) {
+        return (false, 1, None, e);
+    }
+    let payload_json = payload.to_string();
+
+    let mut cmd = tokio::process::Command::new(&hook.command);
+    cmd.args(&hook.args);
+    #[cfg(windows)]
+    cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    cmd.env("PROXYPAL_EVENT", event)
+        .env("PROXYPAL_PAYLOAD", &payload_json)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => return (false, 1, None, format!("Failed to start command: {}", e)),
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // The command may not read stdin; a broken pipe is fine
+        let _ = stdin.write_all(payload_json.as_bytes()).await;
+    }
+
+    match tokio::time::timeout(COMMAND_TIMEOUT, child.wait_with_output()).await {
+        Ok(Ok(output)) => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let text = if output.status.success() || stderr.trim().is_empty() { stdout } else { stderr };
+            (output.status.success(), 1, output.status.code(), truncate(&text))
+        }
+        Ok(Err(e)) => (false, 1, None, format!("Command failed: {}", e)),
+        Err(_) => (false, 1, None, format!("Command timed out after {}s", COMMAND_TIMEOUT.as_secs())),
+    }
+}
@@ -1,18 +1,38 @@
+mod alerts;
+mod auth_expiry;
+mod backup;
+mod budgets;
 mod commands;
 mod config;
+mod digest;
+mod export;
+mod hooks;
+mod metrics;
+mod otlp;
+mod providers;
 mod proxy;
 mod state;
 mod types;
 mod utils;
 mod ssh_manager;
 mod cloudflare_manager;
+mod pricing;
+mod request_store;
+mod retention;
+mod subscriptions;
+mod timezone;
+mod usage_merge;
 
 use crate::config::{get_aggregate_path, get_auth_path, get_history_path, load_config, save_config_to_file};
 use crate::state::AppState;
 use crate::types::{
     ProxyStatus, RequestLog, AuthStatus, OAuthState,
     UsageStats, TimeSeriesPoint, ModelUsage, ProviderUsage, RequestHistory,
-    Aggregate, ModelStats,
+    Aggregate, ModelStats, RequestQuery, RequestPage, AccountUsage, ErrorSeriesPoint, ErrorCause,
+    LatencyStats, LatencySummary, split_route_key,
+    UsageSeriesQuery, UsageSeries, UsageGroupTotal, UsageRetention, CompactionSummary, UsageReconcileSummary, UsageDriftReport, SourceTotals,
+    ExportRange, AggregateGrouping, ExportSummary, UsageBackupMetadata, UsageBackupImportSummary,
+    PricingCatalog, PricingCatalogs, PriceRule, CostRecomputeSummary,
     CopilotStatus, CopilotApiDetection, CopilotApiInstallResult,
     ClaudeApiKey, GeminiApiKey, CodexApiKey, VertexApiKey, OpenAICompatibleProvider,
     ThinkingBudgetSettings, ReasoningEffortSettings,
@@ -21,10 +41,15 @@ use crate::types::{
 };
 use crate::ssh_manager::SshManager;
 use crate::cloudflare_manager::CloudflareManager;
-use crate::utils::{estimate_request_cost, detect_provider_from_model, detect_provider_from_path, extract_model_from_path};
+use crate::request_store::RequestStore;
+use crate::proxy::endpoints::EndpointTable;
+use crate::proxy::correlation::{normalize_request_id, stable_request_id, ApiKeyDirectory, LruCache, RequestContext};
+use crate::proxy::failures::{classify_failure, failure_message};
+use crate::timezone::{local_log_time_to_utc, now_ms, DisplayTimezone};
+use crate::utils::{detect_provider_from_model, detect_provider_from_path, extract_model_from_path};
 use serde::{Deserialize, Serialize};
 use std::sync::Mutex;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tauri::{
     menu::{Menu, MenuItem},
@@ -39,7 +64,6 @@ fn get_management_key() -> String {
     load_config().management_key
 }
 use regex::Regex;
-use std::io::{BufRead, BufReader, Seek, SeekFrom};
 
 // Windows-specific imports for hiding CMD windows
 #[cfg(target_os = "windows")]
@@ -137,17 +161,19 @@ fn migrate_to_split_storage() {
     agg.total_cost_usd = history.total_cost_usd;
 
     // Build time-series from requests
+    let tz = DisplayTimezone::from_setting(&load_config().display_timezone);
     for req in &history.requests {
-        if let Some(dt) = chrono::DateTime::from_timestamp_millis(req.timestamp as i64) {
-            let day = dt.format("%Y-%m-%d").to_string();
-            update_timeseries(&mut agg.requests_by_day, &day, 1);
-            let tokens = (req.tokens_in.unwrap_or(0) + req.tokens_out.unwrap_or(0)) as u64;
-            update_timeseries(&mut agg.tokens_by_day, &day, tokens);
-        }
+        let day = tz.day_label(req.timestamp);
+        update_timeseries(&mut agg.requests_by_day, &day, 1);
+        let tokens = (req.tokens_in.unwrap_or(0) + req.tokens_out.unwrap_or(0)) as u64;
+        update_timeseries(&mut agg.tokens_by_day, &day, tokens);
 
         // Build model/provider stats
         update_model_stats(&mut agg, req);
         update_provider_stats(&mut agg, req);
+        update_endpoint_stats(&mut agg, req);
+        update_account_stats(&mut agg, req);
+        update_latency_stats(&mut agg, req, &tz);
     }
 
     // Also use existing time-series from history if available
@@ -195,6 +221,76 @@ fn update_model_stats(agg: &mut Aggregate, req: &RequestLog) {
     entry.cached_tokens += req.tokens_cached.unwrap_or(0) as u64;
 }
 
+fn update_endpoint_stats(agg: &mut Aggregate, req: &RequestLog) {
+    let entry = agg.endpoint_stats.entry(req.endpoint_kind.clone()).or_insert(ModelStats::default());
+    entry.requests += 1;
+    if req.status < 400 {
+        entry.success_count += 1;
+    }
+    entry.tokens += (req.tokens_in.unwrap_or(0) + req.tokens_out.unwrap_or(0)) as u64;
+    entry.input_tokens += req.tokens_in.unwrap_or(0) as u64;
+    entry.output_tokens += req.tokens_out.unwrap_or(0) as u64;
+    entry.cached_tokens += req.tokens_cached.unwrap_or(0) as u64;
+}
+
+fn update_account_stats(agg: &mut Aggregate, req: &RequestLog) {
+    let Some(account) = &req.account else {
+        return;
+    };
+    let entry = agg.account_stats.entry(account.clone()).or_insert(ModelStats::default());
+    entry.requests += 1;
+    if req.status < 400 {
+        entry.success_count += 1;
+    }
+    entry.tokens += (req.tokens_in.unwrap_or(0) + req.tokens_out.unwrap_or(0)) as u64;
+    entry.input_tokens += req.tokens_in.unwrap_or(0) as u64;
+    entry.output_tokens += req.tokens_out.unwrap_or(0) as u64;
+    entry.cached_tokens += req.tokens_cached.unwrap_or(0) as u64;
+}
+
+// Add tokens filled in after a request was first counted (see reconcile_request_tokens),
+// and the cost they imply. Model stats aren't touched here; they're merged from the ledger on sync.
+fn add_backfilled_tokens(agg: &mut Aggregate, req: &RequestLog, tz: &DisplayTimezone) {
+    let tokens_in = req.tokens_in.unwrap_or(0) as u64;
+    let tokens_out = req.tokens_out.unwrap_or(0) as u64;
+    let tokens_cached = req.tokens_cached.unwrap_or(0) as u64;
+    
+    update_timeseries(&mut agg.tokens_by_day, &tz.day_label(req.timestamp), tokens_in + tokens_out);
+    update_timeseries(&mut agg.tokens_by_hour, &tz.hour_label(req.timestamp), tokens_in + tokens_out);
+    
+    if let Some(entry) = agg.provider_stats.get_mut(&req.provider) {
+        entry.tokens += tokens_in + tokens_out;
+    }
+    if let Some(entry) = agg.endpoint_stats.get_mut(&req.endpoint_kind) {
+        entry.tokens += tokens_in + tokens_out;
+        entry.input_tokens += tokens_in;
+        entry.output_tokens += tokens_out;
+        entry.cached_tokens += tokens_cached;
+    }
+    if let Some(entry) = req.account.as_ref().and_then(|a| agg.account_stats.get_mut(a)) {
+        entry.tokens += tokens_in + tokens_out;
+        entry.input_tokens += tokens_in;
+        entry.output_tokens += tokens_out;
+        entry.cached_tokens += tokens_cached;
+    }
+    
+    let watcher = &mut crate::usage_merge::sources_mut(agg).watcher;
+    watcher.tokens_in += tokens_in;
+    watcher.tokens_out += tokens_out;
+    watcher.tokens_cached += tokens_cached;
+    watcher.cost_usd += crate::pricing::request_cost(req);
+}
+
+fn update_latency_stats(agg: &mut Aggregate, req: &RequestLog, tz: &DisplayTimezone) {
+    // Failed requests often return immediately (or time out), which would skew percentiles
+    if req.status >= 400 || req.duration_ms == 0 {
+        return;
+    }
+    agg.latency.record(&req.model, &req.provider, req.duration_ms);
+    // Old days are dropped by the series retention (`daily_days`)
+    agg.latency_by_day.entry(tz.day_label(req.timestamp)).or_default().record(&req.model, &req.provider, req.duration_ms);
+}
+
 fn update_provider_stats(agg: &mut Aggregate, req: &RequestLog) {
     let provider = if req.provider.is_empty() || req.provider == "unknown" {
         "unknown".to_string()
@@ -212,15 +308,17 @@ fn update_provider_stats(agg: &mut Aggregate, req: &RequestLog) {
 
 // Load auth status from file
 fn load_auth_status() -> AuthStatus {
+    // Start from every registered provider, so ones added since the file was written show up
+    let mut auth = AuthStatus::default();
     let path = get_auth_path();
     if path.exists() {
         if let Ok(data) = std::fs::read_to_string(&path) {
-            if let Ok(auth) = serde_json::from_str(&data) {
-                return auth;
+            if let Ok(saved) = serde_json::from_str::<AuthStatus>(&data) {
+                auth.accounts.extend(saved.accounts);
             }
         }
     }
-    AuthStatus::default()
+    auth
 }
 
 // Save auth status to file
@@ -257,7 +355,7 @@ fn extract_timestamp_from_line(line: &str) -> Option<u64> {
         let datetime_str = format!("{} {}", date_str, time_str);
         return chrono::NaiveDateTime::parse_from_str(&datetime_str, "%Y-%m-%d %H:%M:%S")
             .ok()
-            .map(|dt| dt.and_local_timezone(chrono::Local).unwrap().timestamp_millis() as u64);
+            .and_then(local_log_time_to_utc);
     }
     None
 }
@@ -265,28 +363,38 @@ fn extract_timestamp_from_line(line: &str) -> Option<u64> {
 // Parse a GIN log line and extract request information
 // Format: [GIN] 2025/12/04 - 20:51:48 | 200 | 6.656s | ::1 | POST "/api/provider/anthropic/v1/messages"
 // Also handles new format: | request_id | 200 | 6.656s | ip | POST "/path"
-fn parse_gin_log_line(line: &str, request_counter: &AtomicU64, model_cache: &std::sync::RwLock<std::collections::HashMap<String, String>>) -> Option<RequestLog> {
-    // Check for model info in DEBUG lines and cache it
+// Only requests to endpoints in `endpoints` are tracked
+fn parse_gin_log_line(line: &str, request_contexts: &mut LruCache<String, RequestContext>, endpoints: &EndpointTable, api_keys: &ApiKeyDirectory) -> Option<RequestLog> {
+    // Check for model/account info in DEBUG lines and remember it for the request's access line
     // Format: | f803bb77 | Use OAuth user@email.com for model claude-opus-4-5-thinking
+    //         | f803bb77 | Use API key sk-a...wxyz for model claude-sonnet-4-5
     if line.contains("for model ") {
         lazy_static::lazy_static! {
+            static ref ROUTE_REGEX: Regex = Regex::new(
+                r#"\|\s+([a-f0-9]{8})\s+\|.*Use (OAuth|API key)\s+(\S+)\s+for model\s+(\S+)"#
+            ).unwrap();
             static ref MODEL_REGEX: Regex = Regex::new(
                 r#"\|\s+([a-f0-9]{8})\s+\|.*for model\s+(\S+)"#
             ).unwrap();
         }
-        if let Some(caps) = MODEL_REGEX.captures(line) {
+        if let Some(caps) = ROUTE_REGEX.captures(line) {
+            let request_id = caps.get(1)?.as_str().to_string();
+            let credential = caps.get(3)?.as_str().to_string();
+            let model = caps.get(4)?.as_str().to_string();
+            // Key-based routes log a masked key; map it back to the configured key's index
+            let api_key_index = if caps.get(2)?.as_str() == "API key" {
+                api_keys.lookup(&credential, &detect_provider_from_model(&model))
+            } else {
+                None
+            };
+            let context = request_contexts.get_or_insert_default(request_id);
+            context.model = Some(model);
+            context.account = Some(credential);
+            context.api_key_index = api_key_index;
+        } else if let Some(caps) = MODEL_REGEX.captures(line) {
             let request_id = caps.get(1)?.as_str().to_string();
             let model = caps.get(2)?.as_str().to_string();
-            if let Ok(mut cache) = model_cache.write() {
-                cache.insert(request_id, model);
-                // Keep cache size reasonable
-                if cache.len() > 1000 {
-                    let keys: Vec<String> = cache.keys().take(500).cloned().collect();
-                    for key in keys {
-                        cache.remove(&key);
-                    }
-                }
-            }
+            request_contexts.get_or_insert_default(request_id).model = Some(model);
         }
         return None;
     }
@@ -296,6 +404,15 @@ fn parse_gin_log_line(line: &str, request_counter: &AtomicU64, model_cache: &std
     let is_new_format = !is_gin_log && line.contains("| POST") || line.contains("| GET");
     
     if !is_gin_log && !is_new_format {
+        // Keep other lines logged under a request ID (upstream errors, retries, ...);
+        // they're used to classify the request if it fails
+        lazy_static::lazy_static! {
+            static ref TAGGED_REGEX: Regex = Regex::new(r#"\|\s+([a-f0-9]{8})\s+\|"#).unwrap();
+        }
+        if let Some(caps) = TAGGED_REGEX.captures(line) {
+            let request_id = caps.get(1)?.as_str().to_string();
+            request_contexts.get_or_insert_default(request_id).push_line(line);
+        }
         return None;
     }
     
@@ -311,18 +428,6 @@ fn parse_gin_log_line(line: &str, request_counter: &AtomicU64, model_cache: &std
         return None;
     }
     
-    // Only track actual API calls (chat completions, messages, etc.)
-    let is_trackable = line.contains("/chat/completions") || 
-                       line.contains("/v1/messages") ||
-                       line.contains("/completions") ||
-                       line.contains("/v1beta") ||
-                       line.contains(":generateContent") ||
-                       line.contains(":streamGenerateContent");
-    
-    if !is_trackable {
-        return None;
-    }
-    
     // Try new format first: | request_id | status | duration | ip | METHOD "path"
     // Example: | f803bb77 | 200 | 12.453s | 127.0.0.1 | POST "/v1/messages"
     lazy_static::lazy_static! {
@@ -336,11 +441,12 @@ fn parse_gin_log_line(line: &str, request_counter: &AtomicU64, model_cache: &std
     
     // Try new format
     if let Some(captures) = NEW_FORMAT_REGEX.captures(line) {
-        let request_id = captures.get(1)?.as_str().to_string();
+        let request_id = normalize_request_id(captures.get(1)?.as_str());
         let status: u16 = captures.get(2)?.as_str().parse().ok()?;
         let duration_str = captures.get(3)?.as_str();
         let method = captures.get(4)?.as_str().to_string();
         let path = captures.get(5)?.as_str().to_string();
+        let endpoint = endpoints.classify(&path)?;
         
         // Get timestamp from the beginning of the line if present
         let timestamp = extract_timestamp_from_line(line)
@@ -352,30 +458,35 @@ fn parse_gin_log_line(line: &str, request_counter: &AtomicU64, model_cache: &std
         // Parse duration to milliseconds
         let duration_ms = parse_duration(duration_str);
         
-        // Look up model from cache using request_id, or fall back to path extraction
-        let model = if request_id != "--------" {
-            model_cache.read().ok()
-                .and_then(|cache| cache.get(&request_id).cloned())
-                .or_else(|| extract_model_from_path(&path))
-                .unwrap_or_else(|| "unknown".to_string())
-        } else {
-            extract_model_from_path(&path).unwrap_or_else(|| "unknown".to_string())
-        };
+        // The access line is the last one logged for a request, so take (not just read)
+        // its context - upstream IDs are only 32 bits and do get reused
+        let context = request_id.as_ref()
+            .and_then(|id| request_contexts.remove(id))
+            .unwrap_or_default();
+        
+        // Use the model from the request's DEBUG line, or fall back to path extraction
+        let model = context.model
+            .or_else(|| extract_model_from_path(&path))
+            .unwrap_or_else(|| "unknown".to_string());
         
         // Determine provider from model first (more accurate), fallback to path-based detection
         let model_provider = detect_provider_from_model(&model);
         let provider = if model_provider != "unknown" {
             model_provider
         } else {
-            detect_provider_from_path(&path).unwrap_or_else(|| "unknown".to_string())
+            detect_provider_from_path(&path).unwrap_or_else(|| endpoint.protocol.clone())
         };
         
-        // Generate unique ID
-        let count = request_counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        let id = format!("req_{}_{}", timestamp, count);
+        // Classify failures using the lines logged under this request ID
+        let error_category = classify_failure(status, &context.lines).map(str::to_string);
+        let error_message = error_category.as_ref().and_then(|_| failure_message(&context.lines));
+        
+        // Deterministic ID so replayed lines de-duplicate
+        let id = stable_request_id(timestamp, request_id.as_deref(), line);
         
         return Some(RequestLog {
             id,
+            request_id,
             timestamp,
             provider,
             model,
@@ -386,6 +497,12 @@ fn parse_gin_log_line(line: &str, request_counter: &AtomicU64, model_cache: &std
             tokens_in: None,
             tokens_out: None,
             tokens_cached: None,
+            api_key: None,
+            endpoint_kind: endpoint.kind,
+            account: context.account,
+            api_key_index: context.api_key_index,
+            error_category,
+            error_message,
         });
     }
     
@@ -398,16 +515,14 @@ fn parse_gin_log_line(line: &str, request_counter: &AtomicU64, model_cache: &std
     let duration_str = captures.get(4)?.as_str(); // 6.656s or 65ms
     let method = captures.get(5)?.as_str().to_string();
     let path = captures.get(6)?.as_str().to_string();
+    let endpoint = endpoints.classify(&path)?;
     
     // Parse timestamp
     let datetime_str = format!("{} {}", date_str.replace('/', "-"), time_str);
     let timestamp = chrono::NaiveDateTime::parse_from_str(&datetime_str, "%Y-%m-%d %H:%M:%S")
         .ok()
-        .map(|dt| dt.and_local_timezone(chrono::Local).unwrap().timestamp_millis() as u64)
-        .unwrap_or_else(|| std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64);
+        .and_then(local_log_time_to_utc)
+        .unwrap_or_else(now_ms);
     
     // Parse duration to milliseconds
     let duration_ms = parse_duration(duration_str);
@@ -423,15 +538,15 @@ fn parse_gin_log_line(line: &str, request_counter: &AtomicU64, model_cache: &std
     let provider = if model_provider != "unknown" {
         model_provider
     } else {
-        detect_provider_from_path(&path).unwrap_or_else(|| "unknown".to_string())
+        detect_provider_from_path(&path).unwrap_or_else(|| endpoint.protocol.clone())
     };
     
-    let id = request_counter.fetch_add(1, Ordering::SeqCst);
-    // Use timestamp + counter for unique ID (survives app restarts)
-    let unique_id = format!("req_{}_{}", timestamp, id);
+    // Old GIN lines carry no request ID, so the ID is derived from the line's content
+    let id = stable_request_id(timestamp, None, line);
     
     Some(RequestLog {
-        id: unique_id,
+        id,
+        request_id: None,
         timestamp,
         provider,
         model,
@@ -439,9 +554,15 @@ fn parse_gin_log_line(line: &str, request_counter: &AtomicU64, model_cache: &std
         path,
         status,
         duration_ms,
-        tokens_in: None,  // Not available from GIN logs
+        tokens_in: None,  // Not in GIN logs; backfilled from usage details (reconcile_request_tokens)
         tokens_out: None, // Not available from GIN logs
         tokens_cached: None, // Not available from GIN logs
+        api_key: None,
+        endpoint_kind: endpoint.kind,
+        account: None,
+        api_key_index: None,
+        error_category: classify_failure(status, &[]).map(str::to_string),
+        error_message: None,
     })
 }
 
@@ -450,12 +571,18 @@ fn start_log_watcher(
     app_handle: tauri::AppHandle,
     log_path: std::path::PathBuf,
     running: Arc<AtomicBool>,
-    request_counter: Arc<AtomicU64>,
 ) {
     std::thread::spawn(move || {
-        // Model cache to associate request IDs with model names from DEBUG lines
-        let model_cache: std::sync::RwLock<std::collections::HashMap<String, String>> = 
-            std::sync::RwLock::new(std::collections::HashMap::new());
+        // Endpoints to track (built-ins plus user-defined paths from config), and the
+        // configured API keys so key-based routes can be attributed to a key.
+        // Both are rebuilt whenever the config is saved.
+        let mut config_revision = crate::config::config_revision();
+        let config = load_config();
+        let mut api_keys = ApiKeyDirectory::from_config(&config);
+        let mut endpoints = EndpointTable::new(config.custom_tracked_endpoints);
+        
+        // Per-request context (model, ...) gathered from DEBUG lines, keyed by request ID
+        let mut request_contexts: LruCache<String, RequestContext> = LruCache::new(2000);
         
         // Wait for log file to exist
         let mut attempts = 0;
@@ -469,104 +596,118 @@ fn start_log_watcher(
             return;
         }
         
-        // Open file and seek to end (only watch new entries)
-        let file = match std::fs::File::open(&log_path) {
-            Ok(f) => f,
+        // Resume from the persisted cursor so requests logged while we weren't
+        // watching (restarts, rotations) still make it into analytics
+        let mut tailer = match crate::proxy::log_tail::LogTailer::open(
+            log_path.clone(),
+            crate::proxy::log_tail::load_cursor(),
+        ) {
+            Ok(t) => t,
             Err(e) => {
                 eprintln!("[LogWatcher] Failed to open log file: {}", e);
                 return;
             }
         };
         
-        let mut reader = BufReader::new(file);
-        // Seek to end to only process new lines
-        if let Err(e) = reader.seek(SeekFrom::End(0)) {
-            eprintln!("[LogWatcher] Failed to seek to end: {}", e);
-            return;
-        }
-        
-        // Track file position
-        let mut last_pos = reader.stream_position().unwrap_or(0);
-        
         println!("[LogWatcher] Started watching: {:?}", log_path);
         
+        // The cursor is written at most every few seconds while the log moves, and on stop
+        let mut cursor_dirty = false;
+        let mut cursor_saved_at = std::time::Instant::now();
+        
         // Poll for new content (more reliable than notify for log files)
         while running.load(Ordering::SeqCst) {
             std::thread::sleep(std::time::Duration::from_millis(500));
             
-            // Check if file has grown
-            let current_size = std::fs::metadata(&log_path)
-                .map(|m| m.len())
-                .unwrap_or(last_pos);
-            
-            if current_size <= last_pos {
-                // File might have been rotated, reset
-                if current_size < last_pos {
-                    last_pos = 0;
-                    if let Err(e) = reader.seek(SeekFrom::Start(0)) {
-                        eprintln!("[LogWatcher] Failed to seek after rotation: {}", e);
-                        continue;
-                    }
-                }
-                continue;
+            if crate::config::config_revision() != config_revision {
+                config_revision = crate::config::config_revision();
+                let config = load_config();
+                api_keys = ApiKeyDirectory::from_config(&config);
+                endpoints = EndpointTable::new(config.custom_tracked_endpoints);
             }
             
-            // Read new lines
-            let mut line = String::new();
-            while reader.read_line(&mut line).unwrap_or(0) > 0 {
-                if let Some(request_log) = parse_gin_log_line(&line, &request_counter, &model_cache) {
-                    // Emit to frontend for live display
-                    let _ = app_handle.emit("request-log", request_log.clone());
+            // Read new lines (follows rotation and truncation)
+            let mut ingested = false;
+            let poll_result = tailer.poll(|line| {
+                if let Some(request_log) = parse_gin_log_line(line, &mut request_contexts, &endpoints, &api_keys) {
+                    // Privacy mode: keep reading (so the cursor moves on) but record nothing
+                    let (privacy_mode, retention, tz, otlp_enabled) = {
+                        let config = app_handle.state::<AppState>().config.lock().unwrap();
+                        (
+                            config.privacy_mode,
+                            config.usage_retention.clone(),
+                            DisplayTimezone::from_setting(&config.display_timezone),
+                            config.otlp.enabled,
+                        )
+                    };
+                    if privacy_mode {
+                        return;
+                    }
                     
                     // Persist to history (without token data for now)
                     let mut history = load_request_history();
                     
-                    // Check for duplicate by timestamp and path
-                    let is_duplicate = history.requests.iter().any(|r| 
-                        r.timestamp == request_log.timestamp && r.path == request_log.path
-                    );
+                    // IDs are derived from the upstream request ID (or line content), so a
+                    // replayed line maps to the same ID and the store rejects it
+                    let is_duplicate = match app_handle.try_state::<RequestStore>() {
+                        Some(store) => match store.insert(&request_log) {
+                            Ok(inserted) => !inserted,
+                            Err(e) => {
+                                eprintln!("[LogWatcher] Failed to store request: {}", e);
+                                history.requests.iter().any(|r| r.id == request_log.id)
+                            }
+                        },
+                        None => history.requests.iter().any(|r| r.id == request_log.id),
+                    };
                     
                     if !is_duplicate {
+                        ingested = true;
+                        
+                        // Emit to frontend for live display
+                        let _ = app_handle.emit("request-log", request_log.clone());
+                        if otlp_enabled {
+                            crate::otlp::record(&request_log);
+                        }
+                        
                         // Load aggregate for cumulative stats
                         let mut agg = load_aggregate();
                         
-                        // Update aggregate counters
-                        agg.total_requests += 1;
+                        // Count the request on the watcher's side, then re-derive the headline totals
+                        let watcher = &mut crate::usage_merge::sources_mut(&mut agg).watcher;
+                        watcher.requests += 1;
                         if request_log.status < 400 {
-                            agg.total_success_count += 1;
+                            watcher.success_count += 1;
                         } else {
-                            agg.total_failure_count += 1;
+                            watcher.failure_count += 1;
+                        }
+                        watcher.cost_usd += crate::pricing::request_cost(&request_log);
+                        crate::usage_merge::merge_sources(&mut agg);
+                        if let Some(category) = &request_log.error_category {
+                            *agg.error_stats.entry(category.clone()).or_insert(0) += 1;
                         }
-                        agg.total_tokens_in += request_log.tokens_in.unwrap_or(0) as u64;
-                        agg.total_tokens_out += request_log.tokens_out.unwrap_or(0) as u64;
-                        agg.total_tokens_cached += request_log.tokens_cached.unwrap_or(0) as u64;
                         
-                        // Update time-series (today's date and current hour)
-                        let now = chrono::Local::now();
-                        let today = now.format("%Y-%m-%d").to_string();
-                        let hour_label = now.format("%Y-%m-%dT%H").to_string();
+                        // Update time-series, bucketed by the request's own time in the display zone
+                        let day_label = tz.day_label(request_log.timestamp);
+                        let hour_label = tz.hour_label(request_log.timestamp);
                         
                         // Update daily data
-                        update_timeseries(&mut agg.requests_by_day, &today, 1);
+                        update_timeseries(&mut agg.requests_by_day, &day_label, 1);
                         let tokens = (request_log.tokens_in.unwrap_or(0) + request_log.tokens_out.unwrap_or(0)) as u64;
-                        update_timeseries(&mut agg.tokens_by_day, &today, tokens);
+                        update_timeseries(&mut agg.tokens_by_day, &day_label, tokens);
                         
                         // Update hourly data (for Activity Patterns heatmap)
                         update_timeseries(&mut agg.requests_by_hour, &hour_label, 1);
                         update_timeseries(&mut agg.tokens_by_hour, &hour_label, tokens);
                         
-                        // Trim hourly data to keep last 7 days worth (168 hours)
-                        const MAX_HOURLY_POINTS: usize = 168;
-                        if agg.requests_by_hour.len() > MAX_HOURLY_POINTS {
-                            agg.requests_by_hour = agg.requests_by_hour.split_off(agg.requests_by_hour.len() - MAX_HOURLY_POINTS);
-                        }
-                        if agg.tokens_by_hour.len() > MAX_HOURLY_POINTS {
-                            agg.tokens_by_hour = agg.tokens_by_hour.split_off(agg.tokens_by_hour.len() - MAX_HOURLY_POINTS);
-                        }
+                        // Drop hourly points past retention, roll old days into months
+                        crate::retention::apply_series_retention(&mut agg, &retention, now_ms(), &tz);
                         
                         // Update model/provider stats
                         update_model_stats(&mut agg, &request_log);
                         update_provider_stats(&mut agg, &request_log);
+                        update_endpoint_stats(&mut agg, &request_log);
+                        update_account_stats(&mut agg, &request_log);
+                        update_latency_stats(&mut agg, &request_log, &tz);
                         
                         // Update history (keep only last 500 for UI display)
                         history.requests.push(request_log);
@@ -583,12 +724,30 @@ fn start_log_watcher(
                         }
                     }
                 }
-                line.clear();
+            });
+            
+            if ingested {
+                tauri::async_runtime::spawn(crate::budgets::evaluate(app_handle.clone()));
             }
             
-            last_pos = reader.stream_position().unwrap_or(last_pos);
+            match poll_result {
+                Ok(moved) => cursor_dirty |= moved,
+                Err(e) => eprintln!("[LogWatcher] Failed to read log file: {}", e),
+            }
+            if cursor_dirty && cursor_saved_at.elapsed() >= crate::proxy::log_tail::CURSOR_SAVE_INTERVAL {
+                match crate::proxy::log_tail::save_cursor(&tailer.cursor()) {
+                    Ok(()) => cursor_dirty = false,
+                    Err(e) => eprintln!("[LogWatcher] Failed to save log cursor: {}", e),
+                }
+                cursor_saved_at = std::time::Instant::now();
+            }
         }
         
+        if cursor_dirty {
+            if let Err(e) = crate::proxy::log_tail::save_cursor(&tailer.cursor()) {
+                eprintln!("[LogWatcher] Failed to save log cursor: {}", e);
+            }
+        }
         println!("[LogWatcher] Stopped watching");
     });
 }
@@ -803,12 +962,17 @@ async fn start_proxy(
         section
     };
     
+    // Keys suspended by an enforcing budget stay out until its period resets. Vertex keys
+    // aren't written here (they only live in the proxy), so they're suspended there alone.
+    let suspended_keys = crate::budgets::suspended_api_keys();
+    let is_suspended = |endpoint: &str, key: &str| suspended_keys.contains(&(endpoint.to_string(), key.to_string()));
+    
     // Build claude-api-key section combining copilot and user's persisted keys
     let claude_api_key_section = {
         let mut entries: Vec<String> = Vec::new();
         
         // Add user's persisted Claude API keys only
-        for key in &config.claude_api_keys {
+        for key in config.claude_api_keys.iter().filter(|k| !is_suspended("claude-api-key", &k.api_key)) {
             let mut entry = String::new();
             entry.push_str(&format!("  - api-key: \"{}\"\n", key.api_key));
             if let Some(ref base_url) = key.base_url {
@@ -839,7 +1003,7 @@ async fn start_proxy(
         String::new()
     } else {
         let mut section = String::from("# Gemini API keys\ngemini-api-key:\n");
-        for key in &config.gemini_api_keys {
+        for key in config.gemini_api_keys.iter().filter(|k| !is_suspended("gemini-api-key", &k.api_key)) {
             section.push_str(&format!("  - api-key: \"{}\"\n", key.api_key));
             if let Some(ref base_url) = key.base_url {
                 section.push_str(&format!("    base-url: \"{}\"\n", base_url));
@@ -859,7 +1023,7 @@ async fn start_proxy(
         String::new()
     } else {
         let mut section = String::from("# Codex API keys\ncodex-api-key:\n");
-        for key in &config.codex_api_keys {
+        for key in config.codex_api_keys.iter().filter(|k| !is_suspended("codex-api-key", &k.api_key)) {
             section.push_str(&format!("  - api-key: \"{}\"\n", key.api_key));
             if let Some(ref base_url) = key.base_url {
                 section.push_str(&format!("    base-url: \"{}\"\n", base_url));
@@ -1081,6 +1245,10 @@ ws-auth: {}
                     println!("[CLIProxyAPI] Process terminated: {:?}", payload);
                     // Update status when process dies unexpectedly
                     if let Some(state) = app_handle.try_state::<AppState>() {
+                        // stop_proxy clears the watcher flag before killing the process
+                        if state.log_watcher_running.load(Ordering::SeqCst) {
+                            crate::alerts::record_sidecar_exit();
+                        }
                         let mut status = state.proxy_status.lock().unwrap();
                         status.running = false;
                         let _ = app_handle.emit("proxy-status-changed", status.clone());
@@ -1124,11 +1292,13 @@ ws-auth: {}
         .send()
         .await;
     
+    // Bring back usage statistics from the previous run
+    restore_proxy_usage(port).await;
+    
     // Start log file watcher for request tracking
     // This replaces the old polling approach and captures ALL requests including Amp proxy forwarding
     let log_path = config_dir.join("logs").join("main.log");
     let log_watcher_running = state.log_watcher_running.clone();
-    let request_counter = state.request_counter.clone();
     
     // Signal any existing watcher to stop, then start new one
     log_watcher_running.store(false, Ordering::SeqCst);
@@ -1136,7 +1306,7 @@ ws-auth: {}
     log_watcher_running.store(true, Ordering::SeqCst);
     
     let app_handle2 = app.clone();
-    start_log_watcher(app_handle2, log_path, log_watcher_running, request_counter);
+    start_log_watcher(app_handle2, log_path, log_watcher_running);
     
     // Sync usage statistics from proxy to local history on startup (in background)
     // This ensures analytics page shows data without requiring restart or manual refresh
@@ -1184,6 +1354,12 @@ async fn stop_proxy(
     // Stop the log watcher
     state.log_watcher_running.store(false, Ordering::SeqCst);
 
+    // Snapshot usage statistics before they're lost with the process
+    let port = state.proxy_status.lock().unwrap().port;
+    if let Err(e) = snapshot_proxy_usage(&app, port).await {
+        eprintln!("[UsageSnapshot] Snapshot on stop failed: {}", e);
+    }
+
     // Kill the tracked child process
     {
         let mut process = state.proxy_process.lock().unwrap();
@@ -2147,6 +2323,153 @@ fn get_auth_status(state: State<AppState>) -> AuthStatus {
     state.auth_status.lock().unwrap().clone()
 }
 
+// Registered account providers, in display order
+#[tauri::command]
+fn get_providers() -> Vec<providers::ProviderDescriptor> {
+    providers::PROVIDERS.to_vec()
+}
+
+// Time zone for day/hour buckets, from the display_timezone setting
+fn display_timezone(state: &AppState) -> DisplayTimezone {
+    DisplayTimezone::from_setting(&state.config.lock().unwrap().display_timezone)
+}
+
+// Day/hour series from a CLIProxyAPI usage snapshot
+struct SnapshotSeries {
+    requests_by_day: Vec<TimeSeriesPoint>,
+    tokens_by_day: Vec<TimeSeriesPoint>,
+    requests_by_hour: Vec<TimeSeriesPoint>,
+    tokens_by_hour: Vec<TimeSeriesPoint>,
+}
+
+// Bucket a usage snapshot by each request's timestamp in the display time zone.
+// CLIProxyAPI's own "tokens_by_hour" keys are hour-of-day ("HH") totals across all
+// days, so they can't be placed on a date; its day keys are only used as a fallback
+// when the details carry no timestamps.
+fn usage_series_from_snapshot(usage: &serde_json::Value, tz: &DisplayTimezone) -> SnapshotSeries {
+    let mut requests_by_day: std::collections::BTreeMap<String, u64> = std::collections::BTreeMap::new();
+    let mut tokens_by_day: std::collections::BTreeMap<String, u64> = std::collections::BTreeMap::new();
+    let mut requests_by_hour: std::collections::BTreeMap<String, u64> = std::collections::BTreeMap::new();
+    let mut tokens_by_hour: std::collections::BTreeMap<String, u64> = std::collections::BTreeMap::new();
+    let mut timestamped = false;
+    
+    if let Some(apis) = usage.get("apis").and_then(|v| v.as_object()) {
+        for api_data in apis.values() {
+            let Some(models) = api_data.get("models").and_then(|v| v.as_object()) else {
+                continue;
+            };
+            for model_data in models.values() {
+                let Some(details) = model_data.get("details").and_then(|v| v.as_array()) else {
+                    continue;
+                };
+                for detail in details {
+                    let Some(timestamp) = detail.get("timestamp")
+                        .and_then(|v| v.as_str())
+                        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                        .map(|dt| dt.timestamp_millis() as u64) else {
+                        continue;
+                    };
+                    timestamped = true;
+                    
+                    let tokens = detail.get("tokens");
+                    let token_count = |key: &str| tokens.and_then(|t| t.get(key)).and_then(|v| v.as_u64()).unwrap_or(0);
+                    let total = match token_count("total_tokens") {
+                        0 => token_count("input_tokens") + token_count("output_tokens"),
+                        total => total,
+                    };
+                    
+                    let day = tz.day_label(timestamp);
+                    let hour = tz.hour_label(timestamp);
+                    *requests_by_day.entry(day.clone()).or_insert(0) += 1;
+                    *tokens_by_day.entry(day).or_insert(0) += total;
+                    *requests_by_hour.entry(hour.clone()).or_insert(0) += 1;
+                    *tokens_by_hour.entry(hour).or_insert(0) += total;
+                }
+            }
+        }
+    }
+    
+    if !timestamped {
+        for (key, series) in [("requests_by_day", &mut requests_by_day), ("tokens_by_day", &mut tokens_by_day)] {
+            if let Some(days) = usage.get(key).and_then(|v| v.as_object()) {
+                for (day, value) in days {
+                    if let Some(v) = value.as_u64() {
+                        series.insert(day.clone(), v);
+                    }
+                }
+            }
+        }
+    }
+    
+    let to_points = |series: std::collections::BTreeMap<String, u64>| -> Vec<TimeSeriesPoint> {
+        series.into_iter().map(|(label, value)| TimeSeriesPoint { label, value }).collect()
+    };
+    SnapshotSeries {
+        requests_by_day: to_points(requests_by_day),
+        tokens_by_day: to_points(tokens_by_day),
+        requests_by_hour: to_points(requests_by_hour),
+        tokens_by_hour: to_points(tokens_by_hour),
+    }
+}
+
+// Merge what CLIProxyAPI reports into the aggregate: series points and per-model stats
+// keep the larger value, and the proxy's totals feed the headline merge (see usage_merge).
+// Points older than retention keeps are skipped rather than merged and compacted again:
+// the aggregate already counts them in the coarser series.
+fn merge_proxy_usage(
+    agg: &mut Aggregate,
+    model_stats: &std::collections::HashMap<String, ModelStats>,
+    totals: SourceTotals,
+    series: &SnapshotSeries,
+    retention: &UsageRetention,
+    tz: &DisplayTimezone,
+) {
+    use crate::usage_merge::{merge_model_stats_max, merge_series_max, set_proxy_totals};
+    
+    let (hour_cutoff, day_cutoff) = crate::retention::series_cutoffs(retention, now_ms(), tz);
+    let retained = |points: &[TimeSeriesPoint], cutoff: &Option<String>| -> Vec<TimeSeriesPoint> {
+        points
+            .iter()
+            .filter(|p| cutoff.as_ref().map_or(true, |c| p.label.as_str() >= c.as_str()))
+            .cloned()
+            .collect()
+    };
+    merge_series_max(&mut agg.requests_by_day, &retained(&series.requests_by_day, &day_cutoff));
+    merge_series_max(&mut agg.tokens_by_day, &retained(&series.tokens_by_day, &day_cutoff));
+    merge_series_max(&mut agg.requests_by_hour, &retained(&series.requests_by_hour, &hour_cutoff));
+    merge_series_max(&mut agg.tokens_by_hour, &retained(&series.tokens_by_hour, &hour_cutoff));
+    
+    for (model, stats) in model_stats {
+        merge_model_stats_max(agg.model_stats.entry(model.clone()).or_default(), stats);
+    }
+    set_proxy_totals(agg, totals);
+}
+
+// Per-model stats and totals on the proxy's side. They come from the ledger (which
+// keeps earlier proxy runs); the snapshot itself is only used when the ledger isn't
+// being written.
+fn proxy_usage_totals(
+    state: &AppState,
+    store: &RequestStore,
+    usage: &serde_json::Value,
+) -> (std::collections::HashMap<String, ModelStats>, SourceTotals) {
+    use crate::usage_merge::{details_cost, model_stats_from_details, proxy_totals};
+    
+    if let Some(model_stats) = record_usage_ledger(state, store, usage) {
+        match store.usage_ledger_cost() {
+            Ok(cost) => {
+                let totals = proxy_totals(&model_stats, cost);
+                return (model_stats, totals);
+            }
+            Err(e) => eprintln!("[Pricing] Failed to price usage ledger: {}", e),
+        }
+    }
+    let details = crate::proxy::usage_snapshot::usage_details(usage);
+    let model_stats = model_stats_from_details(&details);
+    let totals = proxy_totals(&model_stats, details_cost(&details));
+    (model_stats, totals)
+}
+
 // Live usage data from Go backend
 struct LiveUsageData {
     total_tokens: u64,
@@ -2159,7 +2482,7 @@ struct LiveUsageData {
 }
 
 // Fetch live usage stats from Go backend (blocking version for sync context)
-fn fetch_live_usage_stats_blocking(port: u16) -> Option<LiveUsageData> {
+fn fetch_live_usage_stats_blocking(port: u16, tz: &DisplayTimezone) -> Option<LiveUsageData> {
     let url = format!("http://127.0.0.1:{}/v0/management/usage", port);
     let client = reqwest::blocking::Client::new();
     
@@ -2231,27 +2554,12 @@ fn fetch_live_usage_stats_blocking(port: u16) -> Option<LiveUsageData> {
         cached_tokens,
         model_tokens,
         model_token_breakdown,
-        tokens_by_hour: {
-            // Parse tokens_by_hour from Go backend: { "HH": value, ... }
-            let mut result = Vec::new();
-            if let Some(tbh) = usage.get("tokens_by_hour").and_then(|v| v.as_object()) {
-                let today = chrono::Local::now().format("%Y-%m-%d").to_string();
-                for (hour, value) in tbh {
-                    if let Some(v) = value.as_u64() {
-                        // Convert "HH" format to "YYYY-MM-DDTHH" format
-                        let label = format!("{}T{}", today, hour);
-                        result.push(TimeSeriesPoint { label, value: v });
-                    }
-                }
-                result.sort_by(|a, b| a.label.cmp(&b.label));
-            }
-            result
-        },
+        tokens_by_hour: usage_series_from_snapshot(usage, tz).tokens_by_hour,
     })
 }
 
 // Blocking version of sync_usage_from_proxy for use in sync contexts
-fn sync_usage_from_proxy_blocking(port: u16) {
+fn sync_usage_from_proxy_blocking(state: &AppState, store: &RequestStore, port: u16, tz: &DisplayTimezone) {
     let url = format!("http://127.0.0.1:{}/v0/management/usage", port);
     let client = reqwest::blocking::Client::new();
     
@@ -2277,177 +2585,28 @@ fn sync_usage_from_proxy_blocking(port: u16) {
         None => return,
     };
     
-    // Parse time-series data from CLIProxyAPI
-    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
-    
-    // Parse tokens_by_day
-    let mut tokens_by_day: Vec<TimeSeriesPoint> = Vec::new();
-    if let Some(tbd) = usage.get("tokens_by_day").and_then(|v| v.as_object()) {
-        for (day, value) in tbd {
-            if let Some(v) = value.as_u64() {
-                tokens_by_day.push(TimeSeriesPoint { label: day.clone(), value: v });
-            }
-        }
-        tokens_by_day.sort_by(|a, b| a.label.cmp(&b.label));
-    }
-    
-    // Parse tokens_by_hour with normalized format
-    let mut tokens_by_hour: Vec<TimeSeriesPoint> = Vec::new();
-    if let Some(tbh) = usage.get("tokens_by_hour").and_then(|v| v.as_object()) {
-        for (hour, value) in tbh {
-            if let Some(v) = value.as_u64() {
-                let label = if hour.len() == 2 {
-                    format!("{}T{}", today, hour)
-                } else {
-                    hour.clone()
-                };
-                tokens_by_hour.push(TimeSeriesPoint { label, value: v });
-            }
-        }
-        tokens_by_hour.sort_by(|a, b| a.label.cmp(&b.label));
-    }
-    
-    // Parse requests_by_day
-    let mut requests_by_day: Vec<TimeSeriesPoint> = Vec::new();
-    if let Some(rbd) = usage.get("requests_by_day").and_then(|v| v.as_object()) {
-        for (day, value) in rbd {
-            if let Some(v) = value.as_u64() {
-                requests_by_day.push(TimeSeriesPoint { label: day.clone(), value: v });
-            }
-        }
-        requests_by_day.sort_by(|a, b| a.label.cmp(&b.label));
-    }
-    
-    // Parse requests_by_hour with normalized format
-    let mut requests_by_hour: Vec<TimeSeriesPoint> = Vec::new();
-    if let Some(rbh) = usage.get("requests_by_hour").and_then(|v| v.as_object()) {
-        for (hour, value) in rbh {
-            if let Some(v) = value.as_u64() {
-                let label = if hour.len() == 2 {
-                    format!("{}T{}", today, hour)
-                } else {
-                    hour.clone()
-                };
-                requests_by_hour.push(TimeSeriesPoint { label, value: v });
-            }
-        }
-        requests_by_hour.sort_by(|a, b| a.label.cmp(&b.label));
-    }
-    
-    // Parse model stats and totals
-    let mut total_requests: u64 = 0;
-    let mut model_stats: std::collections::HashMap<String, ModelStats> = std::collections::HashMap::new();
+    let (model_stats, totals) = proxy_usage_totals(state, store, usage);
+    let series = usage_series_from_snapshot(usage, tz);
+    let retention = state.config.lock().unwrap().usage_retention.clone();
     
-    if let Some(apis) = usage.get("apis").and_then(|v| v.as_object()) {
-        for (_api_name, api_data) in apis {
-            if let Some(models) = api_data.get("models").and_then(|v| v.as_object()) {
-                for (model_name, model_data) in models {
-                    let model_requests = model_data.get("total_requests").and_then(|v| v.as_u64()).unwrap_or(0);
-                    let model_tokens = model_data.get("total_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
-                    
-                    total_requests += model_requests;
-                    
-                    let stats = model_stats.entry(model_name.clone()).or_insert_with(Default::default);
-                    stats.requests += model_requests;
-                    stats.tokens += model_tokens;
-                    stats.success_count += model_requests;
-                    
-                    // Parse token breakdown from details
-                    if let Some(details) = model_data.get("details").and_then(|v| v.as_array()) {
-                        for detail in details {
-                            if let Some(tokens) = detail.get("tokens") {
-                                stats.input_tokens += tokens.get("input_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
-                                stats.output_tokens += tokens.get("output_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
-                                stats.cached_tokens += tokens.get("cached_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-    
-    // Update aggregate
     let mut agg = load_aggregate();
-    
-    // Merge time-series data
-    for point in &tokens_by_day {
-        if let Some(existing) = agg.tokens_by_day.iter_mut().find(|p| p.label == point.label) {
-            existing.value = point.value;
-        } else {
-            agg.tokens_by_day.push(point.clone());
-        }
-    }
-    agg.tokens_by_day.sort_by(|a, b| a.label.cmp(&b.label));
-    
-    for point in &tokens_by_hour {
-        if let Some(existing) = agg.tokens_by_hour.iter_mut().find(|p| p.label == point.label) {
-            existing.value = point.value;
-        } else {
-            agg.tokens_by_hour.push(point.clone());
-        }
-    }
-    agg.tokens_by_hour.sort_by(|a, b| a.label.cmp(&b.label));
-    if agg.tokens_by_hour.len() > 168 {
-        agg.tokens_by_hour = agg.tokens_by_hour.split_off(agg.tokens_by_hour.len() - 168);
-    }
-    
-    for point in &requests_by_day {
-        if let Some(existing) = agg.requests_by_day.iter_mut().find(|p| p.label == point.label) {
-            existing.value = point.value;
-        } else {
-            agg.requests_by_day.push(point.clone());
-        }
-    }
-    agg.requests_by_day.sort_by(|a, b| a.label.cmp(&b.label));
-    
-    for point in &requests_by_hour {
-        if let Some(existing) = agg.requests_by_hour.iter_mut().find(|p| p.label == point.label) {
-            existing.value = point.value;
-        } else {
-            agg.requests_by_hour.push(point.clone());
-        }
-    }
-    agg.requests_by_hour.sort_by(|a, b| a.label.cmp(&b.label));
-    if agg.requests_by_hour.len() > 168 {
-        agg.requests_by_hour = agg.requests_by_hour.split_off(agg.requests_by_hour.len() - 168);
-    }
-    
-    // Update model stats
-    for (model_name, stats) in model_stats {
-        let agg_stats = agg.model_stats.entry(model_name).or_insert_with(Default::default);
-        agg_stats.requests = stats.requests;
-        agg_stats.success_count = stats.success_count;
-        agg_stats.tokens = stats.tokens;
-        agg_stats.input_tokens = stats.input_tokens;
-        agg_stats.output_tokens = stats.output_tokens;
-        agg_stats.cached_tokens = stats.cached_tokens;
-    }
-    
-    // Update totals
-    if total_requests > agg.total_requests {
-        agg.total_requests = total_requests;
-    }
-    let synced_success: u64 = agg.model_stats.values().map(|s| s.success_count).sum();
-    if synced_success > agg.total_success_count {
-        agg.total_success_count = synced_success;
-    }
-    
+    merge_proxy_usage(&mut agg, &model_stats, totals, &series, &retention, tz);
     let _ = save_aggregate(&agg);
 }
 
 // Compute usage statistics - fetches live data from Go backend when proxy is running
 #[tauri::command]
-fn get_usage_stats(state: State<'_, AppState>) -> Result<UsageStats, String> {
+fn get_usage_stats(state: State<'_, AppState>, store: State<'_, RequestStore>) -> Result<UsageStats, String> {
     // Get proxy status
     let (is_running, port) = {
         let status = state.proxy_status.lock().unwrap();
         (status.running, status.port)
     };
+    let tz = display_timezone(&state);
     
     // Sync from proxy first if running (this updates aggregate with latest data from CLIProxyAPI)
     if is_running {
-        sync_usage_from_proxy_blocking(port);
+        sync_usage_from_proxy_blocking(&state, &store, port, &tz);
     }
     
     // Now load the updated aggregate and history
@@ -2456,14 +2615,32 @@ fn get_usage_stats(state: State<'_, AppState>) -> Result<UsageStats, String> {
     
     // Try to fetch live data from Go backend if proxy is running
     let live_data = if is_running {
-        fetch_live_usage_stats_blocking(port)
+        fetch_live_usage_stats_blocking(port, &tz)
     } else {
         None
     };
     
     // Merge live data with aggregate
+    // Live counters restart with the proxy; never report less than the aggregate already holds
     let (total_tokens, input_tokens, output_tokens, cached_tokens, model_tokens, model_token_breakdown): (u64, u64, u64, u64, std::collections::HashMap<String, u64>, std::collections::HashMap<String, (u64, u64, u64)>) = if let Some(ref live) = live_data {
-        (live.total_tokens, live.input_tokens, live.output_tokens, live.cached_tokens, live.model_tokens.clone(), live.model_token_breakdown.clone())
+        let mut model_tokens = live.model_tokens.clone();
+        let mut model_token_breakdown = live.model_token_breakdown.clone();
+        for (model, stats) in &agg.model_stats {
+            let tokens = model_tokens.entry(model.clone()).or_insert(0);
+            *tokens = (*tokens).max(stats.tokens);
+            let breakdown = model_token_breakdown.entry(model.clone()).or_insert((0, 0, 0));
+            if stats.input_tokens + stats.output_tokens > breakdown.0 + breakdown.1 {
+                *breakdown = (stats.input_tokens, stats.output_tokens, stats.cached_tokens);
+            }
+        }
+        (
+            live.total_tokens.max(agg.total_tokens_in + agg.total_tokens_out),
+            live.input_tokens.max(agg.total_tokens_in),
+            live.output_tokens.max(agg.total_tokens_out),
+            live.cached_tokens.max(agg.total_tokens_cached),
+            model_tokens,
+            model_token_breakdown,
+        )
     } else {
         // Build model token breakdown from aggregate stats
         let agg_model_tokens: std::collections::HashMap<String, u64> = agg.model_stats.iter()
@@ -2486,7 +2663,7 @@ fn get_usage_stats(state: State<'_, AppState>) -> Result<UsageStats, String> {
     let failure_count = agg.total_failure_count;
     
     // Calculate today's stats from aggregate time-series
-    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let today = tz.day_label(now_ms());
     let requests_today = agg.requests_by_day.iter()
         .find(|p| p.label == today)
         .map(|p| p.value)
@@ -2585,10 +2762,7 @@ fn get_usage_stats(state: State<'_, AppState>) -> Result<UsageStats, String> {
         // Build from history as fallback for existing data
         let mut requests_by_hour_map: std::collections::HashMap<String, u64> = std::collections::HashMap::new();
         for req in &history.requests {
-            if let Some(dt) = chrono::DateTime::from_timestamp_millis(req.timestamp as i64) {
-                let hour_label = dt.format("%Y-%m-%dT%H").to_string();
-                *requests_by_hour_map.entry(hour_label).or_insert(0) += 1;
-            }
+            *requests_by_hour_map.entry(tz.hour_label(req.timestamp)).or_insert(0) += 1;
         }
         requests_by_hour_map.into_iter()
             .map(|(label, value)| TimeSeriesPoint { label, value })
@@ -2610,11 +2784,8 @@ fn get_usage_stats(state: State<'_, AppState>) -> Result<UsageStats, String> {
             // Build from history as fallback
             let mut tokens_by_hour_map: std::collections::HashMap<String, u64> = std::collections::HashMap::new();
             for req in &history.requests {
-                if let Some(dt) = chrono::DateTime::from_timestamp_millis(req.timestamp as i64) {
-                    let hour_label = dt.format("%Y-%m-%dT%H").to_string();
-                    let tokens = (req.tokens_in.unwrap_or(0) + req.tokens_out.unwrap_or(0)) as u64;
-                    *tokens_by_hour_map.entry(hour_label).or_insert(0) += tokens;
-                }
+                let tokens = (req.tokens_in.unwrap_or(0) + req.tokens_out.unwrap_or(0)) as u64;
+                *tokens_by_hour_map.entry(tz.hour_label(req.timestamp)).or_insert(0) += tokens;
             }
             tokens_by_hour_map.into_iter()
                 .map(|(label, value)| TimeSeriesPoint { label, value })
@@ -2647,62 +2818,536 @@ fn get_usage_stats(state: State<'_, AppState>) -> Result<UsageStats, String> {
     })
 }
 
-// Get request history
+// Get request history
+#[tauri::command]
+fn get_request_history() -> RequestHistory {
+    load_request_history()
+}
+
+// Add a request to history (called when request-log event is emitted)
+// Returns only the added request to minimize data transfer (memory optimization)
+#[tauri::command]
+fn add_request_to_history(
+    state: State<'_, AppState>,
+    store: State<'_, RequestStore>,
+    request: RequestLog,
+) -> Result<RequestLog, String> {
+    if state.config.lock().unwrap().privacy_mode {
+        return Ok(request);
+    }
+    
+    // Client API keys are only kept as fingerprints, here as in the store
+    let mut request = request;
+    request.api_key = request.api_key.as_deref().map(crate::request_store::api_key_fingerprint);
+    
+    let mut history = load_request_history();
+    
+    // Calculate cost for this request
+    let tokens_in = request.tokens_in.unwrap_or(0);
+    let tokens_out = request.tokens_out.unwrap_or(0);
+    let cost = crate::pricing::request_cost(&request);
+    let tokens_cached = request.tokens_cached.unwrap_or(0);
+    
+    // Update totals
+    history.total_tokens_in += tokens_in as u64;
+    history.total_tokens_out += tokens_out as u64;
+    history.total_tokens_cached += tokens_cached as u64;
+    history.total_cost_usd += cost;
+    
+    // Add request (with deduplication check)
+    // Check if request with same ID already exists to prevent duplicates
+    let request_clone = request.clone();
+    store.insert(&request)?;
+    if !history.requests.iter().any(|r| r.id == request.id) {
+        history.requests.push(request);
+        
+        // Trim to prevent unbounded growth (keep last 500 requests)
+        const MAX_HISTORY_SIZE: usize = 500;
+        if history.requests.len() > MAX_HISTORY_SIZE {
+            let excess = history.requests.len() - MAX_HISTORY_SIZE;
+            history.requests.drain(0..excess);
+        }
+    }
+    
+    // Save
+    save_request_history(&history)?;
+    
+    // Return only the added request, not the full history
+    Ok(request_clone)
+}
+
+// Clear request history
+#[tauri::command]
+fn clear_request_history(store: State<'_, RequestStore>) -> Result<(), String> {
+    store.clear()?;
+    let history = RequestHistory::default();
+    save_request_history(&history)
+}
+
+// Roll raw requests past retention into the store's baseline and apply series
+// retention to aggregate.json
+fn run_usage_compaction(
+    store: &RequestStore,
+    retention: &UsageRetention,
+    tz: &DisplayTimezone,
+) -> Result<CompactionSummary, String> {
+    let now = now_ms();
+    
+    let requests_rolled_up = if retention.raw_days > 0 {
+        let before = now.saturating_sub(retention.raw_days as u64 * 24 * 60 * 60 * 1000);
+        store.roll_up_before(before, retention, tz)?
+    } else {
+        0
+    };
+    
+    let mut agg = load_aggregate();
+    let summary = crate::retention::apply_series_retention(&mut agg, retention, now, tz);
+    save_aggregate(&agg)?;
+    
+    Ok(CompactionSummary { requests_rolled_up, ..summary })
+}
+
+// Run compaction shortly after startup and then every few hours
+fn start_usage_compaction(app_handle: tauri::AppHandle) {
+    const INITIAL_DELAY: std::time::Duration = std::time::Duration::from_secs(60);
+    const INTERVAL: std::time::Duration = std::time::Duration::from_secs(6 * 60 * 60);
+    
+    std::thread::spawn(move || {
+        std::thread::sleep(INITIAL_DELAY);
+        loop {
+            let state = app_handle.state::<AppState>();
+            let retention = state.config.lock().unwrap().usage_retention.clone();
+            let tz = display_timezone(&state);
+            let store = app_handle.state::<RequestStore>();
+            match run_usage_compaction(&store, &retention, &tz) {
+                Ok(summary) if summary.requests_rolled_up > 0 || summary.daily_points_rolled_up > 0 => {
+                    println!("[Retention] Compacted usage data: {:?}", summary);
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("[Retention] Compaction failed: {}", e),
+            }
+            std::thread::sleep(INTERVAL);
+        }
+    });
+}
+
+// Record CLIProxyAPI usage details in the request store's ledger and return the
+// cumulative per-model totals. None in privacy mode or if the ledger is unavailable.
+fn record_usage_ledger(
+    state: &AppState,
+    store: &RequestStore,
+    usage: &serde_json::Value,
+) -> Option<std::collections::HashMap<String, ModelStats>> {
+    if state.config.lock().unwrap().privacy_mode {
+        return None;
+    }
+    let details = crate::proxy::usage_snapshot::usage_details(usage);
+    if let Err(e) = store.record_usage(&details) {
+        eprintln!("[UsageSnapshot] Failed to record usage ledger: {}", e);
+        return None;
+    }
+    if let Err(e) = reconcile_request_tokens(store) {
+        eprintln!("[UsageSnapshot] Failed to backfill request tokens: {}", e);
+    }
+    match store.usage_ledger_totals() {
+        Ok(totals) => Some(totals),
+        Err(e) => {
+            eprintln!("[UsageSnapshot] Failed to read usage ledger: {}", e);
+            None
+        }
+    }
+}
+
+// Fill in token counts of recently logged requests from the usage ledger. Matched
+// requests are updated in the store and history.json, and their tokens are added to
+// the watcher's totals and the per-provider/endpoint/account stats.
+fn reconcile_request_tokens(store: &RequestStore) -> Result<UsageReconcileSummary, String> {
+    const WINDOW_MS: u64 = 24 * 60 * 60 * 1000;
+    
+    let (summary, updated) = store.reconcile_usage(now_ms().saturating_sub(WINDOW_MS))?;
+    if updated.is_empty() {
+        return Ok(summary);
+    }
+    
+    let mut history = load_request_history();
+    for req in &updated {
+        if let Some(entry) = history.requests.iter_mut().find(|r| r.id == req.id) {
+            entry.tokens_in = req.tokens_in;
+            entry.tokens_out = req.tokens_out;
+            entry.tokens_cached = req.tokens_cached;
+        }
+        history.total_tokens_in += req.tokens_in.unwrap_or(0) as u64;
+        history.total_tokens_out += req.tokens_out.unwrap_or(0) as u64;
+        history.total_tokens_cached += req.tokens_cached.unwrap_or(0) as u64;
+        history.total_cost_usd += crate::pricing::request_cost(req);
+    }
+    save_request_history(&history)?;
+    
+    let tz = DisplayTimezone::from_setting(&load_config().display_timezone);
+    let mut agg = load_aggregate();
+    for req in &updated {
+        add_backfilled_tokens(&mut agg, req, &tz);
+    }
+    crate::usage_merge::merge_sources(&mut agg);
+    save_aggregate(&agg)?;
+    
+    println!("[UsageSnapshot] Backfilled tokens for {} requests ({} still unmatched)", summary.matched, summary.unmatched_requests);
+    Ok(summary)
+}
+
+// Backfill request tokens now (also runs whenever usage is synced from the proxy)
+#[tauri::command]
+fn backfill_request_tokens(store: State<'_, RequestStore>) -> Result<UsageReconcileSummary, String> {
+    reconcile_request_tokens(&store)
+}
+
+// Save CLIProxyAPI's usage export to disk and record it in the ledger.
+// Returns the number of new ledger entries.
+async fn snapshot_proxy_usage(app_handle: &tauri::AppHandle, port: u16) -> Result<u64, String> {
+    let state = app_handle.state::<AppState>();
+    if state.config.lock().unwrap().privacy_mode {
+        return Ok(0);
+    }
+    
+    let export = crate::proxy::usage_snapshot::fetch_export(port).await?;
+    let usage = export.get("usage").ok_or("Missing 'usage' field in export")?;
+    let details = crate::proxy::usage_snapshot::usage_details(usage);
+    let store = app_handle.state::<RequestStore>();
+    let added = store.record_usage(&details)?;
+    reconcile_request_tokens(&store)?;
+    crate::proxy::usage_snapshot::save_snapshot(&export)?;
+    Ok(added)
+}
+
+// Re-import the last saved usage snapshot into a freshly started proxy
+async fn restore_proxy_usage(port: u16) {
+    let Some(snapshot) = crate::proxy::usage_snapshot::load_snapshot() else {
+        return;
+    };
+    match crate::proxy::usage_snapshot::import_export(port, &snapshot).await {
+        Ok(result) => println!("[UsageSnapshot] Restored usage snapshot: {}", result),
+        Err(e) => eprintln!("[UsageSnapshot] Failed to restore usage snapshot: {}", e),
+    }
+}
+
+// Snapshot the proxy's usage statistics every few minutes while it's running
+fn start_usage_snapshots(app_handle: tauri::AppHandle) {
+    const INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+    
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(INTERVAL).await;
+            let (running, port) = {
+                let state = app_handle.state::<AppState>();
+                let status = state.proxy_status.lock().unwrap();
+                (status.running, status.port)
+            };
+            if !running {
+                continue;
+            }
+            if let Err(e) = snapshot_proxy_usage(&app_handle, port).await {
+                eprintln!("[UsageSnapshot] Periodic snapshot failed: {}", e);
+            }
+        }
+    });
+}
+
+// Re-evaluate budgets every minute: tokens backfilled from usage snapshots count
+// towards them, and suspensions are lifted when a period resets
+fn start_budget_monitor(app_handle: tauri::AppHandle) {
+    const INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+    
+    tauri::async_runtime::spawn(async move {
+        loop {
+            crate::budgets::evaluate(app_handle.clone()).await;
+            tokio::time::sleep(INTERVAL).await;
+        }
+    });
+}
+
+// Apply retention now (also runs periodically in the background)
+#[tauri::command]
+fn compact_usage_data(state: State<'_, AppState>, store: State<'_, RequestStore>) -> Result<CompactionSummary, String> {
+    let retention = state.config.lock().unwrap().usage_retention.clone();
+    run_usage_compaction(&store, &retention, &display_timezone(&state))
+}
+
+// Permanently delete usage data from before `before` (ms): stored requests, history
+// entries and time-series points. Cumulative totals in aggregate.json are left as is.
+#[tauri::command]
+fn purge_usage_data(state: State<'_, AppState>, store: State<'_, RequestStore>, before: u64) -> Result<u64, String> {
+    let tz = display_timezone(&state);
+    let deleted = store.purge_before(before, &tz)?;
+    
+    let mut agg = load_aggregate();
+    crate::retention::prune_series_before(&mut agg, before, &tz);
+    save_aggregate(&agg)?;
+    
+    let mut history = load_request_history();
+    history.requests.retain(|r| r.timestamp >= before);
+    save_request_history(&history)?;
+    
+    Ok(deleted)
+}
+
+// Query the full request log with filters, time range, sorting and cursor pagination
+#[tauri::command]
+fn query_requests(store: State<'_, RequestStore>, query: RequestQuery) -> Result<RequestPage, String> {
+    store.query(&query)
+}
+
+// Per-account usage breakdown (OAuth accounts and API keys), optionally limited to a time range
+#[tauri::command]
+fn get_account_usage(store: State<'_, RequestStore>, from: Option<u64>, to: Option<u64>) -> Result<Vec<AccountUsage>, String> {
+    store.account_usage(from, to)
+}
+
+// Failure counts over time per error category, grouped by provider, model or account
+#[tauri::command]
+fn get_error_series(
+    state: State<'_, AppState>,
+    store: State<'_, RequestStore>,
+    group_by: String,
+    bucket: Option<String>,
+    from: Option<u64>,
+    to: Option<u64>,
+) -> Result<Vec<ErrorSeriesPoint>, String> {
+    store.error_series(&group_by, bucket.as_deref().unwrap_or("day"), from, to, &display_timezone(&state))
+}
+
+// Most frequent recent failure causes, with example log lines
+#[tauri::command]
+fn get_top_errors(
+    store: State<'_, RequestStore>,
+    since: Option<u64>,
+    limit: Option<u32>,
+) -> Result<Vec<ErrorCause>, String> {
+    let since = since.unwrap_or_else(|| {
+        // Default to the last 24 hours
+        (chrono::Utc::now() - chrono::Duration::hours(24)).timestamp_millis() as u64
+    });
+    store.top_errors(since, limit.unwrap_or(10))
+}
+
+// Usage over time grouped by model, provider or account, optionally compared
+// with the preceding period of the same length (e.g. this week vs. last week)
+#[tauri::command]
+fn get_usage_series(
+    state: State<'_, AppState>,
+    store: State<'_, RequestStore>,
+    query: UsageSeriesQuery,
+) -> Result<UsageSeries, String> {
+    if query.to <= query.from {
+        return Err("Invalid range: 'to' must be after 'from'".to_string());
+    }
+    let bucket = query.bucket.as_deref().unwrap_or("day");
+    let tz = display_timezone(&state);
+    
+    let current = store.usage_series(&query.group_by, bucket, query.from, query.to, &tz)?;
+    let previous = if query.compare_previous {
+        let length = query.to - query.from;
+        let previous_from = query.from.saturating_sub(length);
+        Some(store.usage_series(&query.group_by, bucket, previous_from, query.from, &tz)?)
+    } else {
+        None
+    };
+    
+    let mut totals: Vec<UsageGroupTotal> = Vec::new();
+    let total_for = |totals: &mut Vec<UsageGroupTotal>, group: &str| -> usize {
+        match totals.iter().position(|t| t.group == group) {
+            Some(i) => i,
+            None => {
+                totals.push(UsageGroupTotal {
+                    group: group.to_string(),
+                    previous_requests: previous.as_ref().map(|_| 0),
+                    previous_tokens: previous.as_ref().map(|_| 0),
+                    previous_cost_usd: previous.as_ref().map(|_| 0.0),
+                    ..Default::default()
+                });
+                totals.len() - 1
+            }
+        }
+    };
+    for point in &current {
+        let i = total_for(&mut totals, &point.group);
+        totals[i].requests += point.requests;
+        totals[i].tokens += point.input_tokens + point.output_tokens;
+        totals[i].cost_usd += point.cost_usd;
+    }
+    for point in previous.iter().flatten() {
+        let i = total_for(&mut totals, &point.group);
+        let total = &mut totals[i];
+        total.previous_requests = total.previous_requests.map(|r| r + point.requests);
+        total.previous_tokens = total.previous_tokens.map(|t| t + point.input_tokens + point.output_tokens);
+        total.previous_cost_usd = total.previous_cost_usd.map(|c| c + point.cost_usd);
+    }
+    for total in &mut totals {
+        total.requests_change_pct = total.previous_requests
+            .filter(|&previous| previous > 0)
+            .map(|previous| (total.requests as f64 - previous as f64) / previous as f64 * 100.0);
+    }
+    totals.sort_by(|a, b| b.requests.cmp(&a.requests));
+    
+    Ok(UsageSeries { current, previous, totals })
+}
+
+// Latency percentiles per model, provider or route (model through a provider), optionally limited to a day range
+// ("YYYY-MM-DD", inclusive); all-time when no range is given
 #[tauri::command]
-fn get_request_history() -> RequestHistory {
-    load_request_history()
+fn get_latency_stats(
+    group_by: String,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<LatencySummary>, String> {
+    let agg = load_aggregate();
+    
+    let stats = if from.is_none() && to.is_none() {
+        agg.latency
+    } else {
+        let mut merged = LatencyStats::default();
+        for (day, stats) in &agg.latency_by_day {
+            let after_start = from.as_ref().is_none_or(|f| day >= f);
+            let before_end = to.as_ref().is_none_or(|t| day <= t);
+            if after_start && before_end {
+                merged.merge(stats);
+            }
+        }
+        merged
+    };
+    
+    let histograms = match group_by.as_str() {
+        "model" => stats.by_model,
+        "provider" => stats.by_provider,
+        "route" => stats.by_route,
+        other => return Err(format!("Unsupported grouping: {}", other)),
+    };
+    let route = group_by == "route";
+    let known = |key: &str| !key.is_empty() && key != "unknown";
+    
+    let mut summaries: Vec<LatencySummary> = histograms.iter()
+        .filter_map(|(key, histogram)| {
+            let (key, provider) = if route {
+                let (model, provider) = split_route_key(key);
+                (model, Some(provider))
+            } else {
+                (key.as_str(), None)
+            };
+            (known(key) && provider.is_none_or(known)).then(|| LatencySummary {
+                provider: provider.map(str::to_string),
+                ..histogram.summary(key)
+            })
+        })
+        .collect();
+    summaries.sort_by(|a, b| b.count.cmp(&a.count));
+    Ok(summaries)
 }
 
-// Add a request to history (called when request-log event is emitted)
-// Returns only the added request to minimize data transfer (memory optimization)
+// Rebuild aggregate.json from the request store (e.g. after it was deleted or drifted)
 #[tauri::command]
-fn add_request_to_history(request: RequestLog) -> Result<RequestLog, String> {
-    let mut history = load_request_history();
-    
-    // Calculate cost for this request
-    let tokens_in = request.tokens_in.unwrap_or(0);
-    let tokens_out = request.tokens_out.unwrap_or(0);
-    let cost = estimate_request_cost(&request.model, tokens_in, tokens_out);
-    let tokens_cached = request.tokens_cached.unwrap_or(0);
-    
-    // Update totals
-    history.total_tokens_in += tokens_in as u64;
-    history.total_tokens_out += tokens_out as u64;
-    history.total_tokens_cached += tokens_cached as u64;
-    history.total_cost_usd += cost;
+fn rebuild_aggregate(state: State<'_, AppState>, store: State<'_, RequestStore>) -> Result<Aggregate, String> {
+    let tz = display_timezone(&state);
+    let mut agg = store.derive_aggregate(&tz)?;
+    let retention = state.config.lock().unwrap().usage_retention.clone();
+    crate::retention::apply_series_retention(&mut agg, &retention, now_ms(), &tz);
+    
+    // The store holds what the watcher logged; keep the proxy's side from the current aggregate
+    let proxy = load_aggregate().sources.map(|s| s.proxy).unwrap_or_default();
+    agg.sources = None;
+    crate::usage_merge::sources_mut(&mut agg);
+    crate::usage_merge::set_proxy_totals(&mut agg, proxy);
+    save_aggregate(&agg)?;
+    Ok(agg)
+}
+
+// Re-price stored usage with the current pricing catalog: the watcher's cost from the
+// request store and the proxy's from the usage ledger. Requests already rolled up by
+// retention keep the cost they were rolled up with.
+fn recompute_costs(store: &RequestStore, tz: &DisplayTimezone) -> Result<CostRecomputeSummary, String> {
+    let watcher_cost = store.derive_aggregate(tz)?.total_cost_usd;
+    let ledger_used = !store.usage_ledger_totals()?.is_empty();
+    let proxy_cost = if ledger_used { Some(store.usage_ledger_cost()?) } else { None };
     
-    // Add request (with deduplication check)
-    // Check if request with same ID already exists to prevent duplicates
-    let request_clone = request.clone();
-    if !history.requests.iter().any(|r| r.id == request.id) {
-        history.requests.push(request);
-        
-        // Trim to prevent unbounded growth (keep last 500 requests)
-        const MAX_HISTORY_SIZE: usize = 500;
-        if history.requests.len() > MAX_HISTORY_SIZE {
-            let excess = history.requests.len() - MAX_HISTORY_SIZE;
-            history.requests.drain(0..excess);
-        }
+    let mut agg = load_aggregate();
+    let previous_cost_usd = agg.total_cost_usd;
+    let sources = crate::usage_merge::sources_mut(&mut agg);
+    sources.watcher.cost_usd = watcher_cost;
+    if let Some(cost) = proxy_cost {
+        sources.proxy.cost_usd = cost;
     }
+    crate::usage_merge::merge_sources(&mut agg);
+    save_aggregate(&agg)?;
     
-    // Save
+    let mut history = load_request_history();
+    history.total_cost_usd = agg.total_cost_usd;
     save_request_history(&history)?;
     
-    // Return only the added request, not the full history
-    Ok(request_clone)
+    store.set_pricing_fingerprint(&crate::pricing::fingerprint())?;
+    Ok(CostRecomputeSummary {
+        previous_cost_usd,
+        cost_usd: agg.total_cost_usd,
+    })
 }
 
-// Clear request history
+// Re-price stored usage in the background if the pricing catalog changed since costs
+// were last computed (e.g. a new bundled catalog after an update)
+fn start_cost_recompute(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let store = app_handle.state::<RequestStore>();
+        if store.pricing_fingerprint().ok().flatten() == Some(crate::pricing::fingerprint()) {
+            return;
+        }
+        let tz = display_timezone(&app_handle.state::<AppState>());
+        match recompute_costs(&store, &tz) {
+            Ok(summary) => println!("[Pricing] Recomputed costs: {:?}", summary),
+            Err(e) => eprintln!("[Pricing] Failed to recompute costs: {}", e),
+        }
+    });
+}
+
+// Bundled and user pricing catalogs
 #[tauri::command]
-fn clear_request_history() -> Result<(), String> {
-    let history = RequestHistory::default();
-    save_request_history(&history)
+fn get_pricing_catalog() -> PricingCatalogs {
+    crate::pricing::catalogs()
+}
+
+// Save the user pricing catalog and re-price stored usage with it
+#[tauri::command]
+fn save_pricing_catalog(
+    state: State<'_, AppState>,
+    store: State<'_, RequestStore>,
+    catalog: PricingCatalog,
+) -> Result<CostRecomputeSummary, String> {
+    crate::pricing::save_user_catalog(&catalog)?;
+    recompute_costs(&store, &display_timezone(&state))
+}
+
+// The price rule that applies to a model (for the given provider and time, default now)
+#[tauri::command]
+fn resolve_model_price(model: String, provider: Option<String>, timestamp: Option<u64>) -> Option<PriceRule> {
+    let provider = provider.unwrap_or_else(|| detect_provider_from_model(&model));
+    crate::pricing::resolve(&model, &provider, timestamp.unwrap_or_else(now_ms))
+}
+
+// Where log-derived and proxy-derived usage disagree: each headline total with both
+// sources' values, and per-model counts in [from, to) (ms, defaults to the last 7 days)
+#[tauri::command]
+fn get_usage_drift(
+    store: State<'_, RequestStore>,
+    from: Option<u64>,
+    to: Option<u64>,
+) -> Result<UsageDriftReport, String> {
+    let to = to.unwrap_or_else(now_ms);
+    let from = from.unwrap_or_else(|| to.saturating_sub(7 * 24 * 60 * 60 * 1000));
+    Ok(UsageDriftReport {
+        fields: crate::usage_merge::field_drift(&load_aggregate()),
+        models: store.usage_drift_by_model(from, to)?,
+    })
 }
 
 // Sync usage statistics from CLIProxyAPI's Management API
 // This fetches real token counts that aren't available in GIN logs
 #[tauri::command]
-async fn sync_usage_from_proxy(state: State<'_, AppState>) -> Result<RequestHistory, String> {
+async fn sync_usage_from_proxy(state: State<'_, AppState>, store: State<'_, RequestStore>) -> Result<RequestHistory, String> {
     let port = {
         let config = state.config.lock().unwrap();
         config.port
@@ -2730,250 +3375,25 @@ async fn sync_usage_from_proxy(state: State<'_, AppState>) -> Result<RequestHist
     // Structure: { "usage": { "total_tokens": N, "apis": { "POST /v1/messages": { "total_tokens": N, "models": {...} } } } }
     let usage = body.get("usage").ok_or("Missing 'usage' field in response")?;
     
-    // Calculate input/output token split from APIs data
-    let mut total_input: u64 = 0;
-    let mut total_output: u64 = 0;
-    let mut total_cached: u64 = 0;
-    let mut model_stats: std::collections::HashMap<String, (u64, u64, u64, u64)> = std::collections::HashMap::new(); // (requests, input, output, cached)
-    
-    if let Some(apis) = usage.get("apis").and_then(|v| v.as_object()) {
-        for (_api_path, api_data) in apis {
-            if let Some(models) = api_data.get("models").and_then(|v| v.as_object()) {
-                for (model_name, model_data) in models {
-                    if let Some(details) = model_data.get("details").and_then(|v| v.as_array()) {
-                        for detail in details {
-                            if let Some(tokens) = detail.get("tokens") {
-                                let input = tokens.get("input_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
-                                let output = tokens.get("output_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
-                                let cached = tokens.get("cached_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
-                                total_input += input;
-                                total_output += output;
-                                total_cached += cached;
-                                
-                                let entry = model_stats.entry(model_name.clone()).or_insert((0, 0, 0, 0));
-                                entry.0 += 1; // request count
-                                entry.1 += input;
-                                entry.2 += output;
-                                entry.3 += cached;
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-    
-    // Calculate cost based on real token data
-    let mut total_cost: f64 = 0.0;
-    for (model_name, (_, input, output, _cached)) in &model_stats {
-        total_cost += estimate_request_cost(model_name, *input as u32, *output as u32);
-    }
-    
-    // Extract time-series data from CLIProxyAPI response
-    // Structure: { "usage": { "tokens_by_day": {...}, "tokens_by_hour": {...}, "requests_by_day": {...}, "requests_by_hour": {...} } }
-    let mut tokens_by_day: Vec<TimeSeriesPoint> = Vec::new();
-    let mut tokens_by_hour: Vec<TimeSeriesPoint> = Vec::new();
-    let mut requests_by_day: Vec<TimeSeriesPoint> = Vec::new();
-    let mut requests_by_hour: Vec<TimeSeriesPoint> = Vec::new();
-    
-    if let Some(tbd) = usage.get("tokens_by_day").and_then(|v| v.as_object()) {
-        for (day, value) in tbd {
-            if let Some(v) = value.as_u64() {
-                tokens_by_day.push(TimeSeriesPoint {
-                    label: day.clone(),
-                    value: v,
-                });
-            }
-        }
-        tokens_by_day.sort_by(|a, b| a.label.cmp(&b.label));
-        if tokens_by_day.len() > 14 {
-            tokens_by_day = tokens_by_day.split_off(tokens_by_day.len() - 14);
-        }
-    }
-    
-    if let Some(tbh) = usage.get("tokens_by_hour").and_then(|v| v.as_object()) {
-        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
-        for (hour, value) in tbh {
-            if let Some(v) = value.as_u64() {
-                // Normalize to YYYY-MM-DDTHH format if only HH is provided
-                let label = if hour.len() == 2 {
-                    format!("{}T{}", today, hour)
-                } else {
-                    hour.clone()
-                };
-                tokens_by_hour.push(TimeSeriesPoint {
-                    label,
-                    value: v,
-                });
-            }
-        }
-        tokens_by_hour.sort_by(|a, b| a.label.cmp(&b.label));
-        // Keep last 168 hours (7 days worth)
-        if tokens_by_hour.len() > 168 {
-            tokens_by_hour = tokens_by_hour.split_off(tokens_by_hour.len() - 168);
-        }
-    }
-    
-    // Parse requests_by_day from proxy (source of truth)
-    if let Some(rbd) = usage.get("requests_by_day").and_then(|v| v.as_object()) {
-        for (day, value) in rbd {
-            if let Some(v) = value.as_u64() {
-                requests_by_day.push(TimeSeriesPoint {
-                    label: day.clone(),
-                    value: v,
-                });
-            }
-        }
-        requests_by_day.sort_by(|a, b| a.label.cmp(&b.label));
-        if requests_by_day.len() > 14 {
-            requests_by_day = requests_by_day.split_off(requests_by_day.len() - 14);
-        }
-    }
+    let (model_stats, totals) = proxy_usage_totals(&state, &store, usage);
+    let tz = display_timezone(&state);
+    let series = usage_series_from_snapshot(usage, &tz);
+    let retention = state.config.lock().unwrap().usage_retention.clone();
     
-    // Parse requests_by_hour from proxy (source of truth for Activity Patterns heatmap)
-    if let Some(rbh) = usage.get("requests_by_hour").and_then(|v| v.as_object()) {
-        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
-        for (hour, value) in rbh {
-            if let Some(v) = value.as_u64() {
-                // Normalize to YYYY-MM-DDTHH format if only HH is provided
-                let label = if hour.len() == 2 {
-                    format!("{}T{}", today, hour)
-                } else {
-                    hour.clone()
-                };
-                requests_by_hour.push(TimeSeriesPoint {
-                    label,
-                    value: v,
-                });
-            }
-        }
-        requests_by_hour.sort_by(|a, b| a.label.cmp(&b.label));
-        // Keep last 168 hours (7 days worth)
-        if requests_by_hour.len() > 168 {
-            requests_by_hour = requests_by_hour.split_off(requests_by_hour.len() - 168);
-        }
-    }
+    let mut agg = load_aggregate();
+    merge_proxy_usage(&mut agg, &model_stats, totals, &series, &retention, &tz);
+    save_aggregate(&agg)?;
     
-    // Update local history with synced data
+    // Mirror the merged totals and recent token series into the local history
     let mut history = load_request_history();
-    history.total_tokens_in = total_input;
-    history.total_tokens_out = total_output;
-    history.total_tokens_cached = total_cached;
-    history.total_cost_usd = total_cost;
-    history.tokens_by_day = tokens_by_day.clone();
-    history.tokens_by_hour = tokens_by_hour.clone();
-    
-    // Save updated history
+    history.total_tokens_in = agg.total_tokens_in;
+    history.total_tokens_out = agg.total_tokens_out;
+    history.total_tokens_cached = agg.total_tokens_cached;
+    history.total_cost_usd = agg.total_cost_usd;
+    history.tokens_by_day = agg.tokens_by_day.iter().rev().take(14).rev().cloned().collect();
+    history.tokens_by_hour = agg.tokens_by_hour.iter().rev().take(168).rev().cloned().collect();
     save_request_history(&history)?;
     
-    // Also update aggregate with token data from proxy
-    let mut agg = load_aggregate();
-    agg.total_tokens_in = total_input;
-    agg.total_tokens_out = total_output;
-    agg.total_cost_usd = total_cost;
-    // Merge tokens_by_day into aggregate (proxy is source of truth for tokens)
-    for point in &tokens_by_day {
-        if let Some(existing) = agg.tokens_by_day.iter_mut().find(|p| p.label == point.label) {
-            existing.value = point.value; // Update with proxy value
-        } else {
-            agg.tokens_by_day.push(point.clone());
-        }
-    }
-    agg.tokens_by_day.sort_by(|a, b| a.label.cmp(&b.label));
-    
-    // Merge requests_by_day into aggregate (proxy is source of truth for requests)
-    for point in &requests_by_day {
-        if let Some(existing) = agg.requests_by_day.iter_mut().find(|p| p.label == point.label) {
-            existing.value = point.value; // Update with proxy value
-        } else {
-            agg.requests_by_day.push(point.clone());
-        }
-    }
-    agg.requests_by_day.sort_by(|a, b| a.label.cmp(&b.label));
-    
-    // Merge requests_by_hour into aggregate (for Activity Patterns heatmap)
-    for point in &requests_by_hour {
-        if let Some(existing) = agg.requests_by_hour.iter_mut().find(|p| p.label == point.label) {
-            existing.value = point.value; // Update with proxy value
-        } else {
-            agg.requests_by_hour.push(point.clone());
-        }
-    }
-    agg.requests_by_hour.sort_by(|a, b| a.label.cmp(&b.label));
-    // Trim to last 168 hours (7 days)
-    if agg.requests_by_hour.len() > 168 {
-        agg.requests_by_hour = agg.requests_by_hour.split_off(agg.requests_by_hour.len() - 168);
-    }
-    
-    // Merge tokens_by_hour into aggregate
-    for point in &tokens_by_hour {
-        if let Some(existing) = agg.tokens_by_hour.iter_mut().find(|p| p.label == point.label) {
-            existing.value = point.value; // Update with proxy value
-        } else {
-            agg.tokens_by_hour.push(point.clone());
-        }
-    }
-    agg.tokens_by_hour.sort_by(|a, b| a.label.cmp(&b.label));
-    // Trim to last 168 hours (7 days)
-    if agg.tokens_by_hour.len() > 168 {
-        agg.tokens_by_hour = agg.tokens_by_hour.split_off(agg.tokens_by_hour.len() - 168);
-    }
-    
-    // Update total_requests from proxy data if available
-    if !requests_by_day.is_empty() {
-        let proxy_total: u64 = requests_by_day.iter().map(|p| p.value).sum();
-        if proxy_total > agg.total_requests {
-            agg.total_requests = proxy_total;
-        }
-    }
-    
-    // Sync model_stats from proxy (source of truth for per-model request/token counts)
-    // Structure: { "apis": { "provider": { "models": { "model-name": { "total_requests": N, "total_tokens": N, "details": [...] } } } } }
-    if let Some(apis) = usage.get("apis").and_then(|v| v.as_object()) {
-        for (_provider, provider_data) in apis {
-            if let Some(models) = provider_data.get("models").and_then(|v| v.as_object()) {
-                for (model_name, model_data) in models {
-                    let total_requests = model_data.get("total_requests").and_then(|v| v.as_u64()).unwrap_or(0);
-                    let total_tokens = model_data.get("total_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
-                    
-                    // Sum up token details from the model's request history
-                    let mut input_tokens: u64 = 0;
-                    let mut output_tokens: u64 = 0;
-                    let mut cached_tokens: u64 = 0;
-                    
-                    if let Some(details) = model_data.get("details").and_then(|v| v.as_array()) {
-                        for detail in details {
-                            if let Some(tokens) = detail.get("tokens").and_then(|v| v.as_object()) {
-                                input_tokens += tokens.get("input_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
-                                output_tokens += tokens.get("output_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
-                                cached_tokens += tokens.get("cached_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
-                            }
-                        }
-                    }
-                    
-                    // Update or insert model stats
-                    let stats = agg.model_stats.entry(model_name.clone()).or_insert_with(Default::default);
-                    stats.requests = total_requests;
-                    stats.success_count = total_requests; // Assume all synced requests succeeded
-                    stats.tokens = total_tokens;
-                    stats.input_tokens = input_tokens;
-                    stats.output_tokens = output_tokens;
-                    stats.cached_tokens = cached_tokens;
-                }
-            }
-        }
-    }
-    
-    // Update total_success_count from synced model stats (proxy only tracks successful requests)
-    let synced_success: u64 = agg.model_stats.values().map(|s| s.success_count).sum();
-    if synced_success > agg.total_success_count {
-        agg.total_success_count = synced_success;
-    }
-    // Update total_tokens_cached in aggregate
-    agg.total_tokens_cached = total_cached;
-    
-    let _ = save_aggregate(&agg);
-    
     Ok(history)
 }
 
@@ -2985,25 +3405,48 @@ async fn export_usage_stats(state: State<'_, AppState>) -> Result<serde_json::Va
         config.port
     };
     
-    let client = reqwest::Client::new();
-    let export_url = format!("http://127.0.0.1:{}/v0/management/usage/export", port);
-    
-    let response = client
-        .get(&export_url)
-        .header("X-Management-Key", &get_management_key())
-        .timeout(std::time::Duration::from_secs(10))
-        .send()
-        .await
-        .map_err(|e| format!("Failed to export usage: {}. Is the proxy running?", e))?;
-    
-    if !response.status().is_success() {
-        return Err(format!("Export API returned status: {}", response.status()));
-    }
-    
-    let body: serde_json::Value = response.json().await
-        .map_err(|e| format!("Failed to parse export response: {}", e))?;
-    
-    Ok(body)
+    crate::proxy::usage_snapshot::fetch_export(port).await
+}
+
+// Write the request log (filtered like query_requests) to `path` as CSV or JSON Lines
+#[tauri::command]
+async fn export_requests(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    filter: RequestQuery,
+    format: String,
+    path: String,
+) -> Result<ExportSummary, String> {
+    let format = crate::export::ExportFormat::parse(&format)?;
+    let tz = display_timezone(&state);
+    // Reads the store and writes the file; keep it off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        let store = app.state::<RequestStore>();
+        crate::export::export_requests(&store, &filter, format, std::path::Path::new(&path), &tz)
+    })
+    .await
+    .map_err(|e| format!("Export failed: {}", e))?
+}
+
+// Write a rolled-up usage table (by time bucket and/or provider, model, account or endpoint) to `path`
+#[tauri::command]
+async fn export_aggregate(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    range: ExportRange,
+    grouping: AggregateGrouping,
+    format: String,
+    path: String,
+) -> Result<ExportSummary, String> {
+    let format = crate::export::ExportFormat::parse(&format)?;
+    let tz = display_timezone(&state);
+    tauri::async_runtime::spawn_blocking(move || {
+        let store = app.state::<RequestStore>();
+        let aggregate = load_aggregate();
+        crate::export::export_aggregate(&store, &aggregate, &range, &grouping, format, std::path::Path::new(&path), &tz)
+    })
+    .await
+    .map_err(|e| format!("Export failed: {}", e))?
 }
 
 // Import usage statistics into CLIProxyAPI from backup
@@ -3014,29 +3457,73 @@ async fn import_usage_stats(state: State<'_, AppState>, data: serde_json::Value)
         config.port
     };
     
-    let client = reqwest::Client::new();
-    let import_url = format!("http://127.0.0.1:{}/v0/management/usage/import", port);
+    crate::proxy::usage_snapshot::import_export(port, &data).await
+}
+
+// Write a ProxyPal usage backup (request log, aggregate and pricing) to `path`
+#[tauri::command]
+async fn export_usage_backup(app: tauri::AppHandle, path: String) -> Result<UsageBackupMetadata, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let store = app.state::<RequestStore>();
+        crate::backup::write(&store, &load_aggregate(), std::path::Path::new(&path))
+    })
+    .await
+    .map_err(|e| format!("Backup failed: {}", e))?
+}
+
+// Merge a ProxyPal usage backup into the local request store, history and aggregate.
+// Safe to repeat: requests already stored and counts already seen are not added again.
+#[tauri::command]
+async fn import_usage_backup(app: tauri::AppHandle, path: String) -> Result<UsageBackupImportSummary, String> {
+    // Parses the file and writes the store, aggregate and history, so it runs off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        let backup = crate::backup::read(std::path::Path::new(&path))?;
+        let state = app.state::<AppState>();
+        let store = app.state::<RequestStore>();
+        let tz = display_timezone(&state);
+        let retention = state.config.lock().unwrap().usage_retention.clone();
+    
+        let requests_added = store.insert_many(&backup.requests)?;
+    
+        let mut catalog = crate::pricing::catalogs().user;
+        let pricing_rules_added = crate::backup::merge_pricing(&mut catalog, &backup.pricing);
+        if pricing_rules_added > 0 {
+            crate::pricing::save_user_catalog(&catalog)?;
+        }
     
-    let response = client
-        .post(&import_url)
-        .header("X-Management-Key", &get_management_key())
-        .header("Content-Type", "application/json")
-        .json(&data)
-        .timeout(std::time::Duration::from_secs(30))
-        .send()
-        .await
-        .map_err(|e| format!("Failed to import usage: {}. Is the proxy running?", e))?;
+        let mut agg = load_aggregate();
+        crate::backup::merge_aggregate(&mut agg, &backup.aggregate, &retention, now_ms(), &tz);
+        save_aggregate(&agg)?;
     
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        return Err(format!("Import API returned status: {} - {}", status, body));
-    }
+        let mut history = load_request_history();
+        crate::backup::merge_history(&mut history, &backup.requests, &agg);
+        save_request_history(&history)?;
     
-    let body: serde_json::Value = response.json().await
-        .map_err(|e| format!("Failed to parse import response: {}", e))?;
+        // New prices apply to everything stored, imported requests included
+        let agg = if pricing_rules_added > 0 {
+            recompute_costs(&store, &tz)?;
+            load_aggregate()
+        } else {
+            agg
+        };
     
-    Ok(body)
+        println!(
+            "[Backup] Imported {} of {} requests from backup written {}",
+            requests_added,
+            backup.requests.len(),
+            backup.metadata.created_at
+        );
+        Ok(UsageBackupImportSummary {
+            requests_added,
+            requests_skipped: backup.requests.len() as u64 - requests_added,
+            pricing_rules_added,
+            backup_created_at: backup.metadata.created_at,
+            total_requests: agg.total_requests,
+            total_cost_usd: agg.total_cost_usd,
+        })
+    })
+    .await
+    .map_err(|e| format!("Import failed: {}", e))?
 }
 
 /// OAuth URL response for frontend modal
@@ -3056,18 +3543,7 @@ async fn get_oauth_url(state: State<'_, AppState>, provider: String) -> Result<O
     };
 
     // Get the OAuth URL from CLIProxyAPI's Management API
-    // Add is_webui=true to use the embedded callback forwarder
-    // Use 127.0.0.1 consistently (not localhost) to avoid access control issues
-    let endpoint = match provider.as_str() {
-        "claude" => format!("http://127.0.0.1:{}/v0/management/anthropic-auth-url?is_webui=true", port),
-        "openai" => format!("http://127.0.0.1:{}/v0/management/codex-auth-url?is_webui=true", port),
-        "gemini" => format!("http://127.0.0.1:{}/v0/management/gemini-cli-auth-url?is_webui=true", port),
-        "qwen" => format!("http://127.0.0.1:{}/v0/management/qwen-auth-url?is_webui=true", port),
-        "iflow" => format!("http://127.0.0.1:{}/v0/management/iflow-auth-url?is_webui=true", port),
-        "antigravity" => format!("http://127.0.0.1:{}/v0/management/antigravity-auth-url?is_webui=true", port),
-        "vertex" => return Err("Vertex uses service account import, not OAuth. Use import_vertex_credential instead.".to_string()),
-        _ => return Err(format!("Unknown provider: {}", provider)),
-    };
+    let endpoint = providers::get(&provider)?.oauth_url_endpoint(port)?;
 
     // Make HTTP request to get OAuth URL
     let client = reqwest::Client::new();
@@ -3131,18 +3607,7 @@ async fn open_oauth(app: tauri::AppHandle, state: State<'_, AppState>, provider:
     };
 
     // Get the OAuth URL from CLIProxyAPI's Management API
-    // Add is_webui=true to use the embedded callback forwarder
-    // Use 127.0.0.1 consistently (not localhost) to avoid access control issues
-    let endpoint = match provider.as_str() {
-        "claude" => format!("http://127.0.0.1:{}/v0/management/anthropic-auth-url?is_webui=true", port),
-        "openai" => format!("http://127.0.0.1:{}/v0/management/codex-auth-url?is_webui=true", port),
-        "gemini" => format!("http://127.0.0.1:{}/v0/management/gemini-cli-auth-url?is_webui=true", port),
-        "qwen" => format!("http://127.0.0.1:{}/v0/management/qwen-auth-url?is_webui=true", port),
-        "iflow" => format!("http://127.0.0.1:{}/v0/management/iflow-auth-url?is_webui=true", port),
-        "antigravity" => format!("http://127.0.0.1:{}/v0/management/antigravity-auth-url?is_webui=true", port),
-        "vertex" => return Err("Vertex uses service account import, not OAuth. Use import_vertex_credential instead.".to_string()),
-        _ => return Err(format!("Unknown provider: {}", provider)),
-    };
+    let endpoint = providers::get(&provider)?.oauth_url_endpoint(port)?;
 
     // Make HTTP request to get OAuth URL
     let client = reqwest::Client::new();
@@ -3237,33 +3702,9 @@ async fn refresh_auth_status(app: tauri::AppHandle, state: State<'_, AppState>)
     // Scan auth directory for credential files and count them per provider
     if let Ok(entries) = std::fs::read_dir(&auth_dir) {
         for entry in entries.flatten() {
-            let filename = entry.file_name().to_string_lossy().to_lowercase();
-            
-            // CLIProxyAPI naming patterns:
-            // - claude-{email}.json or anthropic-*.json
-            // - codex-{email}.json
-            // - gemini-{email}-{project}.json
-            // - qwen-{email}.json
-            // - iflow-{email}.json
-            // - vertex-{project_id}.json
-            // - antigravity-{email}.json
-            
-            if filename.ends_with(".json") {
-                if filename.starts_with("claude-") || filename.starts_with("anthropic-") {
-                    new_auth.claude += 1;
-                } else if filename.starts_with("codex-") {
-                    new_auth.openai += 1;
-                } else if filename.starts_with("gemini-") {
-                    new_auth.gemini += 1;
-                } else if filename.starts_with("qwen-") {
-                    new_auth.qwen += 1;
-                } else if filename.starts_with("iflow-") {
-                    new_auth.iflow += 1;
-                } else if filename.starts_with("vertex-") {
-                    new_auth.vertex += 1;
-                } else if filename.starts_with("antigravity-") {
-                    new_auth.antigravity += 1;
-                }
+            // CLIProxyAPI names credential files "<prefix>{email or project}.json"
+            if let Some(provider) = providers::for_auth_file(&entry.file_name().to_string_lossy()) {
+                new_auth.add(provider.id, 1);
             }
         }
     }
@@ -3298,17 +3739,9 @@ async fn complete_oauth(
 
     // For now, just increment the account count
     {
+        let descriptor = providers::get(&provider)?;
         let mut auth = state.auth_status.lock().unwrap();
-        match provider.as_str() {
-            "claude" => auth.claude += 1,
-            "openai" => auth.openai += 1,
-            "gemini" => auth.gemini += 1,
-            "qwen" => auth.qwen += 1,
-            "iflow" => auth.iflow += 1,
-            "vertex" => auth.vertex += 1,
-            "antigravity" => auth.antigravity += 1,
-            _ => return Err(format!("Unknown provider: {}", provider)),
-        }
+        auth.add(descriptor.id, 1);
 
         // Save to file
         save_auth_to_file(&auth)?;
@@ -3330,6 +3763,8 @@ async fn disconnect_provider(
     state: State<'_, AppState>,
     provider: String,
 ) -> Result<AuthStatus, String> {
+    let descriptor = providers::get(&provider)?;
+
     // Delete credential files from ~/.cli-proxy-api/ for this provider
     let auth_dir = dirs::home_dir()
         .unwrap_or_else(|| std::path::PathBuf::from("."))
@@ -3338,21 +3773,8 @@ async fn disconnect_provider(
     if auth_dir.exists() {
         if let Ok(entries) = std::fs::read_dir(&auth_dir) {
             for entry in entries.flatten() {
-                let filename = entry.file_name().to_string_lossy().to_lowercase();
-                
                 // Match credential files by provider prefix
-                let should_delete = match provider.as_str() {
-                    "claude" => filename.starts_with("claude-") || filename.starts_with("anthropic-"),
-                    "openai" => filename.starts_with("codex-"),
-                    "gemini" => filename.starts_with("gemini-"),
-                    "qwen" => filename.starts_with("qwen-"),
-                    "iflow" => filename.starts_with("iflow-"),
-                    "vertex" => filename.starts_with("vertex-"),
-                    "antigravity" => filename.starts_with("antigravity-"),
-                    _ => false,
-                };
-                
-                if should_delete && filename.ends_with(".json") {
+                if descriptor.owns_auth_file(&entry.file_name().to_string_lossy()) {
                     if let Err(e) = std::fs::remove_file(entry.path()) {
                         eprintln!("Failed to delete credential file {:?}: {}", entry.path(), e);
                     }
@@ -3362,17 +3784,7 @@ async fn disconnect_provider(
     }
     
     let mut auth = state.auth_status.lock().unwrap();
-
-    match provider.as_str() {
-        "claude" => auth.claude = 0,
-        "openai" => auth.openai = 0,
-        "gemini" => auth.gemini = 0,
-        "qwen" => auth.qwen = 0,
-        "iflow" => auth.iflow = 0,
-        "vertex" => auth.vertex = 0,
-        "antigravity" => auth.antigravity = 0,
-        _ => return Err(format!("Unknown provider: {}", provider)),
-    }
+    auth.set(descriptor.id, 0);
 
     // Save to file
     save_auth_to_file(&auth)?;
@@ -3701,7 +4113,7 @@ async fn import_vertex_credential(
     
     // Update auth status (increment count)
     let mut auth = state.auth_status.lock().unwrap();
-    auth.vertex += 1;
+    auth.add("vertex", 1);
     
     // Save to file
     save_auth_to_file(&auth)?;
@@ -3734,7 +4146,7 @@ async fn get_available_models(state: State<'_, AppState>) -> Result<Vec<Availabl
     
     // Get auth status to determine model sources
     let auth_status = state.auth_status.lock().unwrap().clone();
-    let has_vertex = auth_status.vertex > 0;
+    let has_vertex = auth_status.count("vertex") > 0;
     let has_gemini_api = !config.gemini_api_keys.is_empty();
     let has_copilot = config.copilot.enabled;
     
@@ -5681,13 +6093,10 @@ async fn check_provider_health(state: State<'_, AppState>) -> Result<ProviderHea
             last_checked: now,
         };
         return Ok(ProviderHealth {
-            claude: offline_status.clone(),
-            openai: offline_status.clone(),
-            gemini: offline_status.clone(),
-            qwen: offline_status.clone(),
-            iflow: offline_status.clone(),
-            vertex: offline_status.clone(),
-            antigravity: offline_status,
+            providers: providers::PROVIDERS
+                .iter()
+                .map(|p| (p.id.to_string(), offline_status.clone()))
+                .collect(),
         });
     }
     
@@ -5696,56 +6105,85 @@ async fn check_provider_health(state: State<'_, AppState>) -> Result<ProviderHea
         .build()
         .unwrap_or_else(|_| reqwest::Client::new());
     
-    // Check health for each provider based on auth status
-    // Note: We use a single /v1/models call since the proxy handles routing
-    // For now, if the proxy is responsive, all configured providers are healthy
-    let models_url = format!("http://127.0.0.1:{}/v1/models", port);
+    // Which providers have models routed, from one model list request
     let start = std::time::Instant::now();
-    let (proxy_healthy, latency) = match client
-        .get(&models_url)
+    let models = match client
+        .get(format!("http://127.0.0.1:{}/v1/models", port))
         .header("Authorization", format!("Bearer {}", proxy_api_key))
         .send()
         .await
     {
-        Ok(response) => {
+        Ok(response) if response.status().is_success() => {
             let latency = start.elapsed().as_millis() as u64;
-            (response.status().is_success(), Some(latency))
+            response
+                .json::<ModelsApiResponse>()
+                .await
+                .ok()
+                .map(|list| (list.data.into_iter().map(|m| m.owned_by).collect::<std::collections::HashSet<_>>(), latency))
+        }
+        _ => None,
+    };
+
+    // Usable and total enabled credential files per provider, as CLIProxyAPI sees them
+    let mut accounts: std::collections::HashMap<&str, (usize, usize)> = std::collections::HashMap::new();
+    if let Ok(files) = get_auth_files(state.clone()).await {
+        for file in files.iter().filter(|f| !f.disabled) {
+            if let Some(provider) = providers::for_auth_file(&file.name) {
+                let usable = !file.unavailable && file.status != "error";
+                let entry = accounts.entry(provider.id).or_default();
+                entry.0 += usable as usize;
+                entry.1 += 1;
+            }
         }
-        Err(_) => (false, None),
+    }
+
+    // A provider is offline when none of its models are listed or none of its
+    // credentials are usable, and degraded when some credentials aren't
+    let probe = |provider: &providers::ProviderDescriptor| -> (bool, bool, Option<u64>) {
+        let Some((owners, latency)) = &models else {
+            return (false, false, None);
+        };
+        let listed = provider.model_owners.iter().any(|owner| owners.contains(*owner));
+        let (usable, total) = accounts.get(provider.id).copied().unwrap_or((0, 0));
+        // Providers connected through API keys have no credential files
+        let accounts_ok = total == 0 || usable > 0;
+        (listed && accounts_ok, usable < total, Some(*latency))
     };
+    let probes: std::collections::HashMap<&str, (bool, bool, Option<u64>)> = providers::PROVIDERS
+        .iter()
+        .filter(|p| auth_status.count(p.id) > 0)
+        .map(|p| (p.id, probe(p)))
+        .collect();
     
     // Build health status for each provider
-    let make_status = |is_configured: bool| -> HealthStatus {
-        if !is_configured {
-            HealthStatus {
+    let make_status = |probe: Option<&(bool, bool, Option<u64>)>| -> HealthStatus {
+        match probe {
+            None => HealthStatus {
                 status: "unconfigured".to_string(),
                 latency_ms: None,
                 last_checked: now,
-            }
-        } else if !proxy_healthy {
-            HealthStatus {
+            },
+            Some((false, _, latency)) => HealthStatus {
                 status: "offline".to_string(),
-                latency_ms: latency,
-                last_checked: now,
-            }
-        } else {
-            let is_degraded = latency.map(|l| l > 2000).unwrap_or(false);
-            HealthStatus {
-                status: if is_degraded { "degraded" } else { "healthy" }.to_string(),
-                latency_ms: latency,
+                latency_ms: *latency,
                 last_checked: now,
+            },
+            Some((true, some_unusable, latency)) => {
+                let is_degraded = *some_unusable || latency.map(|l| l > 2000).unwrap_or(false);
+                HealthStatus {
+                    status: if is_degraded { "degraded" } else { "healthy" }.to_string(),
+                    latency_ms: *latency,
+                    last_checked: now,
+                }
             }
         }
     };
     
     Ok(ProviderHealth {
-        claude: make_status(auth_status.claude > 0),
-        openai: make_status(auth_status.openai > 0),
-        gemini: make_status(auth_status.gemini > 0),
-        qwen: make_status(auth_status.qwen > 0),
-        iflow: make_status(auth_status.iflow > 0),
-        vertex: make_status(auth_status.vertex > 0),
-        antigravity: make_status(auth_status.antigravity > 0),
+        providers: providers::PROVIDERS
+            .iter()
+            .map(|p| (p.id.to_string(), make_status(probes.get(p.id))))
+            .collect(),
     })
 }
 
@@ -6112,6 +6550,8 @@ async fn get_auth_files(state: State<'_, AppState>) -> Result<Vec<AuthFile>, Str
                                     failure_count: None,
                                     label: None,
                                     status_message: None,
+                                    expires_at: None,
+                                    refreshable: false,
                                 };
                                 
                                 files.push(disabled_file);
@@ -6123,6 +6563,9 @@ async fn get_auth_files(state: State<'_, AppState>) -> Result<Vec<AuthFile>, Str
         }
     }
     
+    // 3. Token expiry and refresh capability come from the credential files themselves
+    crate::auth_expiry::annotate(&mut files);
+    
     Ok(files)
 }
 
@@ -6876,8 +7319,20 @@ pub fn run() {
         let _ = cmd.spawn().and_then(|mut child| child.wait());
     }
 
+    // Open the request store and import history.json/aggregate.json on first run
+    let request_store = RequestStore::open().unwrap_or_else(|e| {
+        eprintln!("[ProxyPal] {}; falling back to in-memory request store", e);
+        RequestStore::open_in_memory().expect("in-memory request store should always open")
+    });
+    if let Err(e) = request_store.import_legacy_once(&load_request_history(), &load_aggregate()) {
+        eprintln!("[ProxyPal] Failed to import legacy request history: {}", e);
+    }
+
     // Load persisted config and auth
     let config = load_config();
+    
+    // Load the user pricing catalog (stored costs are re-priced in the background if it changed)
+    crate::pricing::reload();
     let auth = load_auth_status();
 
     let app_state = AppState {
@@ -6889,7 +7344,6 @@ pub fn run() {
         copilot_status: Mutex::new(CopilotStatus::default()),
         copilot_process: Mutex::new(None),
         log_watcher_running: Arc::new(AtomicBool::new(false)),
-        request_counter: Arc::new(AtomicU64::new(0)),
     };
 
     tauri::Builder::default()
@@ -6921,6 +7375,7 @@ pub fn run() {
         .manage(app_state)
         .manage(SshManager::new())
         .manage(CloudflareManager::new())
+        .manage(request_store)
         .setup(|app| {
             // Setup system tray
             #[cfg(desktop)]
@@ -6939,6 +7394,18 @@ pub fn run() {
                 });
             }
 
+            // Apply usage data retention in the background
+            start_cost_recompute(app.handle().clone());
+            start_usage_compaction(app.handle().clone());
+            start_usage_snapshots(app.handle().clone());
+            start_budget_monitor(app.handle().clone());
+            crate::metrics::start(app.handle().clone());
+            crate::otlp::start(app.handle().clone());
+            crate::digest::start(app.handle().clone());
+            crate::alerts::start(app.handle().clone());
+            crate::hooks::start(app.handle().clone());
+            crate::auth_expiry::start(app.handle().clone());
+
             // Auto-start SSH connections
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
@@ -6994,6 +7461,7 @@ pub fn run() {
             detect_copilot_api,
             install_copilot_api,
             get_auth_status,
+            get_providers,
             refresh_auth_status,
             open_oauth,
             get_oauth_url,
@@ -7021,9 +7489,27 @@ pub fn run() {
             check_provider_health,
             add_request_to_history,
             clear_request_history,
+            query_requests,
+            get_account_usage,
+            get_error_series,
+            get_top_errors,
+            get_latency_stats,
+            get_usage_series,
+            compact_usage_data,
+            purge_usage_data,
+            rebuild_aggregate,
             sync_usage_from_proxy,
+            backfill_request_tokens,
+            get_usage_drift,
+            get_pricing_catalog,
+            save_pricing_catalog,
+            resolve_model_price,
             export_usage_stats,
+            export_requests,
+            export_aggregate,
             import_usage_stats,
+            export_usage_backup,
+            import_usage_backup,
             get_available_models,
             test_openai_provider,
             fetch_openai_compatible_models,
@@ -7088,6 +7574,32 @@ pub fn run() {
             commands::ssh::save_ssh_config,
             commands::ssh::delete_ssh_config,
             commands::ssh::set_ssh_connection,
+            // Subscriptions
+            commands::subscriptions::get_subscriptions,
+            commands::subscriptions::save_subscription,
+            commands::subscriptions::delete_subscription,
+            commands::subscriptions::get_subscription_report,
+            commands::subscriptions::export_subscription_report,
+            // Budgets
+            commands::budgets::get_budgets,
+            commands::budgets::save_budget,
+            commands::budgets::delete_budget,
+            commands::budgets::get_budget_status,
+            // Usage digests
+            commands::digest::generate_usage_report,
+            // Alerts
+            commands::alerts::get_alert_rules,
+            commands::alerts::save_alert_rule,
+            commands::alerts::delete_alert_rule,
+            commands::alerts::get_alerts,
+            commands::alerts::acknowledge_alert,
+            commands::alerts::acknowledge_all_alerts,
+            // Webhooks and command hooks
+            commands::hooks::get_hooks,
+            commands::hooks::save_hook,
+            commands::hooks::delete_hook,
+            commands::hooks::get_hook_deliveries,
+            commands::hooks::test_hook,
             // Cloudflare Tunnel
             commands::cloudflare::get_cloudflare_configs,
             commands::cloudflare::save_cloudflare_config,
@@ -7130,6 +7642,17 @@ pub fn run() {
                         // Stop log watcher thread
                         state.log_watcher_running.store(false, Ordering::SeqCst);
                         
+                        // Snapshot usage statistics before the proxy goes away
+                        let (running, port) = {
+                            let status = state.proxy_status.lock().unwrap();
+                            (status.running, status.port)
+                        };
+                        if running {
+                            if let Err(e) = tauri::async_runtime::block_on(snapshot_proxy_usage(app_handle, port)) {
+                                eprintln!("[UsageSnapshot] Snapshot on exit failed: {}", e);
+                            }
+                        }
+                        
                         // Kill cliproxyapi process
                         if let Ok(mut process_guard) = state.proxy_process.lock() {
                             if let Some(child) = process_guard.take() {
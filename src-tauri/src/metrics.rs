@@ -0,0 +1,290 @@
+//! Optional Prometheus endpoint (`GET /metrics`) for Grafana dashboards.
+//!
+//! Request and token counters come from the request store's cumulative
+//! counters, latency histograms from the aggregate, and account counts and
+//! up/down gauges from the app state. The listener follows `config.metrics`:
+//! a supervisor re-reads the settings every few seconds and rebinds when they
+//! change, so no restart is needed after editing them.
+
+use std::fmt::Display;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::cloudflare_manager::CloudflareManager;
+use crate::request_store::RequestStore;
+use crate::ssh_manager::SshManager;
+use crate::state::AppState;
+
+/// Histogram bucket bounds (seconds) for request latency
+const LATENCY_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+/// Largest request head we read before giving up
+const MAX_REQUEST_BYTES: usize = 16 * 1024;
+
+/// Start the supervisor that keeps the listener in line with the settings
+pub fn start(app: AppHandle) {
+    const INTERVAL: Duration = Duration::from_secs(5);
+
+    tauri::async_runtime::spawn(async move {
+        let mut bound: Option<(String, u16)> = None;
+        // Last address that failed to bind, so a retried failure is only logged once
+        let mut failed: Option<(String, u16)> = None;
+        let mut server: Option<tauri::async_runtime::JoinHandle<()>> = None;
+        loop {
+            let settings = app.state::<AppState>().config.lock().unwrap().metrics.clone();
+            let wanted = settings.enabled.then(|| (settings.bind.clone(), settings.port));
+
+            if wanted != bound {
+                if let Some(handle) = server.take() {
+                    handle.abort();
+                    println!("[Metrics] Stopped metrics endpoint");
+                }
+                // Only a successful bind is remembered, so a busy port is retried next tick
+                bound = None;
+                if let Some((bind, port)) = wanted.clone() {
+                    match TcpListener::bind((bind.as_str(), port)).await {
+                        Ok(listener) => {
+                            println!("[Metrics] Serving Prometheus metrics on http://{}:{}/metrics", bind, port);
+                            if settings.bearer_token.is_empty() && !is_loopback(&bind) {
+                                eprintln!("[Metrics] Warning: endpoint is reachable from other machines without a bearer token");
+                            }
+                            server = Some(tauri::async_runtime::spawn(serve(app.clone(), listener)));
+                            bound = wanted;
+                            failed = None;
+                        }
+                        Err(e) => {
+                            if failed != wanted {
+                                eprintln!("[Metrics] Failed to listen on {}:{}: {}; retrying", bind, port, e);
+                                failed = wanted;
+                            }
+                        }
+                    }
+                }
+            }
+            tokio::time::sleep(INTERVAL).await;
+        }
+    });
+}
+
+fn is_loopback(bind: &str) -> bool {
+    bind == "localhost" || bind.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+async fn serve(app: AppHandle, listener: TcpListener) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = handle_connection(&app, stream).await {
+                        eprintln!("[Metrics] Request failed: {}", e);
+                    }
+                });
+            }
+            Err(e) => {
+                eprintln!("[Metrics] Accept failed: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+}
+
+/// Read the request head (line and headers); the body, if any, is ignored
+async fn read_head(stream: &mut TcpStream) -> Result<String, String> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_BYTES {
+            return Err("request head too large".to_string());
+        }
+        let n = stream.read(&mut buf).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+/// Compare without returning early, so the token can't be guessed from timings
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+async fn handle_connection(app: &AppHandle, mut stream: TcpStream) -> Result<(), String> {
+    let head = tokio::time::timeout(Duration::from_secs(5), read_head(&mut stream))
+        .await
+        .map_err(|_| "timed out reading request".to_string())??;
+
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+    let authorization = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("authorization"))
+        .map(|(_, value)| value.trim().to_string());
+
+    let token = app.state::<AppState>().config.lock().unwrap().metrics.bearer_token.clone();
+    let authorized = token.is_empty()
+        || authorization
+            .as_deref()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| token_matches(given.trim(), &token));
+
+    let (status, extra_headers, body) = if method != "GET" && method != "HEAD" {
+        ("405 Method Not Allowed", "Allow: GET, HEAD\r\n", String::new())
+    } else if path.split('?').next() != Some("/metrics") {
+        ("404 Not Found", "", "Not found; metrics are served at /metrics\n".to_string())
+    } else if !authorized {
+        ("401 Unauthorized", "WWW-Authenticate: Bearer\r\n", String::new())
+    } else {
+        ("200 OK", "", render(app))
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+        status,
+        body.len(),
+        extra_headers
+    );
+    stream.write_all(response.as_bytes()).await.map_err(|e| e.to_string())?;
+    if method != "HEAD" {
+        stream.write_all(body.as_bytes()).await.map_err(|e| e.to_string())?;
+    }
+    stream.shutdown().await.map_err(|e| e.to_string())
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Prometheus text exposition format writer
+#[derive(Default)]
+struct Exposition {
+    out: String,
+}
+
+impl Exposition {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        self.out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, escape_label(value)))
+                .collect();
+            self.out.push_str(&format!("{{{}}}", labels.join(",")));
+        }
+        self.out.push_str(&format!(" {}\n", value));
+    }
+
+    fn histogram(&mut self, name: &str, label: (&str, &str), histogram: &crate::types::LatencyHistogram) {
+        let bucket_name = format!("{}_bucket", name);
+        for bound in LATENCY_BUCKETS {
+            let count = histogram.count_at_most((bound * 1000.0) as u64);
+            self.sample(&bucket_name, &[label, ("le", &bound.to_string())], count);
+        }
+        self.sample(&bucket_name, &[label, ("le", "+Inf")], histogram.count);
+        self.sample(&format!("{}_sum", name), &[label], histogram.sum_ms as f64 / 1000.0);
+        self.sample(&format!("{}_count", name), &[label], histogram.count);
+    }
+}
+
+fn up(value: bool) -> u8 {
+    value as u8
+}
+
+/// Build the metrics page from the current app state
+pub fn render(app: &AppHandle) -> String {
+    let state = app.state::<AppState>();
+    let config = state.config.lock().unwrap().clone();
+    let mut m = Exposition::default();
+
+    m.family("proxypal_build_info", "gauge", "ProxyPal version");
+    m.sample("proxypal_build_info", &[("version", env!("CARGO_PKG_VERSION"))], 1);
+
+    // Up/down
+    m.family("proxypal_sidecar_up", "gauge", "Whether the CLIProxyAPI sidecar is running");
+    m.sample("proxypal_sidecar_up", &[], up(state.proxy_status.lock().unwrap().running));
+    m.family("proxypal_copilot_up", "gauge", "Whether the Copilot bridge (copilot-api) is running");
+    m.sample("proxypal_copilot_up", &[], up(state.copilot_status.lock().unwrap().running));
+
+    let ssh_statuses = app.try_state::<SshManager>().map(|s| s.statuses()).unwrap_or_default();
+    m.family("proxypal_ssh_tunnel_up", "gauge", "Whether each configured SSH tunnel is connected");
+    for ssh in &config.ssh_configs {
+        let target = format!("{}@{}:{}", ssh.username, ssh.host, ssh.port);
+        let connected = ssh_statuses.get(&ssh.id).is_some_and(|s| s == "connected");
+        m.sample("proxypal_ssh_tunnel_up", &[("id", &ssh.id), ("target", &target)], up(connected));
+    }
+
+    let cf_statuses = app.try_state::<CloudflareManager>().map(|s| s.statuses()).unwrap_or_default();
+    m.family("proxypal_cloudflare_tunnel_up", "gauge", "Whether each configured Cloudflare tunnel is connected");
+    for cf in &config.cloudflare_configs {
+        let connected = cf_statuses.get(&cf.id).is_some_and(|s| s == "connected");
+        m.sample("proxypal_cloudflare_tunnel_up", &[("id", &cf.id), ("name", &cf.name)], up(connected));
+    }
+
+    // Accounts
+    let auth = state.auth_status.lock().unwrap().clone();
+    m.family("proxypal_auth_accounts", "gauge", "Connected OAuth accounts per provider");
+    if let Ok(serde_json::Value::Object(providers)) = serde_json::to_value(&auth) {
+        for (provider, count) in providers {
+            m.sample("proxypal_auth_accounts", &[("provider", &provider)], count.as_u64().unwrap_or(0));
+        }
+    }
+
+    // Requests and tokens
+    match app.state::<RequestStore>().request_counters() {
+        Ok(counters) => {
+            m.family("proxypal_requests_total", "counter", "Requests by provider, model and HTTP status");
+            for c in &counters {
+                let status = c.status.to_string();
+                let labels = [("provider", c.provider.as_str()), ("model", c.model.as_str()), ("status", &status)];
+                m.sample("proxypal_requests_total", &labels, c.requests);
+            }
+            m.family("proxypal_tokens_total", "counter", "Tokens by provider, model, HTTP status and type (input, output, cached)");
+            for c in &counters {
+                let status = c.status.to_string();
+                for (kind, value) in [("input", c.tokens_in), ("output", c.tokens_out), ("cached", c.tokens_cached)] {
+                    let labels = [
+                        ("provider", c.provider.as_str()),
+                        ("model", c.model.as_str()),
+                        ("status", &status),
+                        ("type", kind),
+                    ];
+                    m.sample("proxypal_tokens_total", &labels, value);
+                }
+            }
+        }
+        Err(e) => eprintln!("[Metrics] Failed to read request counters: {}", e),
+    }
+
+    // Latency
+    let latency = crate::load_aggregate().latency;
+    let mut providers: Vec<_> = latency.by_provider.iter().collect();
+    providers.sort_by(|a, b| a.0.cmp(b.0));
+    m.family("proxypal_request_duration_seconds", "histogram", "Latency of successful requests by provider");
+    for (provider, histogram) in providers {
+        m.histogram("proxypal_request_duration_seconds", ("provider", provider), histogram);
+    }
+    let mut models: Vec<_> = latency.by_model.iter().collect();
+    models.sort_by(|a, b| a.0.cmp(b.0));
+    m.family("proxypal_model_request_duration_seconds", "histogram", "Latency of successful requests by model");
+    for (model, histogram) in models {
+        m.histogram("proxypal_model_request_duration_seconds", ("model", model), histogram);
+    }
+
+    m.out
+}
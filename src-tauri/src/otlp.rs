@@ -0,0 +1,321 @@
+//! OpenTelemetry export of logged requests (OTLP over HTTP, JSON encoding).
+//!
+//! The log watcher queues each new request; a background task sends them to the
+//! configured collector in batches, as spans or log records. When the collector
+//! is unreachable the batch stays queued and sending backs off exponentially.
+//! The queue is bounded, dropping the oldest requests once full.
+//!
+//! A request's timestamp is when CLIProxyAPI logged it, i.e. when it finished,
+//! so a span ends there and starts `duration_ms` earlier. Token attributes are
+//! only set when the counts were known at logging time.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use crate::state::AppState;
+use crate::types::{OtlpSettings, RequestLog};
+
+/// Requests sent per export call
+const BATCH_SIZE: usize = 256;
+/// Requests kept while the collector is down; older ones are dropped
+const MAX_QUEUED: usize = 10_000;
+/// How long a partial batch waits before it's sent
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+struct Queue {
+    requests: VecDeque<RequestLog>,
+    /// Dropped because the queue was full, not yet reported
+    dropped: u64,
+}
+
+lazy_static::lazy_static! {
+    static ref QUEUE: Mutex<Queue> = Mutex::new(Queue {
+        requests: VecDeque::new(),
+        dropped: 0,
+    });
+    /// Wakes the exporter early once a full batch is waiting
+    static ref BATCH_READY: tokio::sync::Notify = tokio::sync::Notify::new();
+}
+
+fn push_bounded(queue: &mut Queue) {
+    while queue.requests.len() > MAX_QUEUED {
+        queue.requests.pop_front();
+        queue.dropped += 1;
+    }
+}
+
+/// Check export settings before saving them
+pub fn validate(settings: &OtlpSettings) -> Result<(), String> {
+    if !settings.enabled {
+        return Ok(());
+    }
+    let url = url::Url::parse(settings.endpoint.trim()).map_err(|e| format!("Invalid collector endpoint: {}", e))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err("Collector endpoint must use http or https".to_string());
+    }
+    if settings.signal != "traces" && settings.signal != "logs" {
+        return Err(format!("Unsupported OTLP signal: {}", settings.signal));
+    }
+    for (name, value) in &settings.headers {
+        reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("Invalid header name: {}", name))?;
+        reqwest::header::HeaderValue::from_str(value).map_err(|_| format!("Invalid value for header {}", name))?;
+    }
+    Ok(())
+}
+
+/// Queue a request for export (the watcher calls this only when export is enabled)
+pub fn record(request: &RequestLog) {
+    let mut queue = QUEUE.lock().unwrap();
+    queue.requests.push_back(request.clone());
+    push_bounded(&mut queue);
+    if queue.requests.len() >= BATCH_SIZE {
+        BATCH_READY.notify_one();
+    }
+}
+
+/// Start the background exporter
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let client = reqwest::Client::new();
+        let mut backoff = Duration::ZERO;
+
+        loop {
+            let _ = tokio::time::timeout(FLUSH_INTERVAL, BATCH_READY.notified()).await;
+
+            let settings = app.state::<AppState>().config.lock().unwrap().otlp.clone();
+            if !settings.enabled {
+                QUEUE.lock().unwrap().requests.clear();
+                backoff = Duration::ZERO;
+                continue;
+            }
+
+            loop {
+                let (batch, dropped) = {
+                    let mut queue = QUEUE.lock().unwrap();
+                    let n = queue.requests.len().min(BATCH_SIZE);
+                    let batch: Vec<RequestLog> = queue.requests.drain(..n).collect();
+                    (batch, std::mem::take(&mut queue.dropped))
+                };
+                if dropped > 0 {
+                    eprintln!("[OTLP] Queue full: dropped {} requests that couldn't be exported", dropped);
+                }
+                if batch.is_empty() {
+                    break;
+                }
+
+                match send(&client, &settings, &batch).await {
+                    Ok(()) => {
+                        if !backoff.is_zero() {
+                            println!("[OTLP] Collector reachable again, export resumed");
+                            backoff = Duration::ZERO;
+                        }
+                    }
+                    Err(SendError::Rejected(e)) => {
+                        eprintln!("[OTLP] Collector rejected {} requests: {}", batch.len(), e);
+                    }
+                    Err(SendError::Unavailable(e)) => {
+                        if backoff.is_zero() {
+                            eprintln!("[OTLP] Collector unavailable ({}), retrying with backoff", e);
+                        }
+                        // Put the batch back in front, in order, and wait before retrying
+                        {
+                            let mut queue = QUEUE.lock().unwrap();
+                            for request in batch.into_iter().rev() {
+                                queue.requests.push_front(request);
+                            }
+                            push_bounded(&mut queue);
+                        }
+                        backoff = (backoff * 2).clamp(Duration::from_secs(1), MAX_BACKOFF);
+                        tokio::time::sleep(backoff).await;
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+enum SendError {
+    /// Worth retrying: network error, 429 or 5xx
+    Unavailable(String),
+    /// The collector refused the data, or the request couldn't be built; retrying won't help
+    Rejected(String),
+}
+
+/// Collector URL for the configured signal
+fn signal_url(settings: &OtlpSettings) -> String {
+    let path = if settings.signal == "logs" { "/v1/logs" } else { "/v1/traces" };
+    let base = settings.endpoint.trim().trim_end_matches('/');
+    if base.ends_with(path) {
+        base.to_string()
+    } else {
+        format!("{}{}", base, path)
+    }
+}
+
+async fn send(client: &reqwest::Client, settings: &OtlpSettings, batch: &[RequestLog]) -> Result<(), SendError> {
+    let body = if settings.signal == "logs" {
+        logs_payload(settings, batch)
+    } else {
+        traces_payload(settings, batch)
+    };
+
+    let mut request = client
+        .post(signal_url(settings))
+        .timeout(Duration::from_secs(10))
+        .json(&body);
+    for (name, value) in &settings.headers {
+        request = request.header(name, value);
+    }
+
+    // A bad endpoint or header fails to build the request; retrying can't fix that
+    let response = request.send().await.map_err(|e| {
+        if e.is_builder() {
+            SendError::Rejected(e.to_string())
+        } else {
+            SendError::Unavailable(e.to_string())
+        }
+    })?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let detail = response.text().await.unwrap_or_default();
+    let message = format!("{} {}", status, detail.trim());
+    if status.is_server_error() || status.as_u16() == 429 || status.as_u16() == 408 {
+        Err(SendError::Unavailable(message))
+    } else {
+        Err(SendError::Rejected(message))
+    }
+}
+
+/// Stable hex ID derived from the request ID (SHA-256, at most 32 bytes), so a
+/// re-sent request keeps its identity across restarts and releases
+fn hex_id(id: &str, salt: &str, bytes: usize) -> String {
+    Sha256::digest(format!("{}:{}", salt, id).as_bytes())
+        .iter()
+        .take(bytes)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn string_attr(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+// OTLP/JSON encodes 64-bit integers as strings
+fn int_attr(key: &str, value: u64) -> Value {
+    json!({ "key": key, "value": { "intValue": value.to_string() } })
+}
+
+fn request_attributes(req: &RequestLog) -> Vec<Value> {
+    let mut attrs = vec![
+        string_attr("gen_ai.request.model", &req.model),
+        string_attr("gen_ai.system", &req.provider),
+        string_attr("http.request.method", &req.method),
+        string_attr("url.path", &req.path),
+        int_attr("http.response.status_code", req.status as u64),
+        int_attr("proxypal.duration_ms", req.duration_ms),
+        string_attr("proxypal.endpoint_kind", &req.endpoint_kind),
+        string_attr("proxypal.request.log_id", &req.id),
+    ];
+    if let Some(account) = &req.account {
+        attrs.push(string_attr("proxypal.account", account));
+    }
+    if let Some(request_id) = &req.request_id {
+        attrs.push(string_attr("proxypal.request_id", request_id));
+    }
+    if let Some(tokens) = req.tokens_in {
+        attrs.push(int_attr("gen_ai.usage.input_tokens", tokens as u64));
+    }
+    if let Some(tokens) = req.tokens_out {
+        attrs.push(int_attr("gen_ai.usage.output_tokens", tokens as u64));
+    }
+    if let Some(tokens) = req.tokens_cached {
+        attrs.push(int_attr("proxypal.usage.cached_tokens", tokens as u64));
+    }
+    if let Some(category) = &req.error_category {
+        attrs.push(string_attr("error.type", category));
+    }
+    attrs
+}
+
+fn resource(settings: &OtlpSettings) -> Value {
+    json!({
+        "attributes": [
+            string_attr("service.name", &settings.service_name),
+            string_attr("service.version", env!("CARGO_PKG_VERSION")),
+        ]
+    })
+}
+
+fn scope() -> Value {
+    json!({ "name": "proxypal", "version": env!("CARGO_PKG_VERSION") })
+}
+
+fn nanos(ms: u64) -> String {
+    (ms as u128 * 1_000_000).to_string()
+}
+
+fn traces_payload(settings: &OtlpSettings, batch: &[RequestLog]) -> Value {
+    let spans: Vec<Value> = batch
+        .iter()
+        .map(|req| {
+            let failed = req.status >= 400;
+            json!({
+                "traceId": hex_id(&req.id, "trace", 16),
+                "spanId": hex_id(&req.id, "span", 8),
+                "name": format!("{} {}", req.method, req.path),
+                "kind": 2, // SPAN_KIND_SERVER
+                "startTimeUnixNano": nanos(req.timestamp.saturating_sub(req.duration_ms)),
+                "endTimeUnixNano": nanos(req.timestamp),
+                "attributes": request_attributes(req),
+                "status": if failed {
+                    json!({ "code": 2, "message": req.error_message.clone().unwrap_or_else(|| format!("HTTP {}", req.status)) })
+                } else {
+                    json!({ "code": 1 })
+                },
+            })
+        })
+        .collect();
+
+    json!({
+        "resourceSpans": [{
+            "resource": resource(settings),
+            "scopeSpans": [{ "scope": scope(), "spans": spans }],
+        }]
+    })
+}
+
+fn logs_payload(settings: &OtlpSettings, batch: &[RequestLog]) -> Value {
+    let records: Vec<Value> = batch
+        .iter()
+        .map(|req| {
+            let failed = req.status >= 400;
+            json!({
+                "timeUnixNano": nanos(req.timestamp),
+                "observedTimeUnixNano": nanos(req.timestamp),
+                "severityNumber": if failed { 17 } else { 9 }, // ERROR / INFO
+                "severityText": if failed { "ERROR" } else { "INFO" },
+                "body": {
+                    "stringValue": format!("{} {} {} {} {}ms", req.method, req.path, req.model, req.status, req.duration_ms)
+                },
+                "attributes": request_attributes(req),
+            })
+        })
+        .collect();
+
+    json!({
+        "resourceLogs": [{
+            "resource": resource(settings),
+            "scopeLogs": [{ "scope": scope(), "logRecords": records }],
+        }]
+    })
+}
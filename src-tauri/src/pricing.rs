@@ -0,0 +1,306 @@
+//! Model pricing for cost estimates.
+//!
+//! Prices come from a catalog of rules (see `PriceRule`): the one bundled with
+//! ProxyPal, overridden by the user's `pricing.json` in the config directory.
+//! Only rules effective on the request's (UTC) date are considered, and rules
+//! from both catalogs are ranked together: a model rule beats a provider rule,
+//! which beats the fallback, and among model rules the most specific pattern
+//! (most literal characters) wins. Between equally specific rules a user rule
+//! beats a bundled one, and then the most recently effective one applies. So a
+//! user fallback never overrides a bundled price for a specific model.
+
+use std::sync::{Arc, RwLock};
+
+use sha2::{Digest, Sha256};
+
+use crate::config::get_pricing_path;
+use crate::timezone::DAY_FORMAT;
+use crate::types::{PriceRule, PricingCatalog, PricingCatalogs, RequestLog};
+
+const BUNDLED_CATALOG: &str = include_str!("../pricing/default-pricing.json");
+
+/// Token counts of a request (or a sum of requests) for pricing.
+/// `input` includes cache reads, as CLIProxyAPI reports it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenUsage {
+    pub input: u64,
+    pub output: u64,
+    pub cache_read: u64,
+    pub cache_write: u64,
+}
+
+struct Catalogs {
+    bundled: PricingCatalog,
+    user: PricingCatalog,
+}
+
+lazy_static::lazy_static! {
+    static ref CATALOGS: RwLock<Arc<Catalogs>> = RwLock::new(Arc::new(Catalogs {
+        bundled: bundled_catalog(),
+        user: PricingCatalog::default(),
+    }));
+}
+
+fn bundled_catalog() -> PricingCatalog {
+    serde_json::from_str(BUNDLED_CATALOG).expect("bundled pricing catalog should be valid")
+}
+
+fn load_user_catalog() -> PricingCatalog {
+    let path = get_pricing_path();
+    let Ok(data) = std::fs::read_to_string(&path) else {
+        return PricingCatalog::default();
+    };
+    match serde_json::from_str(&data) {
+        Ok(catalog) => catalog,
+        Err(e) => {
+            eprintln!("[Pricing] Ignoring invalid pricing catalog '{}': {}", path.display(), e);
+            PricingCatalog::default()
+        }
+    }
+}
+
+/// (Re)load the user catalog from disk
+pub fn reload() {
+    let catalogs = Catalogs {
+        bundled: bundled_catalog(),
+        user: load_user_catalog(),
+    };
+    *CATALOGS.write().unwrap() = Arc::new(catalogs);
+}
+
+/// Both catalogs, for display and editing
+pub fn catalogs() -> PricingCatalogs {
+    let catalogs = CATALOGS.read().unwrap().clone();
+    PricingCatalogs {
+        bundled: catalogs.bundled.clone(),
+        user: catalogs.user.clone(),
+        user_path: get_pricing_path().display().to_string(),
+    }
+}
+
+/// Check a catalog before saving it
+pub fn validate(catalog: &PricingCatalog) -> Result<(), String> {
+    for (i, rule) in catalog.rules.iter().enumerate() {
+        let rates = [Some(rule.input), Some(rule.output), rule.cache_read, rule.cache_write];
+        if rates.iter().flatten().any(|r| !r.is_finite() || *r < 0.0) {
+            return Err(format!("Rule {}: prices must be non-negative numbers", i + 1));
+        }
+        if let Some(date) = &rule.effective_from {
+            chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| format!("Rule {}: effectiveFrom must be a YYYY-MM-DD date", i + 1))?;
+        }
+        if rule.model.as_deref().is_some_and(|m| m.trim().is_empty()) {
+            return Err(format!("Rule {}: model pattern is empty", i + 1));
+        }
+    }
+    Ok(())
+}
+
+/// Save the user catalog and make it current
+pub fn save_user_catalog(catalog: &PricingCatalog) -> Result<(), String> {
+    validate(catalog)?;
+    let path = get_pricing_path();
+    let temp_path = path.with_extension("json.tmp");
+    let data = serde_json::to_string_pretty(catalog).map_err(|e| e.to_string())?;
+    std::fs::write(&temp_path, data).map_err(|e| e.to_string())?;
+    std::fs::rename(&temp_path, &path).map_err(|e| e.to_string())?;
+    reload();
+    Ok(())
+}
+
+/// Identifies the current prices, so stored costs can be recomputed when they change.
+/// SHA-256 rather than `DefaultHasher`, whose output may change between Rust releases.
+pub fn fingerprint() -> String {
+    let catalogs = CATALOGS.read().unwrap().clone();
+    let mut hasher = Sha256::new();
+    hasher.update(serde_json::to_string(&catalogs.bundled).unwrap_or_default());
+    hasher.update([0]);
+    hasher.update(serde_json::to_string(&catalogs.user).unwrap_or_default());
+    hasher.finalize()[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Case-insensitive glob match supporting `*` and `?`
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Rank of a matching rule: (tier, specificity, effective date). Higher wins; see
+/// `find_rule` for where the catalog comes in.
+fn rule_rank<'a>(rule: &'a PriceRule, model: &str, provider: &str, day: &str) -> Option<(u8, usize, &'a str)> {
+    let effective = rule.effective_from.as_deref().unwrap_or("");
+    if effective > day {
+        return None;
+    }
+    if let Some(p) = &rule.provider {
+        if !p.eq_ignore_ascii_case(provider) {
+            return None;
+        }
+    }
+    match &rule.model {
+        Some(pattern) if glob_match(pattern, model) => {
+            let literal = pattern.chars().filter(|c| *c != '*' && *c != '?').count();
+            Some((2, literal, effective))
+        }
+        Some(_) => None,
+        None if rule.provider.is_some() => Some((1, 0, effective)),
+        None => Some((0, 0, effective)),
+    }
+}
+
+fn find_rule<'a>(catalogs: &'a Catalogs, model: &str, provider: &str, day: &str) -> Option<&'a PriceRule> {
+    let bundled = catalogs.bundled.rules.iter().map(|rule| (false, rule));
+    let user = catalogs.user.rules.iter().map(|rule| (true, rule));
+    bundled
+        .chain(user)
+        .filter_map(|(is_user, rule)| {
+            let (tier, specificity, effective) = rule_rank(rule, model, provider, day)?;
+            Some(((tier, specificity, is_user, effective), rule))
+        })
+        // Later rules win ties, so an appended rule overrides an earlier identical one
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, rule)| rule)
+}
+
+/// The rule that prices `model` (served by `provider`) at `timestamp_ms`
+pub fn resolve(model: &str, provider: &str, timestamp_ms: u64) -> Option<PriceRule> {
+    let catalogs = CATALOGS.read().unwrap().clone();
+    let day = chrono::DateTime::from_timestamp_millis(timestamp_ms as i64)
+        .map(|dt| dt.format(DAY_FORMAT).to_string())
+        .unwrap_or_default();
+    find_rule(&catalogs, model, provider, &day).cloned()
+}
+
+/// Estimated cost in USD
+pub fn cost(model: &str, provider: &str, timestamp_ms: u64, usage: TokenUsage) -> f64 {
+    let Some(rule) = resolve(model, provider, timestamp_ms) else {
+        return 0.0;
+    };
+    let per_token = |rate: f64| rate / 1_000_000.0;
+    let uncached = usage.input.saturating_sub(usage.cache_read);
+
+    uncached as f64 * per_token(rule.input)
+        + usage.cache_read as f64 * per_token(rule.cache_read.unwrap_or(rule.input))
+        + usage.cache_write as f64 * per_token(rule.cache_write.unwrap_or(rule.input))
+        + usage.output as f64 * per_token(rule.output)
+}
+
+/// Estimated cost of a logged request (zero until its tokens are known)
+pub fn request_cost(req: &RequestLog) -> f64 {
+    let usage = TokenUsage {
+        input: req.tokens_in.unwrap_or(0) as u64,
+        output: req.tokens_out.unwrap_or(0) as u64,
+        cache_read: req.tokens_cached.unwrap_or(0) as u64,
+        cache_write: 0,
+    };
+    cost(&req.model, &req.provider, req.timestamp, usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(model: Option<&str>, provider: Option<&str>, input: f64) -> PriceRule {
+        PriceRule {
+            model: model.map(str::to_string),
+            provider: provider.map(str::to_string),
+            input,
+            output: 0.0,
+            cache_read: None,
+            cache_write: None,
+            effective_from: None,
+        }
+    }
+
+    fn catalogs(bundled: Vec<PriceRule>, user: Vec<PriceRule>) -> Catalogs {
+        Catalogs {
+            bundled: PricingCatalog { rules: bundled },
+            user: PricingCatalog { rules: user },
+        }
+    }
+
+    /// Input price of the rule chosen for `model`, which tells the test rules apart
+    fn price(catalogs: &Catalogs, model: &str, provider: &str, day: &str) -> Option<f64> {
+        find_rule(catalogs, model, provider, day).map(|rule| rule.input)
+    }
+
+    #[test]
+    fn glob_matches_wildcards_case_insensitively() {
+        assert!(glob_match("claude-*", "Claude-Sonnet-4"));
+        assert!(glob_match("gpt-4?", "gpt-4o"));
+        assert!(glob_match("*sonnet*", "claude-3-5-sonnet-latest"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("gpt-4?", "gpt-4"));
+        assert!(!glob_match("claude-*", "my-claude-model"));
+        assert!(!glob_match("gemini", "gemini-pro"));
+    }
+
+    #[test]
+    fn more_literal_characters_win_regardless_of_order() {
+        let rules = vec![rule(Some("claude-sonnet-*"), None, 2.0), rule(Some("claude-*"), None, 1.0)];
+        let catalogs = catalogs(rules, vec![]);
+        assert_eq!(price(&catalogs, "claude-sonnet-4", "claude", "2025-06-01"), Some(2.0));
+        assert_eq!(price(&catalogs, "claude-opus-4", "claude", "2025-06-01"), Some(1.0));
+    }
+
+    #[test]
+    fn equally_specific_globs_go_to_the_later_rule() {
+        // Both patterns have seven literal characters
+        let first = rule(Some("claude-*"), None, 1.0);
+        let second = rule(Some("*-sonnet"), None, 2.0);
+        let catalogs = catalogs(vec![first.clone(), second.clone()], vec![]);
+        assert_eq!(price(&catalogs, "claude-sonnet", "claude", "2025-06-01"), Some(2.0));
+
+        let catalogs = self::catalogs(vec![second, first], vec![]);
+        assert_eq!(price(&catalogs, "claude-sonnet", "claude", "2025-06-01"), Some(1.0));
+    }
+
+    #[test]
+    fn user_rules_win_ties_but_not_specificity() {
+        let catalogs = catalogs(vec![rule(Some("gpt-4o"), None, 1.0)], vec![rule(Some("GPT-4O"), None, 2.0)]);
+        assert_eq!(price(&catalogs, "gpt-4o", "openai", "2025-06-01"), Some(2.0));
+
+        let catalogs = self::catalogs(vec![rule(Some("gpt-4o"), None, 1.0)], vec![rule(Some("gpt-4?"), None, 2.0)]);
+        assert_eq!(price(&catalogs, "gpt-4o", "openai", "2025-06-01"), Some(1.0));
+    }
+
+    #[test]
+    fn model_rules_beat_provider_rules_beat_the_fallback() {
+        let rules = vec![rule(Some("*"), None, 2.0), rule(None, Some("claude"), 1.0), rule(None, None, 0.5)];
+        let catalogs = catalogs(rules, vec![]);
+        assert_eq!(price(&catalogs, "anything", "claude", "2025-06-01"), Some(2.0));
+
+        let catalogs = self::catalogs(vec![rule(None, Some("claude"), 1.0), rule(None, None, 0.5)], vec![]);
+        assert_eq!(price(&catalogs, "anything", "Claude", "2025-06-01"), Some(1.0));
+        assert_eq!(price(&catalogs, "anything", "openai", "2025-06-01"), Some(0.5));
+    }
+
+    #[test]
+    fn rules_take_effect_on_their_date() {
+        let mut newer = rule(Some("claude-*"), None, 2.0);
+        newer.effective_from = Some("2025-07-01".to_string());
+        let catalogs = catalogs(vec![newer, rule(Some("claude-*"), None, 1.0)], vec![]);
+        assert_eq!(price(&catalogs, "claude-x", "claude", "2025-06-30"), Some(1.0));
+        assert_eq!(price(&catalogs, "claude-x", "claude", "2025-07-01"), Some(2.0));
+    }
+}
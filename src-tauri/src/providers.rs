@@ -0,0 +1,127 @@
+//! Registry of the account providers ProxyPal connects through CLIProxyAPI.
+//!
+//! Each descriptor declares everything provider-specific the backend needs:
+//! how an account is connected (the management endpoint returning an OAuth
+//! URL, or why there is none), which credential files in `~/.cli-proxy-api`
+//! belong to it and which `owned_by` values its models carry on `/v1/models`.
+//! Auth status, health and the OAuth commands are driven from this list, so
+//! adding a provider means adding a descriptor.
+//!
+//! A provider is healthy when the proxy lists at least one of its models and
+//! at least one of its credential files is usable (neither disabled nor marked
+//! unavailable or errored by CLIProxyAPI), so each provider is judged on its
+//! own models and accounts rather than on the proxy answering at all.
+
+use serde::Serialize;
+
+/// How a provider's accounts are connected
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(tag = "type")]
+pub enum AuthMethod {
+    /// OAuth in the browser; the management API endpoint returning the authorization URL
+    #[serde(rename = "oauth")]
+    OAuth { endpoint: &'static str },
+    /// Credentials are imported from a file; `hint` explains how
+    #[serde(rename = "import")]
+    Import { hint: &'static str },
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderDescriptor {
+    /// Key used in auth status, health and commands ("claude", "openai", ...)
+    pub id: &'static str,
+    pub display_name: &'static str,
+    pub auth: AuthMethod,
+    /// Credential file name prefixes in `~/.cli-proxy-api` (lowercase)
+    pub auth_file_prefixes: &'static [&'static str],
+    /// `owned_by` values of the provider's models on `/v1/models`
+    pub model_owners: &'static [&'static str],
+}
+
+pub const PROVIDERS: &[ProviderDescriptor] = &[
+    ProviderDescriptor {
+        id: "claude",
+        display_name: "Claude",
+        auth: AuthMethod::OAuth { endpoint: "anthropic-auth-url" },
+        auth_file_prefixes: &["claude-", "anthropic-"],
+        model_owners: &["anthropic"],
+    },
+    ProviderDescriptor {
+        id: "openai",
+        display_name: "ChatGPT",
+        auth: AuthMethod::OAuth { endpoint: "codex-auth-url" },
+        auth_file_prefixes: &["codex-"],
+        model_owners: &["openai"],
+    },
+    ProviderDescriptor {
+        id: "gemini",
+        display_name: "Gemini",
+        auth: AuthMethod::OAuth { endpoint: "gemini-cli-auth-url" },
+        auth_file_prefixes: &["gemini-"],
+        model_owners: &["google"],
+    },
+    ProviderDescriptor {
+        id: "qwen",
+        display_name: "Qwen",
+        auth: AuthMethod::OAuth { endpoint: "qwen-auth-url" },
+        auth_file_prefixes: &["qwen-"],
+        model_owners: &["qwen"],
+    },
+    ProviderDescriptor {
+        id: "iflow",
+        display_name: "iFlow",
+        auth: AuthMethod::OAuth { endpoint: "iflow-auth-url" },
+        auth_file_prefixes: &["iflow-"],
+        model_owners: &["iflow"],
+    },
+    ProviderDescriptor {
+        id: "vertex",
+        display_name: "Vertex AI",
+        auth: AuthMethod::Import {
+            hint: "Vertex uses service account import, not OAuth. Use import_vertex_credential instead.",
+        },
+        auth_file_prefixes: &["vertex-"],
+        model_owners: &["google"],
+    },
+    ProviderDescriptor {
+        id: "antigravity",
+        display_name: "Antigravity",
+        auth: AuthMethod::OAuth { endpoint: "antigravity-auth-url" },
+        auth_file_prefixes: &["antigravity-"],
+        model_owners: &["antigravity"],
+    },
+];
+
+/// The provider with this id
+pub fn get(id: &str) -> Result<&'static ProviderDescriptor, String> {
+    PROVIDERS
+        .iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("Unknown provider: {}", id))
+}
+
+impl ProviderDescriptor {
+    /// Whether a credential file (any case) belongs to this provider
+    pub fn owns_auth_file(&self, filename: &str) -> bool {
+        let filename = filename.to_lowercase();
+        filename.ends_with(".json") && self.auth_file_prefixes.iter().any(|prefix| filename.starts_with(prefix))
+    }
+
+    /// Management API URL returning this provider's OAuth authorization URL.
+    /// `is_webui=true` uses CLIProxyAPI's embedded callback forwarder.
+    pub fn oauth_url_endpoint(&self, port: u16) -> Result<String, String> {
+        match self.auth {
+            // Use 127.0.0.1 consistently (not localhost) to avoid access control issues
+            AuthMethod::OAuth { endpoint } => {
+                Ok(format!("http://127.0.0.1:{}/v0/management/{}?is_webui=true", port, endpoint))
+            }
+            AuthMethod::Import { hint } => Err(hint.to_string()),
+        }
+    }
+}
+
+/// The provider a credential file belongs to
+pub fn for_auth_file(filename: &str) -> Option<&'static ProviderDescriptor> {
+    PROVIDERS.iter().find(|p| p.owns_auth_file(filename))
+}
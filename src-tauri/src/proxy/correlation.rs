@@ -0,0 +1,181 @@
+//! Request-ID correlation for CLIProxyAPI's log lines.
+//!
+//! The new log format tags every line with an 8-hex request ID. DEBUG lines
+//! that arrive before the request's access line ("Use OAuth ... for model X")
+//! are remembered here so the access line can be enriched with them, and the
+//! ID (or, for lines without one, a content hash) gives each request a stable
+//! identity for de-duplication.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+use crate::config::AppConfig;
+
+/// What we've learned about a request from lines logged before its access line
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+    pub model: Option<String>,
+    /// OAuth account email, or the masked API key for key-based routes
+    pub account: Option<String>,
+    /// Position of the API key in ProxyPal's key list for its provider
+    pub api_key_index: Option<u32>,
+    /// Most recent other lines logged under the request ID (upstream errors, retries, ...)
+    pub lines: Vec<String>,
+}
+
+/// How many related lines are kept per request
+const MAX_CONTEXT_LINES: usize = 8;
+
+impl RequestContext {
+    pub fn push_line(&mut self, line: &str) {
+        if self.lines.len() >= MAX_CONTEXT_LINES {
+            self.lines.remove(0);
+        }
+        self.lines.push(line.trim_end().to_string());
+    }
+}
+
+/// Bounded least-recently-used map
+pub struct LruCache<K, V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<K, (V, u64)>,
+    order: BTreeMap<u64, K>,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn touch(&mut self, key: &K) {
+        self.tick += 1;
+        if let Some((_, last_used)) = self.entries.get_mut(key) {
+            self.order.remove(last_used);
+            *last_used = self.tick;
+            self.order.insert(self.tick, key.clone());
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let (value, last_used) = self.entries.remove(key)?;
+        self.order.remove(&last_used);
+        Some(value)
+    }
+
+    /// Get the entry for `key`, inserting a default one if missing
+    pub fn get_or_insert_default(&mut self, key: K) -> &mut V
+    where
+        V: Default,
+    {
+        if self.entries.contains_key(&key) {
+            self.touch(&key);
+        } else {
+            self.insert(key.clone(), V::default());
+        }
+        &mut self.entries.get_mut(&key).expect("entry was just inserted").0
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        self.tick += 1;
+        if let Some((_, last_used)) = self.entries.insert(key.clone(), (value, self.tick)) {
+            self.order.remove(&last_used);
+        }
+        self.order.insert(self.tick, key);
+
+        while self.entries.len() > self.capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
+/// Request IDs are 8 hex chars; "--------" means the line has none
+pub fn normalize_request_id(raw: &str) -> Option<String> {
+    if raw.len() == 8 && raw.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(raw.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+/// Stable 64-bit FNV-1a hash (unlike `DefaultHasher`, guaranteed not to change between builds)
+fn fnv1a(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in data {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Deterministic request ID so replaying the same log line (e.g. catch-up after
+/// a restart) never produces a second entry.
+///
+/// Upstream IDs are only 32 bits and repeat over time, so they're scoped by the
+/// request's timestamp. Lines without an ID fall back to a hash of the line.
+pub fn stable_request_id(timestamp: u64, upstream_id: Option<&str>, line: &str) -> String {
+    match upstream_id {
+        Some(id) => format!("req_{}_{}", timestamp, id),
+        None => format!("req_{}_h{:016x}", timestamp, fnv1a(line.trim_end().as_bytes())),
+    }
+}
+
+/// Mask an API key the same way CLIProxyAPI does before logging it
+pub fn mask_api_key(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    let n = chars.len();
+    let keep = if n > 8 {
+        4
+    } else if n > 4 {
+        2
+    } else if n > 2 {
+        1
+    } else {
+        return key.to_string();
+    };
+    let head: String = chars[..keep].iter().collect();
+    let tail: String = chars[n - keep..].iter().collect();
+    format!("{}...{}", head, tail)
+}
+
+/// Maps the masked keys that appear in logs back to ProxyPal's configured API keys
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeyDirectory {
+    /// (masked key, provider, index within that provider's key list)
+    entries: Vec<(String, &'static str, u32)>,
+}
+
+impl ApiKeyDirectory {
+    pub fn from_config(config: &AppConfig) -> Self {
+        let mut entries = Vec::new();
+        let mut add = |provider: &'static str, keys: Vec<&String>| {
+            for (i, key) in keys.into_iter().enumerate() {
+                entries.push((mask_api_key(key), provider, i as u32));
+            }
+        };
+        add("claude", config.claude_api_keys.iter().map(|k| &k.api_key).collect());
+        add("gemini", config.gemini_api_keys.iter().map(|k| &k.api_key).collect());
+        add("openai", config.codex_api_keys.iter().map(|k| &k.api_key).collect());
+        add("vertex", config.vertex_api_keys.iter().map(|k| &k.api_key).collect());
+        Self { entries }
+    }
+
+    /// Index of the key whose masked form is `masked`, preferring keys of `provider`
+    /// (two providers' keys can share a masked form)
+    pub fn lookup(&self, masked: &str, provider: &str) -> Option<u32> {
+        let mut matches = self.entries.iter().filter(|(m, _, _)| m == masked);
+        let first = matches.clone().next()?;
+        matches
+            .find(|(_, p, _)| *p == provider)
+            .or(Some(first))
+            .map(|(_, _, i)| *i)
+    }
+}
@@ -0,0 +1,87 @@
+//! Table of API endpoints the log watcher tracks.
+//!
+//! Each entry maps a path fragment to the protocol it speaks and the kind of
+//! call it is, so analytics can tell generation traffic apart from auxiliary
+//! calls like embeddings or token counting.
+
+use crate::types::TrackedEndpoint;
+
+pub const KIND_GENERATION: &str = "generation";
+pub const KIND_EMBEDDING: &str = "embedding";
+pub const KIND_TOKEN_COUNT: &str = "count_tokens";
+pub const KIND_IMAGE: &str = "image";
+pub const KIND_AUDIO: &str = "audio";
+pub const KIND_MODERATION: &str = "moderation";
+
+/// Built-in endpoints: (path fragment, protocol, kind).
+/// Order matters - more specific fragments must come before their prefixes.
+const BUILTIN_ENDPOINTS: &[(&str, &str, &str)] = &[
+    // Anthropic
+    ("/v1/messages/count_tokens", "claude", KIND_TOKEN_COUNT),
+    ("/v1/messages", "claude", KIND_GENERATION),
+    // OpenAI
+    ("/chat/completions", "openai", KIND_GENERATION),
+    ("/v1/completions", "openai", KIND_GENERATION),
+    ("/v1/responses", "openai", KIND_GENERATION),
+    ("/v1/embeddings", "openai", KIND_EMBEDDING),
+    ("/v1/images/generations", "openai", KIND_IMAGE),
+    ("/v1/images/edits", "openai", KIND_IMAGE),
+    ("/v1/images/variations", "openai", KIND_IMAGE),
+    ("/v1/audio/", "openai", KIND_AUDIO),
+    ("/v1/moderations", "openai", KIND_MODERATION),
+    // Gemini
+    (":countTokens", "gemini", KIND_TOKEN_COUNT),
+    (":batchEmbedContents", "gemini", KIND_EMBEDDING),
+    (":embedContent", "gemini", KIND_EMBEDDING),
+    (":streamGenerateContent", "gemini", KIND_GENERATION),
+    (":generateContent", "gemini", KIND_GENERATION),
+    (":predict", "gemini", KIND_IMAGE),
+];
+
+/// A matched endpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointMatch {
+    pub protocol: String,
+    pub kind: String,
+}
+
+/// Endpoint lookup table: user-defined paths first, then the built-ins
+#[derive(Debug, Clone, Default)]
+pub struct EndpointTable {
+    custom: Vec<TrackedEndpoint>,
+}
+
+impl EndpointTable {
+    pub fn new(custom: Vec<TrackedEndpoint>) -> Self {
+        let custom = custom
+            .into_iter()
+            .filter(|e| !e.path.trim().is_empty())
+            .collect();
+        Self { custom }
+    }
+
+    /// Find the endpoint a request path belongs to, or None if it isn't tracked
+    pub fn classify(&self, path: &str) -> Option<EndpointMatch> {
+        // Ignore query strings (e.g. "?alt=sse") when matching
+        let path = path.split('?').next().unwrap_or(path);
+
+        if let Some(custom) = self.custom.iter().find(|e| path.contains(e.path.trim())) {
+            return Some(EndpointMatch {
+                protocol: custom.protocol.clone(),
+                kind: if custom.kind.is_empty() {
+                    KIND_GENERATION.to_string()
+                } else {
+                    custom.kind.clone()
+                },
+            });
+        }
+
+        BUILTIN_ENDPOINTS
+            .iter()
+            .find(|(fragment, _, _)| path.contains(fragment))
+            .map(|(_, protocol, kind)| EndpointMatch {
+                protocol: protocol.to_string(),
+                kind: kind.to_string(),
+            })
+    }
+}
@@ -0,0 +1,151 @@
+//! Failure classification for tracked requests.
+//!
+//! 401/403, 408 and 429 are unambiguous and classified by status alone. Other
+//! statuses often aren't (CLIProxyAPI reports some quota exhaustion as 500, and
+//! timeouts as 502), so for those the lines logged under the same request ID
+//! are checked for more specific hints first.
+
+pub const CATEGORY_QUOTA: &str = "quota";
+pub const CATEGORY_AUTH: &str = "auth";
+pub const CATEGORY_TIMEOUT: &str = "timeout";
+pub const CATEGORY_UPSTREAM: &str = "upstream";
+pub const CATEGORY_CLIENT: &str = "client";
+
+/// Hints searched for (lowercased) in related log lines, most specific first
+const LINE_HINTS: &[(&str, &[&str])] = &[
+    (
+        CATEGORY_TIMEOUT,
+        &["timeout", "timed out", "deadline exceeded"],
+    ),
+    (
+        CATEGORY_QUOTA,
+        &[
+            "quota",
+            "rate limit",
+            "rate_limit",
+            "resource_exhausted",
+            "too many requests",
+            "usage limit",
+        ],
+    ),
+    (
+        CATEGORY_AUTH,
+        &[
+            "unauthorized",
+            "invalid_grant",
+            "token expired",
+            "token has expired",
+            "invalid api key",
+            "invalid x-api-key",
+            "permission denied",
+            "permission_denied",
+            "forbidden",
+            "authentication",
+        ],
+    ),
+];
+
+/// Maximum length of the example message kept per failed request
+const MAX_MESSAGE_LEN: usize = 500;
+
+/// Classify a failed request. Returns None for successful requests.
+pub fn classify_failure(status: u16, related_lines: &[String]) -> Option<&'static str> {
+    match status {
+        0..=399 => return None,
+        401 | 403 => return Some(CATEGORY_AUTH),
+        408 => return Some(CATEGORY_TIMEOUT),
+        429 => return Some(CATEGORY_QUOTA),
+        _ => {}
+    }
+
+    for (category, hints) in LINE_HINTS {
+        let matched = related_lines.iter().any(|line| {
+            let lower = line.to_lowercase();
+            hints.iter().any(|hint| lower.contains(hint))
+        });
+        if matched {
+            return Some(category);
+        }
+    }
+
+    Some(match status {
+        504 => CATEGORY_TIMEOUT,
+        s if s >= 500 => CATEGORY_UPSTREAM,
+        _ => CATEGORY_CLIENT,
+    })
+}
+
+/// Pick the related line that best explains the failure (the last error-looking one)
+pub fn failure_message(related_lines: &[String]) -> Option<String> {
+    let is_error_like = |line: &&String| {
+        let lower = line.to_lowercase();
+        lower.contains("error")
+            || lower.contains("warn")
+            || lower.contains("fail")
+            || LINE_HINTS
+                .iter()
+                .any(|(_, hints)| hints.iter().any(|hint| lower.contains(hint)))
+    };
+    let line = related_lines
+        .iter()
+        .rev()
+        .find(is_error_like)
+        .or_else(|| related_lines.last())?;
+    Some(line.trim().chars().take(MAX_MESSAGE_LEN).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn successful_requests_are_not_failures() {
+        assert_eq!(classify_failure(200, &lines(&["error: quota exceeded"])), None);
+        assert_eq!(classify_failure(304, &[]), None);
+    }
+
+    #[test]
+    fn unambiguous_statuses_ignore_hints() {
+        assert_eq!(classify_failure(429, &lines(&["upstream request timed out"])), Some(CATEGORY_QUOTA));
+        assert_eq!(classify_failure(401, &lines(&["rate limit reached"])), Some(CATEGORY_AUTH));
+        assert_eq!(classify_failure(403, &lines(&["deadline exceeded"])), Some(CATEGORY_AUTH));
+        assert_eq!(classify_failure(408, &lines(&["Unauthorized"])), Some(CATEGORY_TIMEOUT));
+    }
+
+    #[test]
+    fn hints_refine_ambiguous_statuses() {
+        assert_eq!(classify_failure(500, &lines(&["RESOURCE_EXHAUSTED: Quota"])), Some(CATEGORY_QUOTA));
+        assert_eq!(classify_failure(502, &lines(&["dial tcp: i/o Timed Out"])), Some(CATEGORY_TIMEOUT));
+        assert_eq!(classify_failure(400, &lines(&["invalid_grant"])), Some(CATEGORY_AUTH));
+    }
+
+    #[test]
+    fn timeout_hints_take_precedence_over_quota_and_auth() {
+        let related = lines(&["token expired", "rate limit", "context deadline exceeded"]);
+        assert_eq!(classify_failure(500, &related), Some(CATEGORY_TIMEOUT));
+        let related = lines(&["permission denied", "usage limit reached"]);
+        assert_eq!(classify_failure(500, &related), Some(CATEGORY_QUOTA));
+    }
+
+    #[test]
+    fn status_decides_without_hints() {
+        let related = lines(&["request finished"]);
+        assert_eq!(classify_failure(504, &related), Some(CATEGORY_TIMEOUT));
+        assert_eq!(classify_failure(500, &related), Some(CATEGORY_UPSTREAM));
+        assert_eq!(classify_failure(503, &[]), Some(CATEGORY_UPSTREAM));
+        assert_eq!(classify_failure(400, &related), Some(CATEGORY_CLIENT));
+        assert_eq!(classify_failure(404, &[]), Some(CATEGORY_CLIENT));
+    }
+
+    #[test]
+    fn message_prefers_the_last_error_line() {
+        let related = lines(&["  error: first  ", "warn: second", "done"]);
+        assert_eq!(failure_message(&related).as_deref(), Some("warn: second"));
+        assert_eq!(failure_message(&lines(&["done "])).as_deref(), Some("done"));
+        assert_eq!(failure_message(&[]), None);
+    }
+}
@@ -0,0 +1,340 @@
+//! Resumable tailing of CLIProxyAPI's `main.log`.
+//!
+//! The tailer remembers which file it was reading (by inode, or creation time
+//! where inodes aren't available) and how far it got, so the log watcher can
+//! pick up where it left off after a restart and drain a rotated file before
+//! moving on to the fresh `main.log`.
+
+use std::fs::{File, Metadata};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::get_log_cursor_path;
+use crate::types::{FileIdentity, LogCursor};
+
+/// Minimum time between cursor writes while the log keeps moving
+pub const CURSOR_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Identify a file independently of its name so a rename (rotation) is detectable
+pub fn file_identity(meta: &Metadata) -> FileIdentity {
+    #[cfg(unix)]
+    let inode = {
+        use std::os::unix::fs::MetadataExt;
+        meta.ino()
+    };
+    #[cfg(not(unix))]
+    let inode = 0;
+
+    let created_ms = meta
+        .created()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+
+    FileIdentity { inode, created_ms }
+}
+
+/// Load the persisted cursor, if any
+pub fn load_cursor() -> Option<LogCursor> {
+    let data = std::fs::read_to_string(get_log_cursor_path()).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+/// Persist the cursor (atomic write so a crash never leaves a half-written file)
+pub fn save_cursor(cursor: &LogCursor) -> Result<(), String> {
+    let path = get_log_cursor_path();
+    let temp_path = path.with_extension("json.tmp");
+    let data = serde_json::to_string_pretty(cursor).map_err(|e| e.to_string())?;
+    std::fs::write(&temp_path, data).map_err(|e| e.to_string())?;
+    std::fs::rename(&temp_path, &path).map_err(|e| e.to_string())
+}
+
+/// Find a rotated log file in the same directory that is still the file the cursor points at
+fn find_rotated_file(log_path: &Path, identity: &FileIdentity) -> Option<PathBuf> {
+    let dir = log_path.parent()?;
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|p| p.as_path() != log_path && p.is_file())
+        .find(|p| {
+            std::fs::metadata(p)
+                .map(|m| file_identity(&m).same_file(identity))
+                .unwrap_or(false)
+        })
+}
+
+pub struct LogTailer {
+    /// Path of the live log file (`main.log`)
+    path: PathBuf,
+    reader: BufReader<File>,
+    identity: FileIdentity,
+    offset: u64,
+    /// True while reading a rotated file found on startup; switch to `path` once drained
+    draining_rotated: bool,
+}
+
+impl LogTailer {
+    /// Open `path` and position the reader according to the saved cursor.
+    ///
+    /// - Same file as the cursor: resume at the saved offset (or from the start if
+    ///   the file was truncated below it).
+    /// - Different file: the old one was rotated while we weren't watching. Drain
+    ///   it from the saved offset if it can still be found, then read `path` from
+    ///   the start.
+    /// - No cursor: first run, only watch new entries.
+    pub fn open(path: PathBuf, cursor: Option<LogCursor>) -> std::io::Result<Self> {
+        let file = File::open(&path)?;
+        let meta = file.metadata()?;
+        let identity = file_identity(&meta);
+
+        let Some(cursor) = cursor else {
+            return Self::from_file(path, file, identity, meta.len(), false);
+        };
+
+        if identity.same_file(&cursor.identity) {
+            let offset = if cursor.offset <= meta.len() { cursor.offset } else { 0 };
+            return Self::from_file(path, file, identity, offset, false);
+        }
+
+        if let Some(rotated) = find_rotated_file(&path, &cursor.identity) {
+            println!("[LogWatcher] Catching up on rotated log: {:?}", rotated);
+            let rotated_file = File::open(&rotated)?;
+            let rotated_len = rotated_file.metadata()?.len();
+            let offset = cursor.offset.min(rotated_len);
+            return Self::from_file(path, rotated_file, cursor.identity, offset, true);
+        }
+
+        Self::from_file(path, file, identity, 0, false)
+    }
+
+    fn from_file(
+        path: PathBuf,
+        file: File,
+        identity: FileIdentity,
+        offset: u64,
+        draining_rotated: bool,
+    ) -> std::io::Result<Self> {
+        let mut reader = BufReader::new(file);
+        reader.seek(SeekFrom::Start(offset))?;
+        Ok(Self {
+            path,
+            reader,
+            identity,
+            offset,
+            draining_rotated,
+        })
+    }
+
+    /// Current position, suitable for persisting with `save_cursor`
+    pub fn cursor(&self) -> LogCursor {
+        let size = self
+            .reader
+            .get_ref()
+            .metadata()
+            .map(|m| m.len())
+            .unwrap_or(self.offset);
+        LogCursor {
+            path: self.path.to_string_lossy().to_string(),
+            identity: self.identity,
+            size,
+            offset: self.offset,
+        }
+    }
+
+    /// Read every complete line available in the current file.
+    /// A trailing partial line is left for the next poll, unless `final_read` says
+    /// the file won't be written to again (rotated away), in which case it's emitted.
+    fn read_available(&mut self, on_line: &mut dyn FnMut(&str), final_read: bool) -> std::io::Result<()> {
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            let n = self.reader.read_until(b'\n', &mut buf)?;
+            if n == 0 {
+                return Ok(());
+            }
+            if buf.last() != Some(&b'\n') && !final_read {
+                // Writer is mid-line; rewind and pick it up once it's complete
+                self.reader.seek(SeekFrom::Start(self.offset))?;
+                return Ok(());
+            }
+            self.offset += n as u64;
+            on_line(&String::from_utf8_lossy(&buf));
+        }
+    }
+
+    fn switch_to_live_file(&mut self) -> std::io::Result<()> {
+        let file = File::open(&self.path)?;
+        self.identity = file_identity(&file.metadata()?);
+        self.reader = BufReader::new(file);
+        self.offset = 0;
+        self.draining_rotated = false;
+        Ok(())
+    }
+
+    /// Feed new lines to `on_line`, following rotations and truncations.
+    /// Returns true if the cursor moved.
+    pub fn poll(&mut self, mut on_line: impl FnMut(&str)) -> std::io::Result<bool> {
+        let start = self.cursor();
+
+        loop {
+            self.read_available(&mut on_line, false)?;
+
+            if self.draining_rotated {
+                // The rotated file is complete; its last line may lack a newline
+                self.read_available(&mut on_line, true)?;
+                self.switch_to_live_file()?;
+                continue;
+            }
+
+            let meta = match std::fs::metadata(&self.path) {
+                Ok(m) => m,
+                // main.log briefly missing mid-rotation; try again next poll
+                Err(_) => break,
+            };
+
+            if !file_identity(&meta).same_file(&self.identity) {
+                // Rotated: anything written to the old file after our last read is
+                // still reachable through the open handle, so drain it first
+                self.read_available(&mut on_line, true)?;
+                println!("[LogWatcher] Log rotated, switching to new file");
+                self.switch_to_live_file()?;
+                continue;
+            }
+
+            if meta.len() < self.offset {
+                // Truncated in place
+                self.reader.seek(SeekFrom::Start(0))?;
+                self.offset = 0;
+                continue;
+            }
+
+            break;
+        }
+
+        let end = self.cursor();
+        Ok(end.offset != start.offset || !end.identity.same_file(&start.identity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("proxypal-log-tail-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn append(path: &Path, text: &str) {
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path).unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    fn poll_lines(tailer: &mut LogTailer) -> Vec<String> {
+        let mut lines = Vec::new();
+        tailer.poll(|line| lines.push(line.to_string())).unwrap();
+        lines
+    }
+
+    #[test]
+    fn first_run_starts_at_the_end() {
+        let dir = temp_dir();
+        let log = dir.join("main.log");
+        append(&log, "old\n");
+        let mut tailer = LogTailer::open(log.clone(), None).unwrap();
+        assert!(poll_lines(&mut tailer).is_empty());
+
+        append(&log, "new\n");
+        assert_eq!(poll_lines(&mut tailer), ["new\n"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn partial_line_waits_for_its_newline() {
+        let dir = temp_dir();
+        let log = dir.join("main.log");
+        append(&log, "");
+        let mut tailer = LogTailer::open(log.clone(), None).unwrap();
+
+        append(&log, "complete\npart");
+        assert_eq!(poll_lines(&mut tailer), ["complete\n"]);
+        assert_eq!(tailer.cursor().offset, 9);
+
+        append(&log, "ial\n");
+        assert_eq!(poll_lines(&mut tailer), ["partial\n"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotated_and_recreated_file_is_drained_first() {
+        let dir = temp_dir();
+        let log = dir.join("main.log");
+        append(&log, "");
+        let mut tailer = LogTailer::open(log.clone(), None).unwrap();
+        append(&log, "a\n");
+        assert_eq!(poll_lines(&mut tailer), ["a\n"]);
+
+        // Written after our last read, then rotated; the last line has no newline
+        append(&log, "b\nlast");
+        std::fs::rename(&log, dir.join("main.log.1")).unwrap();
+        append(&log, "c\n");
+
+        assert_eq!(poll_lines(&mut tailer), ["b\n", "last", "c\n"]);
+        assert_eq!(tailer.cursor().offset, 2);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotation_while_stopped_resumes_from_the_cursor() {
+        let dir = temp_dir();
+        let log = dir.join("main.log");
+        append(&log, "");
+        let mut tailer = LogTailer::open(log.clone(), None).unwrap();
+        append(&log, "a\n");
+        poll_lines(&mut tailer);
+        let cursor = tailer.cursor();
+        drop(tailer);
+
+        append(&log, "b\n");
+        std::fs::rename(&log, dir.join("main.log.1")).unwrap();
+        append(&log, "c\n");
+
+        let mut tailer = LogTailer::open(log.clone(), Some(cursor)).unwrap();
+        assert_eq!(poll_lines(&mut tailer), ["b\n", "c\n"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn truncated_file_is_read_from_the_start() {
+        let dir = temp_dir();
+        let log = dir.join("main.log");
+        append(&log, "");
+        let mut tailer = LogTailer::open(log.clone(), None).unwrap();
+        append(&log, "first line\nsecond line\n");
+        assert_eq!(poll_lines(&mut tailer).len(), 2);
+
+        std::fs::OpenOptions::new().write(true).open(&log).unwrap().set_len(0).unwrap();
+        append(&log, "x\n");
+        assert_eq!(poll_lines(&mut tailer), ["x\n"]);
+        assert_eq!(tailer.cursor().offset, 2);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn cursor_past_the_end_of_a_truncated_file_restarts() {
+        let dir = temp_dir();
+        let log = dir.join("main.log");
+        append(&log, "short\n");
+        let mut cursor = LogTailer::open(log.clone(), None).unwrap().cursor();
+        cursor.offset = 1_000;
+
+        let mut tailer = LogTailer::open(log.clone(), Some(cursor)).unwrap();
+        assert_eq!(poll_lines(&mut tailer), ["short\n"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1 +1,8 @@
 // Proxy-specific helpers (config generation, log watcher, etc.) will live here.
+
+pub mod correlation;
+pub mod endpoints;
+pub mod failures;
+pub mod log_tail;
+pub mod usage_reconcile;
+pub mod usage_snapshot;
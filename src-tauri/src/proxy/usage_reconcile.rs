@@ -0,0 +1,95 @@
+//! Matching CLIProxyAPI usage details to requests seen by the log watcher.
+//!
+//! GIN access lines carry no token counts, while the usage API reports tokens
+//! per request without saying which log line they belong to. A detail is
+//! matched by request ID when both sides carry one, otherwise to a request for
+//! the same model whose time span (completion time minus duration) contains the
+//! detail's timestamp, allowing for the log's one-second resolution.
+
+use std::collections::HashMap;
+
+use crate::proxy::usage_snapshot::UsageDetail;
+use crate::types::RequestLog;
+
+/// Tolerance around a request's time span, in ms
+const MATCH_SLACK_MS: u64 = 2_000;
+
+/// Model names from the log and from the usage API may differ in case or carry a provider prefix
+fn normalize_model(model: &str) -> String {
+    model.rsplit('/').next().unwrap_or(model).to_ascii_lowercase()
+}
+
+/// Distance (ms) between a detail timestamp and a request's time span, or None if outside the window
+fn span_distance(req: &RequestLog, timestamp: u64) -> Option<u64> {
+    let end = req.timestamp;
+    let start = end.saturating_sub(req.duration_ms);
+    if timestamp + MATCH_SLACK_MS < start || timestamp > end + MATCH_SLACK_MS {
+        return None;
+    }
+    Some(if timestamp < start {
+        start - timestamp
+    } else {
+        timestamp.saturating_sub(end)
+    })
+}
+
+/// Pair usage details with requests. Returns (request index, detail index) pairs;
+/// each request and each detail is used at most once.
+pub fn match_usage_details(requests: &[RequestLog], details: &[UsageDetail]) -> Vec<(usize, usize)> {
+    let mut taken = vec![false; requests.len()];
+    let mut matches = Vec::new();
+
+    let by_request_id: HashMap<String, usize> = requests
+        .iter()
+        .enumerate()
+        .filter_map(|(i, r)| r.request_id.as_ref().map(|id| (id.to_ascii_lowercase(), i)))
+        .collect();
+
+    let mut by_model: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, req) in requests.iter().enumerate() {
+        by_model.entry(normalize_model(&req.model)).or_default().push(i);
+    }
+
+    let mut order: Vec<usize> = (0..details.len()).collect();
+    order.sort_by_key(|&i| details[i].timestamp);
+
+    // Request IDs are unambiguous, so claim those before any timestamp matching
+    let mut pending = Vec::new();
+    for d in order {
+        let id_match = details[d]
+            .request_id
+            .as_ref()
+            .and_then(|id| by_request_id.get(&id.to_ascii_lowercase()))
+            .copied()
+            .filter(|&r| !taken[r]);
+        match id_match {
+            Some(r) => {
+                taken[r] = true;
+                matches.push((r, d));
+            }
+            None => pending.push(d),
+        }
+    }
+
+    for d in pending {
+        let detail = &details[d];
+        let Some(candidates) = by_model.get(&normalize_model(&detail.model)) else {
+            continue;
+        };
+        let best = candidates
+            .iter()
+            .copied()
+            .filter(|&r| !taken[r] && (requests[r].status >= 400) == detail.failed)
+            .filter_map(|r| {
+                let distance = span_distance(&requests[r], detail.timestamp)?;
+                Some((distance, requests[r].timestamp.abs_diff(detail.timestamp), r))
+            })
+            .min();
+        if let Some((_, _, r)) = best {
+            taken[r] = true;
+            matches.push((r, d));
+        }
+    }
+
+    matches
+}
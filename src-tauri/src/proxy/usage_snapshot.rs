@@ -0,0 +1,172 @@
+//! Persistence for CLIProxyAPI's in-memory usage statistics.
+//!
+//! CLIProxyAPI loses its usage counters whenever it exits. ProxyPal snapshots
+//! the proxy's usage export periodically and on stop, re-imports the last
+//! snapshot on start, and records every usage detail it sees in a ledger in the
+//! request store. Details are keyed the same way CLIProxyAPI de-duplicates them
+//! on import, so overlapping snapshots are only counted once and cumulative
+//! totals never go backwards.
+
+use serde_json::Value;
+
+use crate::config::get_usage_snapshot_path;
+use crate::get_management_key;
+
+/// One request from a usage snapshot (`apis.<api>.models.<model>.details[]`)
+#[derive(Debug, Clone)]
+pub struct UsageDetail {
+    /// Identity used to de-duplicate details across snapshots
+    pub key: String,
+    /// CLIProxyAPI's request ID, when the detail carries one
+    pub request_id: Option<String>,
+    pub timestamp: u64,
+    pub api: String,
+    pub model: String,
+    pub source: String,
+    pub auth_index: String,
+    pub failed: bool,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub reasoning_tokens: u64,
+    pub cached_tokens: u64,
+    pub total_tokens: u64,
+}
+
+/// Flatten the per-request details of a usage snapshot (the `usage` object)
+pub fn usage_details(usage: &Value) -> Vec<UsageDetail> {
+    let mut details = Vec::new();
+    let Some(apis) = usage.get("apis").and_then(|v| v.as_object()) else {
+        return details;
+    };
+
+    for (api, api_data) in apis {
+        let Some(models) = api_data.get("models").and_then(|v| v.as_object()) else {
+            continue;
+        };
+        for (model, model_data) in models {
+            let Some(entries) = model_data.get("details").and_then(|v| v.as_array()) else {
+                continue;
+            };
+            for entry in entries {
+                let Some(raw_timestamp) = entry.get("timestamp").and_then(|v| v.as_str()) else {
+                    continue;
+                };
+                let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(raw_timestamp) else {
+                    continue;
+                };
+                let text = |field: &str| entry.get(field).and_then(|v| v.as_str()).unwrap_or("").to_string();
+                let tokens = entry.get("tokens");
+                let count = |field: &str| {
+                    tokens
+                        .and_then(|t| t.get(field))
+                        .and_then(|v| v.as_u64())
+                        .unwrap_or(0)
+                };
+
+                let source = text("source");
+                let auth_index = text("auth_index");
+                let failed = entry.get("failed").and_then(|v| v.as_bool()).unwrap_or(false);
+                let input_tokens = count("input_tokens");
+                let output_tokens = count("output_tokens");
+                let reasoning_tokens = count("reasoning_tokens");
+                let cached_tokens = count("cached_tokens");
+                let total_tokens = count("total_tokens");
+
+                details.push(UsageDetail {
+                    key: format!(
+                        "{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}",
+                        api,
+                        model,
+                        raw_timestamp,
+                        source,
+                        auth_index,
+                        failed,
+                        input_tokens,
+                        output_tokens,
+                        reasoning_tokens,
+                        cached_tokens,
+                        total_tokens
+                    ),
+                    request_id: entry
+                        .get("request_id")
+                        .and_then(|v| v.as_str())
+                        .filter(|id| !id.is_empty())
+                        .map(str::to_string),
+                    timestamp: timestamp.timestamp_millis().max(0) as u64,
+                    api: api.clone(),
+                    model: model.clone(),
+                    source,
+                    auth_index,
+                    failed,
+                    input_tokens,
+                    output_tokens,
+                    reasoning_tokens,
+                    cached_tokens,
+                    total_tokens,
+                });
+            }
+        }
+    }
+    details
+}
+
+/// Fetch CLIProxyAPI's usage export (`{ "version", "exported_at", "usage" }`)
+pub async fn fetch_export(port: u16) -> Result<Value, String> {
+    let export_url = format!("http://127.0.0.1:{}/v0/management/usage/export", port);
+    let response = reqwest::Client::new()
+        .get(&export_url)
+        .header("X-Management-Key", &get_management_key())
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to export usage: {}. Is the proxy running?", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("Export API returned status: {}", response.status()));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse export response: {}", e))
+}
+
+/// Merge a usage export into CLIProxyAPI (it skips details it already has)
+pub async fn import_export(port: u16, data: &Value) -> Result<Value, String> {
+    let import_url = format!("http://127.0.0.1:{}/v0/management/usage/import", port);
+    let response = reqwest::Client::new()
+        .post(&import_url)
+        .header("X-Management-Key", &get_management_key())
+        .header("Content-Type", "application/json")
+        .json(data)
+        .timeout(std::time::Duration::from_secs(30))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to import usage: {}. Is the proxy running?", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Import API returned status: {} - {}", status, body));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse import response: {}", e))
+}
+
+/// Write the snapshot file atomically
+pub fn save_snapshot(export: &Value) -> Result<(), String> {
+    let path = get_usage_snapshot_path();
+    let temp_path = path.with_extension("json.tmp");
+    let data = serde_json::to_string(export).map_err(|e| e.to_string())?;
+    std::fs::write(&temp_path, data).map_err(|e| e.to_string())?;
+    std::fs::rename(&temp_path, &path).map_err(|e| e.to_string())
+}
+
+/// The last saved snapshot, if there is one
+pub fn load_snapshot() -> Option<Value> {
+    let data = std::fs::read_to_string(get_usage_snapshot_path()).ok()?;
+    serde_json::from_str(&data).ok()
+}
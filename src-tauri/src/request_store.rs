@@ -0,0 +1,1772 @@
+//! SQLite-backed request log.
+//!
+//! Every request the log watcher sees is stored here without a row cap, so the
+//! Analytics page can query arbitrary time ranges and filters. `history.json`
+//! keeps only the most recent requests for the live table, and `aggregate.json`
+//! can be rebuilt from this store at any time.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::get_requests_db_path;
+use crate::proxy::correlation::mask_api_key;
+use crate::proxy::endpoints::KIND_TOKEN_COUNT;
+use crate::proxy::usage_reconcile::match_usage_details;
+use crate::proxy::usage_snapshot::UsageDetail;
+use crate::retention::{apply_series_retention, prune_series_before};
+use crate::timezone::{now_ms, DisplayTimezone, DAY_FORMAT, HOUR_FORMAT, MONTH_FORMAT};
+use crate::types::{
+    AccountUsage, Aggregate, ErrorCause, ErrorSeriesPoint, ModelDrift, ModelStats, RequestHistory, RequestLog,
+    RequestPage, RequestQuery, TimeSeriesPoint, UsageReconcileSummary, UsageRetention, UsageSeriesPoint,
+};
+use crate::pricing::{self, TokenUsage};
+use crate::utils::detect_provider_from_model;
+
+/// Hex digits of the key's SHA-256 kept in its fingerprint
+const FINGERPRINT_HASH_LEN: usize = 12;
+
+/// Stored form of a client API key: the masked key plus a short SHA-256, e.g.
+/// `sk-a...wxyz#3f9c0b1d27e4`. Stable, so filters and budgets can still match a
+/// key, but the key itself never reaches the database. Already-fingerprinted
+/// values are returned unchanged.
+pub fn api_key_fingerprint(key: &str) -> String {
+    if is_api_key_fingerprint(key) {
+        return key.to_string();
+    }
+    let digest = Sha256::digest(key.as_bytes());
+    let hash: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}#{}", mask_api_key(key), &hash[..FINGERPRINT_HASH_LEN])
+}
+
+fn is_api_key_fingerprint(value: &str) -> bool {
+    value.rsplit_once('#').is_some_and(|(masked, hash)| {
+        masked.contains("...")
+            && hash.len() == FINGERPRINT_HASH_LEN
+            && hash.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase())
+    })
+}
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS requests (
+    id            TEXT PRIMARY KEY,
+    timestamp     INTEGER NOT NULL,
+    provider      TEXT NOT NULL,
+    model         TEXT NOT NULL,
+    method        TEXT NOT NULL,
+    path          TEXT NOT NULL,
+    status        INTEGER NOT NULL,
+    duration_ms   INTEGER NOT NULL,
+    tokens_in     INTEGER,
+    tokens_out    INTEGER,
+    tokens_cached INTEGER,
+    api_key       TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_requests_timestamp ON requests(timestamp);
+CREATE INDEX IF NOT EXISTS idx_requests_model ON requests(model, timestamp);
+CREATE INDEX IF NOT EXISTS idx_requests_provider ON requests(provider, timestamp);
+CREATE INDEX IF NOT EXISTS idx_requests_status ON requests(status, timestamp);
+CREATE INDEX IF NOT EXISTS idx_requests_api_key ON requests(api_key, timestamp);
+
+CREATE TABLE IF NOT EXISTS meta (
+    key   TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS usage_ledger (
+    key              TEXT PRIMARY KEY,
+    timestamp        INTEGER NOT NULL,
+    api              TEXT NOT NULL,
+    model            TEXT NOT NULL,
+    source           TEXT NOT NULL,
+    auth_index       TEXT NOT NULL,
+    failed           INTEGER NOT NULL,
+    tokens_in        INTEGER NOT NULL,
+    tokens_out       INTEGER NOT NULL,
+    tokens_reasoning INTEGER NOT NULL,
+    tokens_cached    INTEGER NOT NULL,
+    tokens_total     INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_usage_ledger_timestamp ON usage_ledger(timestamp);
+"#;
+
+/// Cumulative request and token counts per provider, model and status, kept by
+/// triggers. Compaction and purging delete rows but never these counts, so they
+/// only grow (as Prometheus counters must).
+const COUNTERS_SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS request_counters (
+    provider      TEXT NOT NULL,
+    model         TEXT NOT NULL,
+    status        INTEGER NOT NULL,
+    requests      INTEGER NOT NULL,
+    tokens_in     INTEGER NOT NULL,
+    tokens_out    INTEGER NOT NULL,
+    tokens_cached INTEGER NOT NULL,
+    PRIMARY KEY (provider, model, status)
+);
+CREATE TRIGGER IF NOT EXISTS request_counters_insert AFTER INSERT ON requests BEGIN
+    INSERT INTO request_counters (provider, model, status, requests, tokens_in, tokens_out, tokens_cached)
+    VALUES (NEW.provider, NEW.model, NEW.status, 1,
+            COALESCE(NEW.tokens_in, 0), COALESCE(NEW.tokens_out, 0), COALESCE(NEW.tokens_cached, 0))
+    ON CONFLICT (provider, model, status) DO UPDATE SET
+        requests = requests + 1,
+        tokens_in = tokens_in + excluded.tokens_in,
+        tokens_out = tokens_out + excluded.tokens_out,
+        tokens_cached = tokens_cached + excluded.tokens_cached;
+END;
+CREATE TRIGGER IF NOT EXISTS request_counters_tokens AFTER UPDATE OF tokens_in, tokens_out, tokens_cached ON requests BEGIN
+    UPDATE request_counters SET
+        tokens_in = tokens_in + MAX(COALESCE(NEW.tokens_in, 0) - COALESCE(OLD.tokens_in, 0), 0),
+        tokens_out = tokens_out + MAX(COALESCE(NEW.tokens_out, 0) - COALESCE(OLD.tokens_out, 0), 0),
+        tokens_cached = tokens_cached + MAX(COALESCE(NEW.tokens_cached, 0) - COALESCE(OLD.tokens_cached, 0), 0)
+    WHERE provider = NEW.provider AND model = NEW.model AND status = NEW.status;
+END;
+"#;
+
+/// Usage of requests deleted by compaction, per 15-minute slot and every dimension
+/// grouped series and per-account queries use (NULLs stored as '' / -1 so they can
+/// be part of the key). Together with the remaining rows this keeps grouped history
+/// complete after raw rows age out.
+const ROLLUPS_SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS request_rollups (
+    slot          INTEGER NOT NULL,
+    provider      TEXT NOT NULL,
+    model         TEXT NOT NULL,
+    account       TEXT NOT NULL,
+    endpoint_kind TEXT NOT NULL,
+    api_key       TEXT NOT NULL,
+    api_key_index INTEGER NOT NULL,
+    requests      INTEGER NOT NULL,
+    success_count INTEGER NOT NULL,
+    tokens_in     INTEGER NOT NULL,
+    tokens_out    INTEGER NOT NULL,
+    tokens_cached INTEGER NOT NULL,
+    last_used     INTEGER NOT NULL,
+    PRIMARY KEY (slot, provider, model, account, endpoint_kind, api_key, api_key_index)
+);
+"#;
+
+/// Columns added after the initial schema: (name, declaration, index)
+const COLUMN_MIGRATIONS: &[(&str, &str, Option<&str>)] = &[
+    (
+        "endpoint_kind",
+        "TEXT NOT NULL DEFAULT 'generation'",
+        Some("CREATE INDEX IF NOT EXISTS idx_requests_endpoint_kind ON requests(endpoint_kind, timestamp)"),
+    ),
+    (
+        "request_id",
+        "TEXT",
+        Some("CREATE INDEX IF NOT EXISTS idx_requests_request_id ON requests(request_id)"),
+    ),
+    (
+        "account",
+        "TEXT",
+        Some("CREATE INDEX IF NOT EXISTS idx_requests_account ON requests(account, timestamp)"),
+    ),
+    ("api_key_index", "INTEGER", None),
+    (
+        "error_category",
+        "TEXT",
+        Some("CREATE INDEX IF NOT EXISTS idx_requests_error_category ON requests(error_category, timestamp)"),
+    ),
+    ("error_message", "TEXT", None),
+];
+
+/// Columns added to `usage_ledger` after it was introduced
+const LEDGER_COLUMN_MIGRATIONS: &[(&str, &str, Option<&str>)] = &[
+    ("request_id", "TEXT", None),
+    ("matched_request", "TEXT", None),
+];
+
+const REQUEST_COLUMNS: &str = "id, timestamp, provider, model, method, path, status, duration_ms, \
+     tokens_in, tokens_out, tokens_cached, api_key, endpoint_kind, request_id, account, api_key_index, \
+     error_category, error_message";
+
+/// Rows are grouped into 15-minute slots in SQL and labelled in Rust, since SQLite
+/// only knows the system time zone. No zone offset is finer than 15 minutes, so
+/// each slot lies within a single local hour.
+const SLOT_MS: i64 = 15 * 60 * 1000;
+
+/// UTC days, the granularity of price changes
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Usage rows with `?2 <= time < ?3`: one per stored request, plus the rollups of
+/// requests compaction already deleted, both in `SLOT_MS` slots. `?1` is left for
+/// the caller's bucket size, which must be a multiple of `SLOT_MS`.
+fn usage_rows_sql() -> String {
+    format!(
+        "SELECT (timestamp / {slot}) * {slot} AS slot, provider, model, account, endpoint_kind, api_key,
+                api_key_index, 1 AS requests, (status < 400) AS success_count,
+                COALESCE(tokens_in, 0) AS tokens_in, COALESCE(tokens_out, 0) AS tokens_out,
+                COALESCE(tokens_cached, 0) AS tokens_cached, timestamp AS last_used
+         FROM requests WHERE timestamp >= ?2 AND timestamp < ?3
+         UNION ALL
+         SELECT slot, provider, model, NULLIF(account, ''), endpoint_kind, NULLIF(api_key, ''),
+                NULLIF(api_key_index, -1), requests, success_count, tokens_in, tokens_out, tokens_cached, last_used
+         FROM request_rollups WHERE slot >= ?2 AND slot < ?3",
+        slot = SLOT_MS
+    )
+}
+
+/// Label format for a bucket; "all" puts the whole range in one unlabelled bucket
+fn bucket_format(bucket: &str) -> Result<&'static str, String> {
+    match bucket {
+        "day" => Ok(DAY_FORMAT),
+        "hour" => Ok(HOUR_FORMAT),
+        "month" => Ok(MONTH_FORMAT),
+        "all" => Ok(""),
+        other => Err(format!("Unsupported bucket: {}", other)),
+    }
+}
+
+/// Totals for requests that aren't stored individually: the `aggregate.json`
+/// imported on first run, plus rows rolled up by compaction. It counts every
+/// request up to `cutoff`, so only newer rows are added on top when deriving.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Baseline {
+    cutoff: u64,
+    aggregate: Aggregate,
+}
+
+/// Keyset pagination cursor: the sort value and id of the last row returned
+#[derive(Debug, Serialize, Deserialize)]
+struct PageCursor {
+    value: serde_json::Value,
+    id: String,
+}
+
+/// Usage and API-equivalent cost of one account over a period
+#[derive(Debug, Clone, Default)]
+pub struct AccountCost {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cached_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Requests sharing a provider, model, client key, account and UTC day
+#[derive(Debug, Clone)]
+pub struct RouteUsage {
+    pub provider: String,
+    pub model: String,
+    pub api_key: Option<String>,
+    pub account: Option<String>,
+    pub api_key_index: Option<u32>,
+    /// Start of the UTC day (ms)
+    pub day: u64,
+    pub requests: u64,
+    pub usage: TokenUsage,
+}
+
+/// Requests and tokens counted for one provider, model and status since counting began
+#[derive(Debug, Clone)]
+pub struct RequestCounter {
+    pub provider: String,
+    pub model: String,
+    pub status: u16,
+    pub requests: u64,
+    pub tokens_in: u64,
+    pub tokens_out: u64,
+    pub tokens_cached: u64,
+}
+
+/// Request outcomes of one provider over a window
+#[derive(Debug, Clone)]
+pub struct ProviderOutcomes {
+    pub provider: String,
+    pub requests: u64,
+    /// Status 400 or above
+    pub failed: u64,
+    /// Status 401
+    pub unauthorized: u64,
+    pub succeeded: u64,
+    /// 95th percentile duration of the successful requests
+    pub p95_ms: Option<u64>,
+}
+
+pub struct RequestStore {
+    conn: Mutex<Connection>,
+}
+
+impl RequestStore {
+    /// Open (or create) the request database in the ProxyPal config directory
+    pub fn open() -> Result<Self, String> {
+        let path = get_requests_db_path();
+        let conn = Connection::open(&path)
+            .map_err(|e| format!("Failed to open request database '{}': {}", path.display(), e))?;
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .map_err(|e| e.to_string())?;
+        conn.execute_batch(SCHEMA).map_err(|e| e.to_string())?;
+        Self::migrate(&conn)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Add columns introduced after the database was first created
+    fn migrate(conn: &Connection) -> Result<(), String> {
+        Self::add_missing_columns(conn, "requests", COLUMN_MIGRATIONS)?;
+        Self::add_missing_columns(conn, "usage_ledger", LEDGER_COLUMN_MIGRATIONS)?;
+
+        conn.execute_batch(ROLLUPS_SCHEMA).map_err(|e| e.to_string())?;
+
+        // Counters start from the rows stored when they were introduced
+        conn.execute_batch(COUNTERS_SCHEMA).map_err(|e| e.to_string())?;
+        if Self::get_meta(conn, "request_counters_seeded")?.is_none() {
+            conn.execute_batch(
+                "INSERT OR REPLACE INTO request_counters
+                     (provider, model, status, requests, tokens_in, tokens_out, tokens_cached)
+                 SELECT provider, model, status, COUNT(*),
+                        COALESCE(SUM(tokens_in), 0), COALESCE(SUM(tokens_out), 0), COALESCE(SUM(tokens_cached), 0)
+                 FROM requests GROUP BY provider, model, status",
+            )
+            .map_err(|e| e.to_string())?;
+            Self::set_meta(conn, "request_counters_seeded", "1")?;
+        }
+
+        // Client API keys used to be stored as sent; keep only their fingerprints
+        if Self::get_meta(conn, "api_keys_fingerprinted")?.is_none() {
+            let keys: Vec<String> = conn
+                .prepare("SELECT DISTINCT api_key FROM requests WHERE api_key IS NOT NULL")
+                .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect())
+                .map_err(|e| e.to_string())?;
+            for key in keys.iter().filter(|k| !is_api_key_fingerprint(k)) {
+                conn.execute(
+                    "UPDATE requests SET api_key = ?1 WHERE api_key = ?2",
+                    params![api_key_fingerprint(key), key],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            Self::set_meta(conn, "api_keys_fingerprinted", "1")?;
+        }
+        Ok(())
+    }
+
+    fn add_missing_columns(
+        conn: &Connection,
+        table: &str,
+        migrations: &[(&str, &str, Option<&str>)],
+    ) -> Result<(), String> {
+        let existing: Vec<String> = conn
+            .prepare(&format!("SELECT name FROM pragma_table_info('{}')", table))
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| row.get(0))?
+                    .collect::<Result<Vec<String>, _>>()
+            })
+            .map_err(|e| e.to_string())?;
+
+        for (column, decl, index) in migrations {
+            if !existing.iter().any(|c| c == column) {
+                conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl))
+                    .map_err(|e| e.to_string())?;
+            }
+            if let Some(index) = index {
+                conn.execute_batch(index).map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// In-memory fallback so the app still runs if the database file can't be opened
+    pub fn open_in_memory() -> Result<Self, String> {
+        let conn = Connection::open_in_memory().map_err(|e| format!("Failed to open in-memory request store: {}", e))?;
+        conn.execute_batch(SCHEMA).map_err(|e| e.to_string())?;
+        Self::migrate(&conn)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn get_meta(conn: &Connection, key: &str) -> Result<Option<String>, String> {
+        conn.query_row("SELECT value FROM meta WHERE key = ?1", params![key], |row| {
+            row.get(0)
+        })
+        .optional()
+        .map_err(|e| e.to_string())
+    }
+
+    fn set_meta(conn: &Connection, key: &str, value: &str) -> Result<(), String> {
+        conn.execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+    }
+
+    fn load_baseline(conn: &Connection) -> Result<Option<Baseline>, String> {
+        Ok(Self::get_meta(conn, "legacy_baseline")?.and_then(|data| serde_json::from_str(&data).ok()))
+    }
+
+    fn save_baseline(conn: &Connection, baseline: &Baseline) -> Result<(), String> {
+        let data = serde_json::to_string(baseline).map_err(|e| e.to_string())?;
+        Self::set_meta(conn, "legacy_baseline", &data)
+    }
+
+    /// One-time import of `history.json` requests and the `aggregate.json` totals
+    pub fn import_legacy_once(&self, history: &RequestHistory, aggregate: &Aggregate) -> Result<(), String> {
+        let mut conn = self.conn.lock().unwrap();
+        if Self::get_meta(&conn, "legacy_imported")?.is_some() {
+            return Ok(());
+        }
+
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for req in &history.requests {
+            Self::insert_in(&tx, req)?;
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let cutoff = history
+            .requests
+            .iter()
+            .map(|r| r.timestamp)
+            .max()
+            .unwrap_or(0)
+            .max(now);
+        if aggregate.total_requests > 0 {
+            let baseline = Baseline {
+                cutoff,
+                aggregate: aggregate.clone(),
+            };
+            Self::save_baseline(&tx, &baseline)?;
+        }
+        Self::set_meta(&tx, "legacy_imported", "1")?;
+        tx.commit().map_err(|e| e.to_string())?;
+
+        eprintln!(
+            "[RequestStore] Imported {} requests from history.json",
+            history.requests.len()
+        );
+        Ok(())
+    }
+
+    fn insert_in(conn: &Connection, req: &RequestLog) -> Result<bool, String> {
+        conn.execute(
+            "INSERT OR IGNORE INTO requests (id, timestamp, provider, model, method, path, status,
+                 duration_ms, tokens_in, tokens_out, tokens_cached, api_key, endpoint_kind, request_id,
+                 account, api_key_index, error_category, error_message)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+            params![
+                req.id,
+                req.timestamp as i64,
+                req.provider,
+                req.model,
+                req.method,
+                req.path,
+                req.status,
+                req.duration_ms as i64,
+                req.tokens_in,
+                req.tokens_out,
+                req.tokens_cached,
+                req.api_key.as_deref().map(api_key_fingerprint),
+                req.endpoint_kind,
+                req.request_id,
+                req.account,
+                req.api_key_index,
+                req.error_category,
+                req.error_message,
+            ],
+        )
+        .map(|n| n > 0)
+        .map_err(|e| e.to_string())
+    }
+
+    /// Insert a request; returns false if a request with the same id already exists
+    pub fn insert(&self, req: &RequestLog) -> Result<bool, String> {
+        let conn = self.conn.lock().unwrap();
+        Self::insert_in(&conn, req)
+    }
+
+    /// Insert requests in one transaction, skipping IDs already stored; returns how many were new
+    pub fn insert_many(&self, requests: &[RequestLog]) -> Result<u64, String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let mut added = 0;
+        for req in requests {
+            if Self::insert_in(&tx, req)? {
+                added += 1;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(added)
+    }
+
+    /// Delete every stored request (legacy baseline and usage ledger included)
+    pub fn clear(&self) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute_batch(
+            "DELETE FROM requests; DELETE FROM request_rollups; DELETE FROM usage_ledger; DELETE FROM request_counters;
+             DELETE FROM meta WHERE key IN ('legacy_baseline', 'usage_ledger_carried', 'usage_ledger_cutoff');",
+        )
+            .map_err(|e| e.to_string())
+    }
+
+    fn row_to_request(row: &rusqlite::Row) -> rusqlite::Result<RequestLog> {
+        Ok(RequestLog {
+            id: row.get(0)?,
+            timestamp: row.get::<_, i64>(1)? as u64,
+            provider: row.get(2)?,
+            model: row.get(3)?,
+            method: row.get(4)?,
+            path: row.get(5)?,
+            status: row.get(6)?,
+            duration_ms: row.get::<_, i64>(7)? as u64,
+            tokens_in: row.get(8)?,
+            tokens_out: row.get(9)?,
+            tokens_cached: row.get(10)?,
+            api_key: row.get(11)?,
+            endpoint_kind: row.get(12)?,
+            request_id: row.get(13)?,
+            account: row.get(14)?,
+            api_key_index: row.get(15)?,
+            error_category: row.get(16)?,
+            error_message: row.get(17)?,
+        })
+    }
+
+    /// SQL conditions (joined with AND) and their parameters for a query's filters
+    fn filter_clauses(query: &RequestQuery) -> (Vec<String>, Vec<Value>) {
+        let mut clauses: Vec<String> = Vec::new();
+        let mut values: Vec<Value> = Vec::new();
+
+        let mut push_eq = |column: &str, value: &Option<String>| {
+            if let Some(v) = value {
+                clauses.push(format!("{} = ?", column));
+                values.push(Value::Text(v.clone()));
+            }
+        };
+        push_eq("model", &query.model);
+        push_eq("provider", &query.provider);
+        push_eq("api_key", &query.api_key.as_deref().map(api_key_fingerprint));
+        push_eq("endpoint_kind", &query.endpoint_kind);
+        push_eq("request_id", &query.request_id);
+        push_eq("account", &query.account);
+        push_eq("error_category", &query.error_category);
+
+        if let Some(status) = query.status {
+            clauses.push("status = ?".to_string());
+            values.push(Value::Integer(status as i64));
+        }
+        if query.failed_only.unwrap_or(false) {
+            clauses.push("status >= 400".to_string());
+        }
+        if let Some(from) = query.from {
+            clauses.push("timestamp >= ?".to_string());
+            values.push(Value::Integer(from as i64));
+        }
+        if let Some(to) = query.to {
+            clauses.push("timestamp < ?".to_string());
+            values.push(Value::Integer(to as i64));
+        }
+        (clauses, values)
+    }
+
+    /// Filtered, sorted, cursor-paginated request query
+    pub fn query(&self, query: &RequestQuery) -> Result<RequestPage, String> {
+        let sort_column = match query.sort_by.as_deref().unwrap_or("timestamp") {
+            "timestamp" => "timestamp",
+            "duration" | "durationMs" => "duration_ms",
+            "status" => "status",
+            "model" => "model",
+            "provider" => "provider",
+            other => return Err(format!("Unsupported sort field: {}", other)),
+        };
+        let descending = query.descending.unwrap_or(true);
+        let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+        let (clauses, values) = Self::filter_clauses(query);
+
+        let filter_sql = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", clauses.join(" AND "))
+        };
+
+        let conn = self.conn.lock().unwrap();
+
+        let total: i64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM requests{}", filter_sql),
+                params_from_iter(values.iter()),
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+
+        // Keyset pagination on (sort column, id) so pages stay stable while rows are inserted
+        let mut page_clauses = clauses.clone();
+        let mut page_values = values.clone();
+        if let Some(cursor) = &query.cursor {
+            let cursor: PageCursor =
+                serde_json::from_str(cursor).map_err(|_| "Invalid cursor".to_string())?;
+            let cursor_value = match cursor.value {
+                serde_json::Value::Number(n) => Value::Integer(n.as_i64().unwrap_or(0)),
+                serde_json::Value::String(s) => Value::Text(s),
+                _ => return Err("Invalid cursor".to_string()),
+            };
+            let op = if descending { "<" } else { ">" };
+            page_clauses.push(format!(
+                "({col} {op} ? OR ({col} = ? AND id {op} ?))",
+                col = sort_column,
+                op = op
+            ));
+            page_values.push(cursor_value.clone());
+            page_values.push(cursor_value);
+            page_values.push(Value::Text(cursor.id));
+        }
+
+        let order = if descending { "DESC" } else { "ASC" };
+        let sql = format!(
+            "SELECT {} FROM requests{} ORDER BY {} {order}, id {order} LIMIT {}",
+            REQUEST_COLUMNS,
+            if page_clauses.is_empty() {
+                String::new()
+            } else {
+                format!(" WHERE {}", page_clauses.join(" AND "))
+            },
+            sort_column,
+            limit + 1,
+            order = order
+        );
+
+        let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let mut requests: Vec<RequestLog> = stmt
+            .query_map(params_from_iter(page_values.iter()), Self::row_to_request)
+            .map_err(|e| e.to_string())?
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+
+        let next_cursor = if requests.len() > limit as usize {
+            requests.truncate(limit as usize);
+            requests.last().map(|last| {
+                let value = match sort_column {
+                    "timestamp" => serde_json::json!(last.timestamp),
+                    "duration_ms" => serde_json::json!(last.duration_ms),
+                    "status" => serde_json::json!(last.status),
+                    "model" => serde_json::json!(last.model),
+                    _ => serde_json::json!(last.provider),
+                };
+                serde_json::to_string(&PageCursor {
+                    value,
+                    id: last.id.clone(),
+                })
+                .unwrap_or_default()
+            })
+        } else {
+            None
+        };
+
+        Ok(RequestPage {
+            requests,
+            next_cursor,
+            total: total as u64,
+        })
+    }
+
+    /// Call `f` for every request matching the query's filters, oldest first. Rows are
+    /// read in chunks so the database isn't locked (or held in memory) for the whole pass.
+    /// Sorting and pagination fields of the query are ignored. Returns the row count.
+    pub fn for_each_request(
+        &self,
+        query: &RequestQuery,
+        mut f: impl FnMut(&RequestLog) -> Result<(), String>,
+    ) -> Result<u64, String> {
+        const CHUNK: i64 = 1000;
+        let (clauses, values) = Self::filter_clauses(query);
+        let mut last: Option<(i64, String)> = None;
+        let mut count = 0;
+
+        loop {
+            let mut chunk_clauses = clauses.clone();
+            let mut chunk_values = values.clone();
+            if let Some((timestamp, id)) = &last {
+                chunk_clauses.push("(timestamp > ? OR (timestamp = ? AND id > ?))".to_string());
+                chunk_values.push(Value::Integer(*timestamp));
+                chunk_values.push(Value::Integer(*timestamp));
+                chunk_values.push(Value::Text(id.clone()));
+            }
+            let sql = format!(
+                "SELECT {} FROM requests{} ORDER BY timestamp ASC, id ASC LIMIT {}",
+                REQUEST_COLUMNS,
+                if chunk_clauses.is_empty() {
+                    String::new()
+                } else {
+                    format!(" WHERE {}", chunk_clauses.join(" AND "))
+                },
+                CHUNK
+            );
+
+            let chunk: Vec<RequestLog> = {
+                let conn = self.conn.lock().unwrap();
+                let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+                let rows = stmt
+                    .query_map(params_from_iter(chunk_values.iter()), Self::row_to_request)
+                    .map_err(|e| e.to_string())?;
+                rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+            };
+
+            for req in &chunk {
+                f(req)?;
+            }
+            count += chunk.len() as u64;
+            match chunk.last() {
+                Some(req) if chunk.len() as i64 == CHUNK => last = Some((req.timestamp as i64, req.id.clone())),
+                _ => return Ok(count),
+            }
+        }
+    }
+
+    /// Per-account usage, optionally limited to `[from, to)`
+    pub fn account_usage(&self, from: Option<u64>, to: Option<u64>) -> Result<Vec<AccountUsage>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT account, provider, MAX(api_key_index),
+                        SUM(requests),
+                        SUM(success_count),
+                        SUM(tokens_in),
+                        SUM(tokens_out),
+                        SUM(tokens_cached),
+                        MAX(last_used)
+                 FROM ({rows})
+                 WHERE account IS NOT NULL
+                 GROUP BY account, provider
+                 ORDER BY SUM(requests) DESC",
+                rows = usage_rows_sql()
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(
+                params![SLOT_MS, from.unwrap_or(0) as i64, to.map(|t| t as i64).unwrap_or(i64::MAX)],
+                |row| {
+                    let requests = row.get::<_, i64>(3)? as u64;
+                    let success_count = row.get::<_, i64>(4)? as u64;
+                    Ok(AccountUsage {
+                        account: row.get(0)?,
+                        provider: row.get(1)?,
+                        api_key_index: row.get(2)?,
+                        requests,
+                        success_count,
+                        failure_count: requests - success_count,
+                        input_tokens: row.get::<_, i64>(5)? as u64,
+                        output_tokens: row.get::<_, i64>(6)? as u64,
+                        cached_tokens: row.get::<_, i64>(7)? as u64,
+                        last_used: row.get::<_, i64>(8)? as u64,
+                    })
+                },
+            )
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Requests, tokens and estimated cost per account (lowercased) in `[from, to)`
+    pub fn account_costs(&self, from: u64, to: u64) -> Result<HashMap<String, AccountCost>, String> {
+        self.costs_by("account", from, to)
+    }
+
+    /// Requests, tokens and estimated cost per provider (lowercased) in `[from, to)`
+    pub fn provider_costs(&self, from: u64, to: u64) -> Result<HashMap<String, AccountCost>, String> {
+        self.costs_by("provider", from, to)
+    }
+
+    /// Requests, tokens and estimated cost per (provider, account), both lowercased, in `[from, to)`
+    pub fn provider_account_costs(&self, from: u64, to: u64) -> Result<HashMap<(String, String), AccountCost>, String> {
+        Ok(self
+            .costs_by("provider || '|' || account", from, to)?
+            .into_iter()
+            .filter_map(|(key, cost)| {
+                let (provider, account) = key.split_once('|')?;
+                Some(((provider.to_string(), account.to_string()), cost))
+            })
+            .collect())
+    }
+
+    /// Costs grouped by the lowercased `column`, priced per model and day. Rolled-up
+    /// rows are included so periods older than the raw retention still count.
+    fn costs_by(&self, column: &str, from: u64, to: u64) -> Result<HashMap<String, AccountCost>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT LOWER({col}), model, provider, (slot / ?1) * ?1 AS day,
+                        SUM(requests),
+                        SUM(tokens_in),
+                        SUM(tokens_out),
+                        SUM(tokens_cached)
+                 FROM ({rows})
+                 WHERE {col} IS NOT NULL
+                 GROUP BY LOWER({col}), model, provider, day",
+                col = column,
+                rows = usage_rows_sql()
+            ))
+            .map_err(|e| e.to_string())?;
+        let mut rows = stmt
+            .query(params![DAY_MS, from as i64, to as i64])
+            .map_err(|e| e.to_string())?;
+
+        let mut costs: HashMap<String, AccountCost> = HashMap::new();
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            let key: String = row.get(0).map_err(|e| e.to_string())?;
+            let model: String = row.get(1).map_err(|e| e.to_string())?;
+            let provider: String = row.get(2).map_err(|e| e.to_string())?;
+            let value = |i: usize| row.get::<_, i64>(i).map(|v| v as u64).map_err(|e| e.to_string());
+            let usage = TokenUsage {
+                input: value(5)?,
+                output: value(6)?,
+                cache_read: value(7)?,
+                cache_write: 0,
+            };
+
+            let entry = costs.entry(key).or_default();
+            entry.requests += value(4)?;
+            entry.input_tokens += usage.input;
+            entry.output_tokens += usage.output;
+            entry.cached_tokens += usage.cache_read;
+            entry.cost_usd += pricing::cost(&model, &provider, value(3)?, usage);
+        }
+        Ok(costs)
+    }
+
+    /// Usage in `[from, to)` grouped finely enough to evaluate any budget: by provider,
+    /// model, client key, serving account and UTC day, including compacted rows
+    pub fn route_usage(&self, from: u64, to: u64) -> Result<Vec<RouteUsage>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT provider, model, api_key, account, api_key_index, (slot / ?1) * ?1 AS day,
+                        SUM(requests),
+                        SUM(tokens_in),
+                        SUM(tokens_out),
+                        SUM(tokens_cached)
+                 FROM ({})
+                 GROUP BY provider, model, api_key, account, api_key_index, day",
+                usage_rows_sql()
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![DAY_MS, from as i64, to as i64], |row| {
+                Ok(RouteUsage {
+                    provider: row.get(0)?,
+                    model: row.get(1)?,
+                    api_key: row.get(2)?,
+                    account: row.get(3)?,
+                    api_key_index: row.get(4)?,
+                    day: row.get::<_, i64>(5)? as u64,
+                    requests: row.get::<_, i64>(6)? as u64,
+                    usage: TokenUsage {
+                        input: row.get::<_, i64>(7)? as u64,
+                        output: row.get::<_, i64>(8)? as u64,
+                        cache_read: row.get::<_, i64>(9)? as u64,
+                        cache_write: 0,
+                    },
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Counts and p95 latency per provider of the requests logged at or after `since`
+    pub fn outcomes_since(&self, since: u64) -> Result<Vec<ProviderOutcomes>, String> {
+        let conn = self.conn.lock().unwrap();
+        // The p95 is the duration at rank ceil(0.95 * n) among a provider's successes
+        let mut stmt = conn
+            .prepare(
+                "WITH recent AS (
+                     SELECT provider, status, duration_ms FROM requests WHERE timestamp >= ?1
+                 ),
+                 counts AS (
+                     SELECT provider, COUNT(*) AS requests, SUM(status >= 400) AS failed,
+                            SUM(status = 401) AS unauthorized, SUM(status < 400) AS succeeded
+                     FROM recent GROUP BY provider
+                 ),
+                 ranked AS (
+                     SELECT provider, duration_ms,
+                            ROW_NUMBER() OVER (PARTITION BY provider ORDER BY duration_ms) AS rank,
+                            COUNT(*) OVER (PARTITION BY provider) AS n
+                     FROM recent WHERE status < 400
+                 )
+                 SELECT c.provider, c.requests, c.failed, c.unauthorized, c.succeeded, r.duration_ms
+                 FROM counts c
+                 LEFT JOIN ranked r ON r.provider = c.provider AND r.rank = (r.n * 95 + 99) / 100",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![since as i64], |row| {
+                Ok(ProviderOutcomes {
+                    provider: row.get(0)?,
+                    requests: row.get::<_, i64>(1)? as u64,
+                    failed: row.get::<_, i64>(2)? as u64,
+                    unauthorized: row.get::<_, i64>(3)? as u64,
+                    succeeded: row.get::<_, i64>(4)? as u64,
+                    p95_ms: row.get::<_, Option<i64>>(5)?.map(|ms| ms as u64),
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Failure counts per time bucket (labelled in `tz`), group and error category
+    pub fn error_series(
+        &self,
+        group_by: &str,
+        bucket: &str,
+        from: Option<u64>,
+        to: Option<u64>,
+        tz: &DisplayTimezone,
+    ) -> Result<Vec<ErrorSeriesPoint>, String> {
+        let group_column = match group_by {
+            "provider" => "provider",
+            "model" => "model",
+            "account" => "account",
+            other => return Err(format!("Unsupported grouping: {}", other)),
+        };
+        let format = bucket_format(bucket)?;
+
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT (timestamp / ?1) * ?1 AS slot,
+                        COALESCE({col}, 'unknown'),
+                        error_category,
+                        COUNT(*)
+                 FROM requests
+                 WHERE error_category IS NOT NULL AND timestamp >= ?2 AND timestamp < ?3
+                 GROUP BY slot, {col}, error_category
+                 ORDER BY slot",
+                col = group_column
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(
+                params![SLOT_MS, from.unwrap_or(0) as i64, to.map(|t| t as i64).unwrap_or(i64::MAX)],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)? as u64,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, i64>(3)? as u64,
+                    ))
+                },
+            )
+            .map_err(|e| e.to_string())?;
+
+        let mut points: Vec<ErrorSeriesPoint> = Vec::new();
+        for row in rows {
+            let (slot, group, category, count) = row.map_err(|e| e.to_string())?;
+            let label = tz.format(slot, format);
+            match points
+                .iter_mut()
+                .rfind(|p| p.label == label && p.group == group && p.category == category)
+            {
+                Some(point) => point.count += count,
+                None => points.push(ErrorSeriesPoint {
+                    label,
+                    group,
+                    category,
+                    count,
+                }),
+            }
+        }
+        Ok(points)
+    }
+
+    /// Usage per time bucket and group within `[from, to)`
+    pub fn usage_series(
+        &self,
+        group_by: &str,
+        bucket: &str,
+        from: u64,
+        to: u64,
+        tz: &DisplayTimezone,
+    ) -> Result<Vec<UsageSeriesPoint>, String> {
+        let mut points = Vec::new();
+        self.for_each_usage_period(group_by, bucket, from, to, tz, |period| {
+            points.extend(period);
+            Ok(())
+        })?;
+        Ok(points)
+    }
+
+    /// Usage within `[from, to)`, passed to `f` one time bucket (oldest first) at a
+    /// time with a point per group, so long ranges aren't held in memory at once
+    pub fn for_each_usage_period(
+        &self,
+        group_by: &str,
+        bucket: &str,
+        from: u64,
+        to: u64,
+        tz: &DisplayTimezone,
+        mut f: impl FnMut(Vec<UsageSeriesPoint>) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let group_column = match group_by {
+            "provider" => "provider",
+            "model" => "model",
+            "account" => "account",
+            "endpoint" => "endpoint_kind",
+            "all" => "'all'",
+            other => return Err(format!("Unsupported grouping: {}", other)),
+        };
+        let format = bucket_format(bucket)?;
+
+        let conn = self.conn.lock().unwrap();
+        // Grouped by model and provider as well so cost can be priced per model
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT (slot / ?1) * ?1 AS bucket,
+                        COALESCE({col}, 'unknown'),
+                        model,
+                        provider,
+                        SUM(requests),
+                        SUM(success_count),
+                        SUM(tokens_in),
+                        SUM(tokens_out),
+                        SUM(tokens_cached)
+                 FROM ({rows})
+                 GROUP BY bucket, {col}, model, provider
+                 ORDER BY bucket",
+                col = group_column,
+                rows = usage_rows_sql()
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![SLOT_MS, from as i64, to as i64], |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as u64,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, i64>(4)? as u64,
+                    row.get::<_, i64>(5)? as u64,
+                    row.get::<_, i64>(6)? as u64,
+                    row.get::<_, i64>(7)? as u64,
+                    row.get::<_, i64>(8)? as u64,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+
+        // Slots come in time order, so each label's rows are contiguous
+        let mut period: Vec<UsageSeriesPoint> = Vec::new();
+        for row in rows {
+            let (slot, group, model, provider, requests, success, input, output, cached) =
+                row.map_err(|e| e.to_string())?;
+            let label = tz.format(slot, format);
+            if period.first().is_some_and(|p| p.label != label) {
+                f(std::mem::take(&mut period))?;
+            }
+            let usage = TokenUsage {
+                input,
+                output,
+                cache_read: cached,
+                ..Default::default()
+            };
+            let cost = pricing::cost(&model, &provider, slot, usage);
+            let point = match period.iter().position(|p| p.group == group) {
+                Some(i) => &mut period[i],
+                None => {
+                    period.push(UsageSeriesPoint {
+                        label,
+                        group,
+                        ..Default::default()
+                    });
+                    period.last_mut().unwrap()
+                }
+            };
+            point.requests += requests;
+            point.success_count += success;
+            point.input_tokens += input;
+            point.output_tokens += output;
+            point.cached_tokens += cached;
+            point.cost_usd += cost;
+        }
+        if !period.is_empty() {
+            f(period)?;
+        }
+        Ok(())
+    }
+
+    /// Most frequent failure causes since `since`, with recent example messages
+    pub fn top_errors(&self, since: u64, limit: u32) -> Result<Vec<ErrorCause>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT error_category, provider, model, COUNT(*), MAX(timestamp),
+                        GROUP_CONCAT(DISTINCT account)
+                 FROM requests
+                 WHERE error_category IS NOT NULL AND timestamp >= ?1
+                 GROUP BY error_category, provider, model
+                 ORDER BY COUNT(*) DESC, MAX(timestamp) DESC
+                 LIMIT ?2",
+            )
+            .map_err(|e| e.to_string())?;
+        let mut causes: Vec<ErrorCause> = stmt
+            .query_map(params![since as i64, limit.clamp(1, 100)], |row| {
+                let accounts: Option<String> = row.get(5)?;
+                Ok(ErrorCause {
+                    category: row.get(0)?,
+                    provider: row.get(1)?,
+                    model: row.get(2)?,
+                    count: row.get::<_, i64>(3)? as u64,
+                    accounts: accounts
+                        .map(|a| a.split(',').map(str::to_string).collect())
+                        .unwrap_or_default(),
+                    last_seen: row.get::<_, i64>(4)? as u64,
+                    examples: Vec::new(),
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+
+        let mut examples_stmt = conn
+            .prepare(
+                "SELECT error_message FROM requests
+                 WHERE error_category = ?1 AND provider = ?2 AND model = ?3
+                   AND timestamp >= ?4 AND error_message IS NOT NULL
+                 GROUP BY error_message
+                 ORDER BY MAX(timestamp) DESC
+                 LIMIT 3",
+            )
+            .map_err(|e| e.to_string())?;
+        for cause in &mut causes {
+            cause.examples = examples_stmt
+                .query_map(
+                    params![cause.category, cause.provider, cause.model, since as i64],
+                    |row| row.get::<_, String>(0),
+                )
+                .map_err(|e| e.to_string())?
+                .collect::<Result<_, _>>()
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(causes)
+    }
+
+    /// Fold requests at or before `before` into the baseline and the slot rollups, and
+    /// delete their rows. Totals and grouped series are unchanged; per-request queries
+    /// no longer see those requests.
+    /// Returns the number of rows rolled up.
+    pub fn roll_up_before(
+        &self,
+        before: u64,
+        retention: &UsageRetention,
+        tz: &DisplayTimezone,
+    ) -> Result<u64, String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+        let mut baseline = Self::load_baseline(&tx)?.unwrap_or_else(|| Baseline {
+            cutoff: 0,
+            aggregate: Aggregate::default(),
+        });
+        if before <= baseline.cutoff {
+            return Ok(0);
+        }
+
+        Self::fold_rows(&tx, &mut baseline.aggregate, baseline.cutoff as i64, before as i64, tz)?;
+        tx.execute(
+            "INSERT INTO request_rollups (slot, provider, model, account, endpoint_kind, api_key, api_key_index,
+                 requests, success_count, tokens_in, tokens_out, tokens_cached, last_used)
+             SELECT (timestamp / ?1) * ?1 AS slot, provider, model, COALESCE(account, ''), endpoint_kind,
+                    COALESCE(api_key, ''), COALESCE(api_key_index, -1),
+                    COUNT(*), COALESCE(SUM(status < 400), 0), COALESCE(SUM(tokens_in), 0),
+                    COALESCE(SUM(tokens_out), 0), COALESCE(SUM(tokens_cached), 0), MAX(timestamp)
+             FROM requests WHERE timestamp <= ?2
+             GROUP BY 1, 2, 3, 4, 5, 6, 7
+             ON CONFLICT (slot, provider, model, account, endpoint_kind, api_key, api_key_index) DO UPDATE SET
+                 requests = requests + excluded.requests,
+                 success_count = success_count + excluded.success_count,
+                 tokens_in = tokens_in + excluded.tokens_in,
+                 tokens_out = tokens_out + excluded.tokens_out,
+                 tokens_cached = tokens_cached + excluded.tokens_cached,
+                 last_used = MAX(last_used, excluded.last_used)",
+            params![SLOT_MS, before as i64],
+        )
+        .map_err(|e| e.to_string())?;
+        let deleted = tx
+            .execute("DELETE FROM requests WHERE timestamp <= ?1", params![before as i64])
+            .map_err(|e| e.to_string())?;
+        baseline.cutoff = before;
+        apply_series_retention(&mut baseline.aggregate, retention, now_ms(), tz);
+        Self::save_baseline(&tx, &baseline)?;
+        Self::fold_ledger_before(&tx, before)?;
+        tx.commit().map_err(|e| e.to_string())?;
+
+        Ok(deleted as u64)
+    }
+
+    /// Delete requests before `before` and drop the baseline's series points for that
+    /// period. Returns the number of rows deleted.
+    pub fn purge_before(&self, before: u64, tz: &DisplayTimezone) -> Result<u64, String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let deleted = tx
+            .execute("DELETE FROM requests WHERE timestamp < ?1", params![before as i64])
+            .map_err(|e| e.to_string())?;
+        tx.execute("DELETE FROM request_rollups WHERE slot < ?1", params![before as i64])
+            .map_err(|e| e.to_string())?;
+        if let Some(mut baseline) = Self::load_baseline(&tx)? {
+            prune_series_before(&mut baseline.aggregate, before, tz);
+            Self::save_baseline(&tx, &baseline)?;
+        }
+        Self::fold_ledger_before(&tx, before)?;
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(deleted as u64)
+    }
+
+    fn ledger_cutoff(conn: &Connection) -> Result<u64, String> {
+        Ok(Self::get_meta(conn, "usage_ledger_cutoff")?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0))
+    }
+
+    fn ledger_carried(conn: &Connection) -> Result<HashMap<String, ModelStats>, String> {
+        Ok(Self::get_meta(conn, "usage_ledger_carried")?
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default())
+    }
+
+    /// Add usage details from a CLIProxyAPI snapshot to the ledger. Details already
+    /// recorded (or older than the ledger's roll-up cutoff) are skipped, so the same
+    /// snapshot can be recorded any number of times. Returns the number of new entries.
+    pub fn record_usage(&self, details: &[UsageDetail]) -> Result<u64, String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let cutoff = Self::ledger_cutoff(&tx)?;
+        let mut added = 0u64;
+        {
+            let mut stmt = tx
+                .prepare(
+                    "INSERT OR IGNORE INTO usage_ledger (key, timestamp, api, model, source, auth_index,
+                         failed, tokens_in, tokens_out, tokens_reasoning, tokens_cached, tokens_total, request_id)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                )
+                .map_err(|e| e.to_string())?;
+            for d in details.iter().filter(|d| d.timestamp >= cutoff) {
+                added += stmt
+                    .execute(params![
+                        d.key,
+                        d.timestamp as i64,
+                        d.api,
+                        d.model,
+                        d.source,
+                        d.auth_index,
+                        d.failed,
+                        d.input_tokens as i64,
+                        d.output_tokens as i64,
+                        d.reasoning_tokens as i64,
+                        d.cached_tokens as i64,
+                        d.total_tokens as i64,
+                        d.request_id,
+                    ])
+                    .map_err(|e| e.to_string())? as u64;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(added)
+    }
+
+    /// Backfill token counts of stored requests since `since` from unmatched ledger
+    /// entries (see `proxy::usage_reconcile`). Returns the summary and the updated requests.
+    pub fn reconcile_usage(&self, since: u64) -> Result<(UsageReconcileSummary, Vec<RequestLog>), String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+        let mut requests: Vec<RequestLog> = {
+            let mut stmt = tx
+                .prepare(&format!(
+                    "SELECT {} FROM requests
+                     WHERE tokens_in IS NULL AND timestamp >= ?1 AND endpoint_kind != ?2
+                     ORDER BY timestamp",
+                    REQUEST_COLUMNS
+                ))
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![since as i64, KIND_TOKEN_COUNT], Self::row_to_request)
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+        };
+
+        let details: Vec<UsageDetail> = {
+            let mut stmt = tx
+                .prepare(
+                    "SELECT key, request_id, timestamp, api, model, source, auth_index, failed,
+                            tokens_in, tokens_out, tokens_reasoning, tokens_cached, tokens_total
+                     FROM usage_ledger
+                     WHERE matched_request IS NULL AND timestamp >= ?1
+                     ORDER BY timestamp",
+                )
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![since as i64], |row| {
+                    Ok(UsageDetail {
+                        key: row.get(0)?,
+                        request_id: row.get(1)?,
+                        timestamp: row.get::<_, i64>(2)? as u64,
+                        api: row.get(3)?,
+                        model: row.get(4)?,
+                        source: row.get(5)?,
+                        auth_index: row.get(6)?,
+                        failed: row.get(7)?,
+                        input_tokens: row.get::<_, i64>(8)? as u64,
+                        output_tokens: row.get::<_, i64>(9)? as u64,
+                        reasoning_tokens: row.get::<_, i64>(10)? as u64,
+                        cached_tokens: row.get::<_, i64>(11)? as u64,
+                        total_tokens: row.get::<_, i64>(12)? as u64,
+                    })
+                })
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+        };
+
+        let matches = match_usage_details(&requests, &details);
+        let mut updated = Vec::with_capacity(matches.len());
+        for &(r, d) in &matches {
+            let detail = &details[d];
+            let req = &mut requests[r];
+            req.tokens_in = Some(detail.input_tokens.min(u32::MAX as u64) as u32);
+            req.tokens_out = Some(detail.output_tokens.min(u32::MAX as u64) as u32);
+            req.tokens_cached = Some(detail.cached_tokens.min(u32::MAX as u64) as u32);
+            tx.execute(
+                "UPDATE requests SET tokens_in = ?2, tokens_out = ?3, tokens_cached = ?4 WHERE id = ?1",
+                params![req.id, req.tokens_in, req.tokens_out, req.tokens_cached],
+            )
+            .map_err(|e| e.to_string())?;
+            tx.execute(
+                "UPDATE usage_ledger SET matched_request = ?2 WHERE key = ?1",
+                params![detail.key, req.id],
+            )
+            .map_err(|e| e.to_string())?;
+            updated.push(req.clone());
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+
+        let summary = UsageReconcileSummary {
+            matched: matches.len() as u64,
+            unmatched_requests: (requests.len() - matches.len()) as u64,
+            unmatched_details: (details.len() - matches.len()) as u64,
+        };
+        Ok((summary, updated))
+    }
+
+    /// Cumulative request and token counts per provider, model and status (see `COUNTERS_SCHEMA`)
+    pub fn request_counters(&self) -> Result<Vec<RequestCounter>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT provider, model, status, requests, tokens_in, tokens_out, tokens_cached
+                 FROM request_counters ORDER BY provider, model, status",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(RequestCounter {
+                    provider: row.get(0)?,
+                    model: row.get(1)?,
+                    status: row.get(2)?,
+                    requests: row.get::<_, i64>(3)? as u64,
+                    tokens_in: row.get::<_, i64>(4)? as u64,
+                    tokens_out: row.get::<_, i64>(5)? as u64,
+                    tokens_cached: row.get::<_, i64>(6)? as u64,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Compare per-model counts of logged requests with ledger entries in `[from, to)`.
+    /// Models are matched case-insensitively; only models where the sources differ are returned.
+    pub fn usage_drift_by_model(&self, from: u64, to: u64) -> Result<Vec<ModelDrift>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut drift: BTreeMap<String, ModelDrift> = BTreeMap::new();
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT model, COUNT(*), SUM(status >= 400),
+                        SUM(COALESCE(tokens_in, 0) + COALESCE(tokens_out, 0))
+                 FROM requests
+                 WHERE timestamp >= ?1 AND timestamp < ?2 AND endpoint_kind != ?3
+                 GROUP BY model",
+            )
+            .map_err(|e| e.to_string())?;
+        let mut rows = stmt
+            .query(params![from as i64, to as i64, KIND_TOKEN_COUNT])
+            .map_err(|e| e.to_string())?;
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            let model: String = row.get(0).map_err(|e| e.to_string())?;
+            let entry = drift.entry(model.to_ascii_lowercase()).or_insert_with(|| ModelDrift {
+                model: model.clone(),
+                ..Default::default()
+            });
+            entry.watcher_requests += row.get::<_, i64>(1).map_err(|e| e.to_string())? as u64;
+            entry.watcher_failures += row.get::<_, i64>(2).map_err(|e| e.to_string())? as u64;
+            entry.watcher_tokens += row.get::<_, i64>(3).map_err(|e| e.to_string())? as u64;
+        }
+        drop(rows);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT model, COUNT(*), SUM(failed), SUM(tokens_in + tokens_out)
+                 FROM usage_ledger
+                 WHERE timestamp >= ?1 AND timestamp < ?2
+                 GROUP BY model",
+            )
+            .map_err(|e| e.to_string())?;
+        let mut rows = stmt.query(params![from as i64, to as i64]).map_err(|e| e.to_string())?;
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            let model: String = row.get(0).map_err(|e| e.to_string())?;
+            let entry = drift.entry(model.to_ascii_lowercase()).or_insert_with(|| ModelDrift {
+                model: model.clone(),
+                ..Default::default()
+            });
+            entry.proxy_requests += row.get::<_, i64>(1).map_err(|e| e.to_string())? as u64;
+            entry.proxy_failures += row.get::<_, i64>(2).map_err(|e| e.to_string())? as u64;
+            entry.proxy_tokens += row.get::<_, i64>(3).map_err(|e| e.to_string())? as u64;
+        }
+
+        let mut models: Vec<ModelDrift> = drift
+            .into_values()
+            .filter(|d| d.watcher_requests != d.proxy_requests || d.watcher_tokens != d.proxy_tokens)
+            .collect();
+        models.sort_by_key(|d| std::cmp::Reverse(d.watcher_requests.abs_diff(d.proxy_requests)));
+        Ok(models)
+    }
+
+    /// Estimated cost of every ledger entry at current prices. Entries already
+    /// rolled up into carried totals are priced as of the roll-up cutoff.
+    pub fn usage_ledger_cost(&self) -> Result<f64, String> {
+        let conn = self.conn.lock().unwrap();
+        let cutoff = Self::ledger_cutoff(&conn)?;
+        let mut cost = 0.0;
+        for (model, stats) in Self::ledger_carried(&conn)? {
+            let usage = TokenUsage {
+                input: stats.input_tokens,
+                output: stats.output_tokens,
+                cache_read: stats.cached_tokens,
+                cache_write: 0,
+            };
+            cost += pricing::cost(&model, &detect_provider_from_model(&model), cutoff, usage);
+        }
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT model, (timestamp / ?1) * ?1 AS day, SUM(tokens_in), SUM(tokens_out), SUM(tokens_cached)
+                 FROM usage_ledger GROUP BY model, day",
+            )
+            .map_err(|e| e.to_string())?;
+        let mut rows = stmt.query(params![DAY_MS]).map_err(|e| e.to_string())?;
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            let model: String = row.get(0).map_err(|e| e.to_string())?;
+            let value = |i: usize| row.get::<_, i64>(i).map(|v| v as u64).map_err(|e| e.to_string());
+            let usage = TokenUsage {
+                input: value(2)?,
+                output: value(3)?,
+                cache_read: value(4)?,
+                cache_write: 0,
+            };
+            cost += pricing::cost(&model, &detect_provider_from_model(&model), value(1)?, usage);
+        }
+        Ok(cost)
+    }
+
+    /// Fingerprint of the prices stored costs were last computed with
+    pub fn pricing_fingerprint(&self) -> Result<Option<String>, String> {
+        let conn = self.conn.lock().unwrap();
+        Self::get_meta(&conn, "pricing_fingerprint")
+    }
+
+    pub fn set_pricing_fingerprint(&self, fingerprint: &str) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        Self::set_meta(&conn, "pricing_fingerprint", fingerprint)
+    }
+
+    /// Add per-model sums of ledger entries before `before` to `totals`
+    fn sum_ledger_before(
+        conn: &Connection,
+        before: i64,
+        totals: &mut HashMap<String, ModelStats>,
+    ) -> Result<(), String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT model, COUNT(*), SUM(failed = 0), SUM(tokens_total), SUM(tokens_in),
+                        SUM(tokens_out), SUM(tokens_cached)
+                 FROM usage_ledger WHERE timestamp < ?1 GROUP BY model",
+            )
+            .map_err(|e| e.to_string())?;
+        let mut rows = stmt.query(params![before]).map_err(|e| e.to_string())?;
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            let model: String = row.get(0).map_err(|e| e.to_string())?;
+            let value = |i: usize| row.get::<_, i64>(i).map(|v| v as u64).map_err(|e| e.to_string());
+            let entry = totals.entry(model).or_default();
+            entry.requests += value(1)?;
+            entry.success_count += value(2)?;
+            entry.tokens += value(3)?;
+            entry.input_tokens += value(4)?;
+            entry.output_tokens += value(5)?;
+            entry.cached_tokens += value(6)?;
+        }
+        Ok(())
+    }
+
+    /// Cumulative per-model usage from the ledger, including entries rolled up by retention
+    pub fn usage_ledger_totals(&self) -> Result<HashMap<String, ModelStats>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut totals = Self::ledger_carried(&conn)?;
+        Self::sum_ledger_before(&conn, i64::MAX, &mut totals)?;
+        Ok(totals)
+    }
+
+    /// Fold ledger entries before `before` into the carried totals and delete them.
+    /// Later snapshots may still contain those details; the cutoff keeps them out.
+    fn fold_ledger_before(conn: &Connection, before: u64) -> Result<(), String> {
+        if before <= Self::ledger_cutoff(conn)? {
+            return Ok(());
+        }
+        let mut carried = Self::ledger_carried(conn)?;
+        Self::sum_ledger_before(conn, before as i64, &mut carried)?;
+        conn.execute("DELETE FROM usage_ledger WHERE timestamp < ?1", params![before as i64])
+            .map_err(|e| e.to_string())?;
+        let data = serde_json::to_string(&carried).map_err(|e| e.to_string())?;
+        Self::set_meta(conn, "usage_ledger_carried", &data)?;
+        Self::set_meta(conn, "usage_ledger_cutoff", &before.to_string())
+    }
+
+    /// Rebuild the cumulative aggregate from stored requests (plus the baseline,
+    /// which covers requests that are no longer stored individually)
+    pub fn derive_aggregate(&self, tz: &DisplayTimezone) -> Result<Aggregate, String> {
+        let conn = self.conn.lock().unwrap();
+        let (mut agg, cutoff) = match Self::load_baseline(&conn)? {
+            Some(b) => (b.aggregate, b.cutoff as i64),
+            None => (Aggregate::default(), -1),
+        };
+        Self::fold_rows(&conn, &mut agg, cutoff, i64::MAX, tz)?;
+        Ok(agg)
+    }
+
+    /// Add rows with `after < timestamp <= until` to `agg`, bucketing series in `tz`
+    fn fold_rows(
+        conn: &Connection,
+        agg: &mut Aggregate,
+        after: i64,
+        until: i64,
+        tz: &DisplayTimezone,
+    ) -> Result<(), String> {
+        // Totals
+        conn.query_row(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(status < 400), 0),
+                    COALESCE(SUM(tokens_in), 0),
+                    COALESCE(SUM(tokens_out), 0),
+                    COALESCE(SUM(tokens_cached), 0),
+                    MIN(timestamp)
+             FROM requests WHERE timestamp > ?1 AND timestamp <= ?2",
+            params![after, until],
+            |row| {
+                let total: i64 = row.get(0)?;
+                let success: i64 = row.get(1)?;
+                agg.total_requests += total as u64;
+                agg.total_success_count += success as u64;
+                agg.total_failure_count += (total - success) as u64;
+                agg.total_tokens_in += row.get::<_, i64>(2)? as u64;
+                agg.total_tokens_out += row.get::<_, i64>(3)? as u64;
+                agg.total_tokens_cached += row.get::<_, i64>(4)? as u64;
+                if let Some(first) = row.get::<_, Option<i64>>(5)? {
+                    agg.created_at = agg.created_at.min(first as u64);
+                }
+                Ok(())
+            },
+        )
+        .map_err(|e| e.to_string())?;
+
+        // Daily and hourly series, bucketed from each request's time in the display zone
+        let mut slots = Vec::new();
+        {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT (timestamp / ?1) * ?1 AS slot,
+                            COUNT(*),
+                            COALESCE(SUM(tokens_in), 0) + COALESCE(SUM(tokens_out), 0)
+                     FROM requests WHERE timestamp > ?2 AND timestamp <= ?3
+                     GROUP BY slot",
+                )
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![SLOT_MS, after, until], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)? as u64,
+                        row.get::<_, i64>(1)? as u64,
+                        row.get::<_, i64>(2)? as u64,
+                    ))
+                })
+                .map_err(|e| e.to_string())?;
+            for row in rows {
+                slots.push(row.map_err(|e| e.to_string())?);
+            }
+        }
+        for (format, requests_series, tokens_series) in [
+            (DAY_FORMAT, &mut agg.requests_by_day, &mut agg.tokens_by_day),
+            (HOUR_FORMAT, &mut agg.requests_by_hour, &mut agg.tokens_by_hour),
+        ] {
+            let mut buckets: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+            for (slot, requests, tokens) in &slots {
+                let bucket = buckets.entry(tz.format(*slot, format)).or_default();
+                bucket.0 += requests;
+                bucket.1 += tokens;
+            }
+            for (label, (requests, tokens)) in buckets {
+                add_to_series(requests_series, &label, requests);
+                add_to_series(tokens_series, &label, tokens);
+            }
+            requests_series.sort_by(|a, b| a.label.cmp(&b.label));
+            tokens_series.sort_by(|a, b| a.label.cmp(&b.label));
+        }
+
+        // Per-model and per-provider stats
+        for (column, stats) in [
+            ("model", &mut agg.model_stats),
+            ("provider", &mut agg.provider_stats),
+            ("endpoint_kind", &mut agg.endpoint_stats),
+            ("account", &mut agg.account_stats),
+        ] {
+            add_grouped_stats(conn, column, after, until, stats)?;
+        }
+
+        // Failures per category
+        let mut stmt = conn
+            .prepare(
+                "SELECT error_category, COUNT(*) FROM requests
+                 WHERE timestamp > ?1 AND timestamp <= ?2 AND error_category IS NOT NULL GROUP BY error_category",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![after, until], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+            })
+            .map_err(|e| e.to_string())?;
+        for row in rows {
+            let (category, count) = row.map_err(|e| e.to_string())?;
+            *agg.error_stats.entry(category).or_insert(0) += count;
+        }
+
+        // Latency histograms of successful requests
+        let mut stmt = conn
+            .prepare(
+                "SELECT model, provider, timestamp, duration_ms
+                 FROM requests WHERE timestamp > ?1 AND timestamp <= ?2 AND status < 400 AND duration_ms > 0",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![after, until], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)? as u64,
+                    row.get::<_, i64>(3)? as u64,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        for row in rows {
+            let (model, provider, timestamp, duration_ms) = row.map_err(|e| e.to_string())?;
+            agg.latency.record(&model, &provider, duration_ms);
+            agg.latency_by_day
+                .entry(tz.day_label(timestamp))
+                .or_default()
+                .record(&model, &provider, duration_ms);
+        }
+
+        // Cost is linear in tokens, and prices change at most daily, so pricing the
+        // per-model, per-day sums is exact
+        let mut stmt = conn
+            .prepare(
+                "SELECT model, provider, (timestamp / ?3) * ?3 AS day,
+                        COALESCE(SUM(tokens_in), 0), COALESCE(SUM(tokens_out), 0),
+                        COALESCE(SUM(tokens_cached), 0)
+                 FROM requests WHERE timestamp > ?1 AND timestamp <= ?2
+                 GROUP BY model, provider, day",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![after, until, DAY_MS], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)? as u64,
+                    TokenUsage {
+                        input: row.get::<_, i64>(3)? as u64,
+                        output: row.get::<_, i64>(4)? as u64,
+                        cache_read: row.get::<_, i64>(5)? as u64,
+                        cache_write: 0,
+                    },
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        for row in rows {
+            let (model, provider, day, usage) = row.map_err(|e| e.to_string())?;
+            agg.total_cost_usd += pricing::cost(&model, &provider, day, usage);
+        }
+
+        Ok(())
+    }
+}
+
+fn add_to_series(series: &mut Vec<TimeSeriesPoint>, label: &str, increment: u64) {
+    if let Some(point) = series.iter_mut().find(|p| p.label == label) {
+        point.value += increment;
+    } else {
+        series.push(TimeSeriesPoint {
+            label: label.to_string(),
+            value: increment,
+        });
+    }
+}
+
+fn add_grouped_stats(
+    conn: &Connection,
+    column: &str,
+    after: i64,
+    until: i64,
+    stats: &mut HashMap<String, ModelStats>,
+) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {col},
+                    COUNT(*),
+                    COALESCE(SUM(status < 400), 0),
+                    COALESCE(SUM(tokens_in), 0),
+                    COALESCE(SUM(tokens_out), 0),
+                    COALESCE(SUM(tokens_cached), 0)
+             FROM requests WHERE timestamp > ?1 AND timestamp <= ?2 AND {col} IS NOT NULL GROUP BY {col}",
+            col = column
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![after, until], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)? as u64,
+                row.get::<_, i64>(2)? as u64,
+                row.get::<_, i64>(3)? as u64,
+                row.get::<_, i64>(4)? as u64,
+                row.get::<_, i64>(5)? as u64,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let (key, requests, success, input, output, cached) = row.map_err(|e| e.to_string())?;
+        let key = if key.is_empty() { "unknown".to_string() } else { key };
+        let entry = stats.entry(key).or_default();
+        entry.requests += requests;
+        entry.success_count += success;
+        entry.tokens += input + output;
+        entry.input_tokens += input;
+        entry.output_tokens += output;
+        entry.cached_tokens += cached;
+    }
+    Ok(())
+}
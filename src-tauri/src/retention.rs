@@ -0,0 +1,97 @@
+//! Retention for usage analytics.
+//!
+//! Each granularity (hourly, daily, monthly series) is kept for a configurable
+//! time. Daily points that age out are rolled into the monthly series so the
+//! long-term picture survives; hourly points are covered by the daily series
+//! and are simply dropped. Raw request rows are rolled up by the request store.
+
+use crate::timezone::{months_before, DisplayTimezone};
+use crate::types::{Aggregate, CompactionSummary, TimeSeriesPoint, UsageRetention};
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Remove points whose label sorts before `cutoff`; returns the removed points
+fn split_before(series: &mut Vec<TimeSeriesPoint>, cutoff: &str) -> Vec<TimeSeriesPoint> {
+    let (old, keep): (Vec<_>, Vec<_>) = series.drain(..).partition(|p| p.label.as_str() < cutoff);
+    *series = keep;
+    old
+}
+
+fn add_to_month(series: &mut Vec<TimeSeriesPoint>, day_label: &str, value: u64) {
+    let month = &day_label[..day_label.len().min(7)];
+    match series.iter_mut().find(|p| p.label == month) {
+        Some(point) => point.value += value,
+        None => series.push(TimeSeriesPoint {
+            label: month.to_string(),
+            value,
+        }),
+    }
+}
+
+/// Labels of the oldest hourly and daily points retention keeps (None: kept forever)
+pub fn series_cutoffs(
+    retention: &UsageRetention,
+    now_ms: u64,
+    tz: &DisplayTimezone,
+) -> (Option<String>, Option<String>) {
+    let cutoff = |days: u32| now_ms.saturating_sub(days as u64 * DAY_MS);
+    (
+        (retention.hourly_days > 0).then(|| tz.hour_label(cutoff(retention.hourly_days))),
+        (retention.daily_days > 0).then(|| tz.day_label(cutoff(retention.daily_days))),
+    )
+}
+
+/// Apply hourly/daily/monthly retention to the aggregate's series (labelled in `tz`)
+pub fn apply_series_retention(
+    agg: &mut Aggregate,
+    retention: &UsageRetention,
+    now_ms: u64,
+    tz: &DisplayTimezone,
+) -> CompactionSummary {
+    let mut summary = CompactionSummary::default();
+    let (hour_cutoff, day_cutoff) = series_cutoffs(retention, now_ms, tz);
+
+    if let Some(cutoff) = hour_cutoff {
+        summary.hourly_points_removed = split_before(&mut agg.requests_by_hour, &cutoff).len() as u64;
+        split_before(&mut agg.tokens_by_hour, &cutoff);
+    }
+
+    if let Some(cutoff) = day_cutoff {
+        let old_requests = split_before(&mut agg.requests_by_day, &cutoff);
+        let old_tokens = split_before(&mut agg.tokens_by_day, &cutoff);
+        summary.daily_points_rolled_up = old_requests.len() as u64;
+        for point in old_requests {
+            add_to_month(&mut agg.requests_by_month, &point.label, point.value);
+        }
+        for point in old_tokens {
+            add_to_month(&mut agg.tokens_by_month, &point.label, point.value);
+        }
+        agg.requests_by_month.sort_by(|a, b| a.label.cmp(&b.label));
+        agg.tokens_by_month.sort_by(|a, b| a.label.cmp(&b.label));
+        agg.latency_by_day.retain(|day, _| day.as_str() >= cutoff.as_str());
+    }
+
+    if retention.monthly_months > 0 {
+        let cutoff = months_before(&tz.month_label(now_ms), retention.monthly_months);
+        summary.monthly_points_removed = split_before(&mut agg.requests_by_month, &cutoff).len() as u64;
+        split_before(&mut agg.tokens_by_month, &cutoff);
+    }
+
+    summary
+}
+
+/// Drop every series point for a period that ended before `before` (ms).
+/// All-time totals and per-model/provider stats are kept.
+pub fn prune_series_before(agg: &mut Aggregate, before: u64, tz: &DisplayTimezone) {
+    let hour = tz.hour_label(before);
+    let day = tz.day_label(before);
+    let month = tz.month_label(before);
+
+    split_before(&mut agg.requests_by_hour, &hour);
+    split_before(&mut agg.tokens_by_hour, &hour);
+    split_before(&mut agg.requests_by_day, &day);
+    split_before(&mut agg.tokens_by_day, &day);
+    split_before(&mut agg.requests_by_month, &month);
+    split_before(&mut agg.tokens_by_month, &month);
+    agg.latency_by_day.retain(|d, _| d.as_str() >= day.as_str());
+}
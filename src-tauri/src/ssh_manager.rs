@@ -22,12 +22,15 @@ struct RunningConnection {
 
 pub struct SshManager {
     connections: Arc<Mutex<HashMap<String, RunningConnection>>>,
+    /// Last status emitted per config id
+    statuses: Arc<Mutex<HashMap<String, String>>>,
 }
 
 impl SshManager {
     pub fn new() -> Self {
         Self {
             connections: Arc::new(Mutex::new(HashMap::new())),
+            statuses: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -42,8 +45,10 @@ impl SshManager {
         let notify_clone = notify_stop.clone();
         let config_clone = config.clone();
         
+        let statuses = self.statuses.clone();
         // Helper to emit status
         let emit_status = move |status: &str, msg: Option<String>| {
+            statuses.lock().unwrap().insert(config_clone.id.clone(), status.to_string());
             let _ = app.emit("ssh-status-changed", SshStatusUpdate {
                 id: config_clone.id.clone(),
                 status: status.to_string(),
@@ -214,6 +219,12 @@ impl SshManager {
         connections.clear();
     }
 
+    /// Last status reported by each connection started this session
+    /// ("connecting", "connected", "reconnecting", "error" or "disconnected")
+    pub fn statuses(&self) -> HashMap<String, String> {
+        self.statuses.lock().unwrap().clone()
+    }
+
     #[allow(dead_code)]
     pub fn get_status(&self, id: &str) -> String {
        // Ideally status is tracked. But for now, if it's in the map, it's "running" (enabled).
@@ -1,6 +1,6 @@
 use std::sync::Mutex;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::sync::atomic::AtomicBool;
 use tauri_plugin_shell::process::CommandChild;
 
 use crate::types::{ProxyStatus, AuthStatus, OAuthState, CopilotStatus};
@@ -16,7 +16,6 @@ pub struct AppState {
     pub copilot_status: Mutex<CopilotStatus>,
     pub copilot_process: Mutex<Option<CommandChild>>,
     pub log_watcher_running: Arc<AtomicBool>,
-    pub request_counter: Arc<AtomicU64>,
 }
 
 impl Default for AppState {
@@ -30,7 +29,6 @@ impl Default for AppState {
             copilot_status: Mutex::new(CopilotStatus::default()),
             copilot_process: Mutex::new(None),
             log_watcher_running: Arc::new(AtomicBool::new(false)),
-            request_counter: Arc::new(AtomicU64::new(0)),
         }
     }
 }
@@ -0,0 +1,293 @@
+//! Subscription savings: what flat-rate plans would have cost at API prices.
+//!
+//! Each subscription renews on its billing day. A monthly report covers the
+//! billing period of every subscription that starts in the given month, prices
+//! the requests its linked accounts served during that period with the pricing
+//! catalog, and compares that API-equivalent cost with the fee. A plan's fee is
+//! split evenly across its linked accounts. A plan without linked accounts is
+//! compared with its provider's usage outside accounts linked to the provider's
+//! other plans, split evenly between the provider's unlinked plans, so no
+//! request is counted twice.
+
+use std::collections::HashSet;
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::export::{csv_field, markdown_cell, usd};
+use crate::request_store::{AccountCost, RequestStore};
+use crate::timezone::{now_ms, DisplayTimezone, DAY_FORMAT, MONTH_FORMAT};
+use crate::types::{AccountSavings, ProviderSavings, Subscription, SubscriptionReport};
+
+/// Check a subscription before saving it
+pub fn validate(subscription: &Subscription) -> Result<(), String> {
+    if subscription.provider.trim().is_empty() {
+        return Err("Provider is required".to_string());
+    }
+    if subscription.plan_name.trim().is_empty() {
+        return Err("Plan name is required".to_string());
+    }
+    if !subscription.monthly_fee_usd.is_finite() || subscription.monthly_fee_usd < 0.0 {
+        return Err("Monthly fee must be a non-negative number".to_string());
+    }
+    if !(1..=31).contains(&subscription.billing_day) {
+        return Err("Billing day must be between 1 and 31".to_string());
+    }
+    Ok(())
+}
+
+fn parse_month(month: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid month '{}', expected YYYY-MM", month))
+}
+
+fn next_month(first: NaiveDate) -> NaiveDate {
+    first
+        .checked_add_months(chrono::Months::new(1))
+        .unwrap_or(first)
+}
+
+/// The billing day in the month starting at `first`, clamped to the month's length
+fn billing_date(first: NaiveDate, billing_day: u8) -> NaiveDate {
+    let days_in_month = next_month(first).signed_duration_since(first).num_days() as u32;
+    first
+        .with_day((billing_day as u32).clamp(1, days_in_month))
+        .unwrap_or(first)
+}
+
+/// The billing period starting in `month`: `[start, end)` as dates
+pub fn billing_period(month: &str, billing_day: u8) -> Result<(NaiveDate, NaiveDate), String> {
+    let first = parse_month(month)?;
+    Ok((billing_date(first, billing_day), billing_date(next_month(first), billing_day)))
+}
+
+/// Usage and API-equivalent cost a subscription covers in `[from, to)`: one entry per
+/// linked account, or a single `None` entry for a plan without linked accounts
+pub fn covered_costs(
+    sub: &Subscription,
+    subscriptions: &[Subscription],
+    store: &RequestStore,
+    from: u64,
+    to: u64,
+) -> Result<Vec<(Option<String>, AccountCost)>, String> {
+    if !sub.accounts.is_empty() {
+        let costs = store.account_costs(from, to)?;
+        return Ok(sub
+            .accounts
+            .iter()
+            .map(|account| (Some(account.clone()), costs.get(&account.to_lowercase()).cloned().unwrap_or_default()))
+            .collect());
+    }
+
+    let provider = sub.provider.to_lowercase();
+    let same_provider = || subscriptions.iter().filter(|s| s.provider.eq_ignore_ascii_case(&sub.provider));
+    let linked: HashSet<String> = same_provider()
+        .flat_map(|s| s.accounts.iter().map(|a| a.to_lowercase()))
+        .collect();
+    let unlinked_plans = same_provider().filter(|s| s.accounts.is_empty()).count().max(1) as u64;
+
+    let mut cost = store.provider_costs(from, to)?.remove(&provider).unwrap_or_default();
+    let account_costs = store.provider_account_costs(from, to)?;
+    for account in linked {
+        if let Some(linked_cost) = account_costs.get(&(provider.clone(), account)) {
+            cost.requests = cost.requests.saturating_sub(linked_cost.requests);
+            cost.input_tokens = cost.input_tokens.saturating_sub(linked_cost.input_tokens);
+            cost.output_tokens = cost.output_tokens.saturating_sub(linked_cost.output_tokens);
+            cost.cached_tokens = cost.cached_tokens.saturating_sub(linked_cost.cached_tokens);
+            cost.cost_usd -= linked_cost.cost_usd;
+        }
+    }
+    let share = AccountCost {
+        requests: cost.requests / unlinked_plans,
+        input_tokens: cost.input_tokens / unlinked_plans,
+        output_tokens: cost.output_tokens / unlinked_plans,
+        cached_tokens: cost.cached_tokens / unlinked_plans,
+        cost_usd: cost.cost_usd.max(0.0) / unlinked_plans as f64,
+    };
+    Ok(vec![(None, share)])
+}
+
+/// Build the savings report for billing periods starting in `month` ("YYYY-MM")
+pub fn build_report(
+    subscriptions: &[Subscription],
+    store: &RequestStore,
+    month: &str,
+    tz: &DisplayTimezone,
+) -> Result<SubscriptionReport, String> {
+    let now = now_ms();
+    let mut report = SubscriptionReport {
+        month: parse_month(month)?.format(MONTH_FORMAT).to_string(),
+        in_progress: false,
+        accounts: Vec::new(),
+        providers: Vec::new(),
+        total_fee_usd: 0.0,
+        total_api_cost_usd: 0.0,
+        total_savings_usd: 0.0,
+    };
+
+    for sub in subscriptions {
+        let (start, end) = billing_period(month, sub.billing_day)?;
+        let (from, to) = (tz.start_of_day(start), tz.start_of_day(end));
+        report.in_progress |= to > now;
+        let covered = covered_costs(sub, subscriptions, store, from, to)?;
+        let fee_share = sub.monthly_fee_usd / covered.len() as f64;
+
+        for (account, cost) in covered {
+            report.accounts.push(AccountSavings {
+                subscription_id: sub.id.clone(),
+                provider: sub.provider.clone(),
+                plan_name: sub.plan_name.clone(),
+                account,
+                period_start: start.format(DAY_FORMAT).to_string(),
+                period_end: end.format(DAY_FORMAT).to_string(),
+                requests: cost.requests,
+                input_tokens: cost.input_tokens,
+                output_tokens: cost.output_tokens,
+                cached_tokens: cost.cached_tokens,
+                api_cost_usd: cost.cost_usd,
+                fee_usd: fee_share,
+                savings_usd: cost.cost_usd - fee_share,
+            });
+        }
+
+        let provider = match report
+            .providers
+            .iter()
+            .position(|p| p.provider.eq_ignore_ascii_case(&sub.provider))
+        {
+            Some(i) => &mut report.providers[i],
+            None => {
+                report.providers.push(ProviderSavings {
+                    provider: sub.provider.clone(),
+                    ..Default::default()
+                });
+                report.providers.last_mut().unwrap()
+            }
+        };
+        provider.subscriptions += 1;
+        provider.fee_usd += sub.monthly_fee_usd;
+    }
+
+    for row in &report.accounts {
+        if let Some(provider) = report
+            .providers
+            .iter_mut()
+            .find(|p| p.provider.eq_ignore_ascii_case(&row.provider))
+        {
+            provider.api_cost_usd += row.api_cost_usd;
+        }
+    }
+    for provider in &mut report.providers {
+        provider.savings_usd = provider.api_cost_usd - provider.fee_usd;
+        report.total_fee_usd += provider.fee_usd;
+        report.total_api_cost_usd += provider.api_cost_usd;
+    }
+    report.total_savings_usd = report.total_api_cost_usd - report.total_fee_usd;
+    report
+        .providers
+        .sort_by(|a, b| b.savings_usd.total_cmp(&a.savings_usd));
+    Ok(report)
+}
+
+/// Render a report as a Markdown document
+pub fn to_markdown(report: &SubscriptionReport) -> String {
+    let mut out = format!("# Subscription savings — {}\n\n", report.month);
+    if report.in_progress {
+        out.push_str("_Some billing periods haven't ended yet; usage is counted so far._\n\n");
+    }
+    out.push_str(&format!(
+        "**Fees:** {} · **API-equivalent cost:** {} · **Savings:** {}\n\n",
+        usd(report.total_fee_usd),
+        usd(report.total_api_cost_usd),
+        usd(report.total_savings_usd)
+    ));
+
+    out.push_str("## By provider\n\n");
+    out.push_str("| Provider | Subscriptions | Fees | API-equivalent cost | Savings |\n");
+    out.push_str("|---|---:|---:|---:|---:|\n");
+    for p in &report.providers {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            markdown_cell(&p.provider),
+            p.subscriptions,
+            usd(p.fee_usd),
+            usd(p.api_cost_usd),
+            usd(p.savings_usd)
+        ));
+    }
+
+    out.push_str("\n## By account\n\n");
+    out.push_str("| Provider | Plan | Account | Period | Requests | Tokens in | Tokens out | API-equivalent cost | Fee | Savings |\n");
+    out.push_str("|---|---|---|---|---:|---:|---:|---:|---:|---:|\n");
+    for a in &report.accounts {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} – {} | {} | {} | {} | {} | {} | {} |\n",
+            markdown_cell(&a.provider),
+            markdown_cell(&a.plan_name),
+            markdown_cell(a.account.as_deref().unwrap_or("(no linked accounts)")),
+            a.period_start,
+            a.period_end,
+            a.requests,
+            a.input_tokens,
+            a.output_tokens,
+            usd(a.api_cost_usd),
+            usd(a.fee_usd),
+            usd(a.savings_usd)
+        ));
+    }
+    out
+}
+
+/// Render a report as CSV, one row per subscribed account
+pub fn to_csv(report: &SubscriptionReport) -> String {
+    let mut out = String::from(
+        "month,provider,plan,account,period_start,period_end,requests,input_tokens,output_tokens,cached_tokens,api_cost_usd,fee_usd,savings_usd\n",
+    );
+    for a in &report.accounts {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{:.4},{:.4},{:.4}\n",
+            report.month,
+            csv_field(&a.provider),
+            csv_field(&a.plan_name),
+            csv_field(a.account.as_deref().unwrap_or("")),
+            a.period_start,
+            a.period_end,
+            a.requests,
+            a.input_tokens,
+            a.output_tokens,
+            a.cached_tokens,
+            a.api_cost_usd,
+            a.fee_usd,
+            a.savings_usd
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn billing_day_is_clamped_to_short_months() {
+        assert_eq!(billing_period("2025-02", 31), Ok((date(2025, 2, 28), date(2025, 3, 31))));
+        assert_eq!(billing_period("2024-02", 31), Ok((date(2024, 2, 29), date(2024, 3, 31))));
+        assert_eq!(billing_period("2025-01", 31), Ok((date(2025, 1, 31), date(2025, 2, 28))));
+        assert_eq!(billing_period("2025-04", 30), Ok((date(2025, 4, 30), date(2025, 5, 30))));
+    }
+
+    #[test]
+    fn billing_period_crosses_the_year() {
+        assert_eq!(billing_period("2025-12", 15), Ok((date(2025, 12, 15), date(2026, 1, 15))));
+        assert_eq!(billing_period(" 2025-12 ", 1), Ok((date(2025, 12, 1), date(2026, 1, 1))));
+    }
+
+    #[test]
+    fn billing_period_rejects_malformed_months() {
+        assert!(billing_period("2025-13", 1).is_err());
+        assert!(billing_period("December", 1).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+//! Time zones for usage analytics.
+//!
+//! Request timestamps are stored as UTC epoch milliseconds. Anything shown per
+//! day or hour is bucketed from each request's own timestamp in the display
+//! time zone (the `display_timezone` setting, or the system zone when unset),
+//! so midnight, DST changes and travel don't move counts between buckets.
+
+use chrono::{DateTime, Datelike, Local, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+pub const HOUR_FORMAT: &str = "%Y-%m-%dT%H";
+pub const DAY_FORMAT: &str = "%Y-%m-%d";
+pub const MONTH_FORMAT: &str = "%Y-%m";
+
+/// Time zone used to label day/hour/month buckets
+#[derive(Debug, Clone, Copy)]
+pub enum DisplayTimezone {
+    /// The system time zone
+    Local,
+    /// An IANA zone such as "Europe/Berlin" or "UTC"
+    Named(Tz),
+}
+
+impl DisplayTimezone {
+    /// Parse the `display_timezone` setting. Empty, "local" or unknown names mean the system zone.
+    pub fn from_setting(name: &str) -> Self {
+        let name = name.trim();
+        if name.is_empty() || name.eq_ignore_ascii_case("local") {
+            return Self::Local;
+        }
+        match name.parse::<Tz>() {
+            Ok(tz) => Self::Named(tz),
+            Err(_) => {
+                eprintln!("[Timezone] Unknown display timezone '{}', using system time zone", name);
+                Self::Local
+            }
+        }
+    }
+
+    /// Format a UTC timestamp (ms) in this zone
+    pub fn format(&self, timestamp_ms: u64, format: &str) -> String {
+        let Some(utc) = DateTime::<Utc>::from_timestamp_millis(timestamp_ms as i64) else {
+            return String::new();
+        };
+        match self {
+            Self::Local => utc.with_timezone(&Local).format(format).to_string(),
+            Self::Named(tz) => utc.with_timezone(tz).format(format).to_string(),
+        }
+    }
+
+    pub fn hour_label(&self, timestamp_ms: u64) -> String {
+        self.format(timestamp_ms, HOUR_FORMAT)
+    }
+
+    pub fn day_label(&self, timestamp_ms: u64) -> String {
+        self.format(timestamp_ms, DAY_FORMAT)
+    }
+
+    pub fn month_label(&self, timestamp_ms: u64) -> String {
+        self.format(timestamp_ms, MONTH_FORMAT)
+    }
+
+    /// UTC timestamp (ms) of the first instant of `date` in this zone
+    pub fn start_of_day(&self, date: NaiveDate) -> u64 {
+        let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+        // Midnight can fall in a DST gap; the day then starts at the first valid instant
+        let first_instant = |hours: i64| midnight + chrono::Duration::hours(hours);
+        let earliest = (0..=2).find_map(|h| match self {
+            Self::Local => Local.from_local_datetime(&first_instant(h)).earliest().map(|dt| dt.timestamp_millis()),
+            Self::Named(tz) => tz.from_local_datetime(&first_instant(h)).earliest().map(|dt| dt.timestamp_millis()),
+        });
+        earliest.unwrap_or_else(|| midnight.and_utc().timestamp_millis()).max(0) as u64
+    }
+}
+
+/// The daily, weekly (from Monday) or monthly period `[start, end)` (ms) containing
+/// `now`, in the display time zone
+pub fn period_bounds(period: &str, now: u64, tz: &DisplayTimezone) -> (u64, u64) {
+    let today = NaiveDate::parse_from_str(&tz.day_label(now), DAY_FORMAT).unwrap_or_default();
+    let (start, end) = match period {
+        "weekly" => {
+            let monday = today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+            (monday, monday + chrono::Duration::days(7))
+        }
+        "monthly" => {
+            let first = today.with_day(1).unwrap_or(today);
+            (first, first.checked_add_months(chrono::Months::new(1)).unwrap_or(first))
+        }
+        _ => (today, today + chrono::Duration::days(1)),
+    };
+    (tz.start_of_day(start), tz.start_of_day(end))
+}
+
+/// Current time as UTC epoch milliseconds
+pub fn now_ms() -> u64 {
+    Utc::now().timestamp_millis() as u64
+}
+
+/// Convert a wall-clock time from CLIProxyAPI's log (written in the system time
+/// zone) to UTC epoch milliseconds. During the DST fall-back hour the earlier
+/// instant is used; times inside the spring-forward gap are shifted past it.
+pub fn local_log_time_to_utc(naive: NaiveDateTime) -> Option<u64> {
+    let local = match Local.from_local_datetime(&naive) {
+        LocalResult::Single(dt) => dt,
+        LocalResult::Ambiguous(earliest, _) => earliest,
+        LocalResult::None => Local
+            .from_local_datetime(&(naive + chrono::Duration::hours(1)))
+            .earliest()?,
+    };
+    Some(local.timestamp_millis() as u64)
+}
+
+/// Label of the month `months` before `month_label` ("YYYY-MM")
+pub fn months_before(month_label: &str, months: u32) -> String {
+    let mut parts = month_label.splitn(2, '-');
+    let year: i64 = parts.next().and_then(|y| y.parse().ok()).unwrap_or(0);
+    let month: i64 = parts.next().and_then(|m| m.parse().ok()).unwrap_or(1);
+    let index = year * 12 + (month - 1) - months as i64;
+    format!("{:04}-{:02}", index.div_euclid(12), index.rem_euclid(12) + 1)
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+/// A condition on proxy events that raises an alert
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertRule {
+    pub id: String,
+    pub name: String,
+    /// "error_rate", "auth_failures", "latency_p95", "sidecar_restart" or "tunnel_down"
+    pub kind: String,
+    /// Provider the rule applies to (request rules only); empty = each provider
+    #[serde(default)]
+    pub provider: String,
+    /// Failure percentage, number of 401s, p95 in ms, or number of unexpected sidecar exits
+    /// (unused for "tunnel_down")
+    #[serde(default)]
+    pub threshold: f64,
+    /// Evaluation window; for "tunnel_down", how long a tunnel must stay down
+    #[serde(default = "default_alert_window_secs")]
+    pub window_secs: u64,
+    /// Fewest requests in the window before "error_rate" and "latency_p95" are judged
+    #[serde(default = "default_alert_min_requests")]
+    pub min_requests: u64,
+    /// "info", "warning" or "critical"
+    #[serde(default = "default_alert_severity")]
+    pub severity: String,
+    /// Quiet time after the rule fired, per provider or tunnel
+    #[serde(default = "default_alert_cooldown_secs")]
+    pub cooldown_secs: u64,
+    #[serde(default = "default_alert_enabled")]
+    pub enabled: bool,
+}
+
+fn default_alert_window_secs() -> u64 {
+    300
+}
+
+fn default_alert_min_requests() -> u64 {
+    10
+}
+
+fn default_alert_severity() -> String {
+    "warning".to_string()
+}
+
+fn default_alert_cooldown_secs() -> u64 {
+    900
+}
+
+fn default_alert_enabled() -> bool {
+    true
+}
+
+/// A raised alert; also the payload of the `alert` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Alert {
+    pub id: String,
+    pub rule_id: String,
+    pub rule_name: String,
+    pub kind: String,
+    pub severity: String,
+    /// Provider or tunnel the alert is about; empty for the sidecar
+    pub subject: String,
+    pub message: String,
+    /// Measured value (percentage, count, ms or seconds down) and the rule's threshold
+    pub value: f64,
+    pub threshold: f64,
+    pub triggered_at: u64,
+    #[serde(default)]
+    pub acknowledged_at: Option<u64>,
+}
@@ -1,26 +1,35 @@
-use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::providers::PROVIDERS;
+
+/// Connected accounts per provider id; serialized as `{ "claude": 1, ... }`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(transparent)]
 pub struct AuthStatus {
-    pub claude: u32,
-    pub openai: u32,
-    pub gemini: u32,
-    pub qwen: u32,
-    pub iflow: u32,
-    pub vertex: u32,
-    pub antigravity: u32,
+    pub accounts: BTreeMap<String, u32>,
 }
 
+impl AuthStatus {
+    pub fn count(&self, provider: &str) -> u32 {
+        self.accounts.get(provider).copied().unwrap_or(0)
+    }
+
+    pub fn set(&mut self, provider: &str, count: u32) {
+        self.accounts.insert(provider.to_string(), count);
+    }
+
+    pub fn add(&mut self, provider: &str, count: u32) {
+        *self.accounts.entry(provider.to_string()).or_insert(0) += count;
+    }
+}
+
+/// Every registered provider, with no accounts
 impl Default for AuthStatus {
     fn default() -> Self {
         Self {
-            claude: 0,
-            openai: 0,
-            gemini: 0,
-            qwen: 0,
-            iflow: 0,
-            vertex: 0,
-            antigravity: 0,
+            accounts: PROVIDERS.iter().map(|p| (p.id.to_string(), 0)).collect(),
         }
     }
 }
@@ -39,25 +48,24 @@ pub struct ProxyAuthStatus {
     pub providers: ProxyAuthProviders,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+/// Per-provider status reported by CLIProxyAPI, keyed by its provider names
+#[derive(Debug, Clone, Serialize, Default)]
+#[serde(transparent)]
 pub struct ProxyAuthProviders {
-    #[serde(default)]
-    pub gemini: Option<ProxyAuthProviderStatus>,
-    #[serde(default)]
-    pub claude: Option<ProxyAuthProviderStatus>,
-    #[serde(default)]
-    pub openai: Option<ProxyAuthProviderStatus>,
-    #[serde(default)]
-    pub qwen: Option<ProxyAuthProviderStatus>,
-    #[serde(default)]
-    pub iflow: Option<ProxyAuthProviderStatus>,
-    #[serde(default)]
-    pub vertex: Option<ProxyAuthProviderStatus>,
-    #[serde(default)]
-    pub antigravity: Option<ProxyAuthProviderStatus>,
-    #[serde(default)]
-    pub copilot: Option<ProxyAuthProviderStatus>,
+    pub providers: BTreeMap<String, ProxyAuthProviderStatus>,
+}
+
+// Entries that aren't a provider status (e.g. null) are skipped rather than failing the whole response
+impl<'de> Deserialize<'de> for ProxyAuthProviders {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = BTreeMap::<String, serde_json::Value>::deserialize(deserializer)?;
+        Ok(Self {
+            providers: raw
+                .into_iter()
+                .filter_map(|(name, value)| serde_json::from_value(value).ok().map(|status| (name, status)))
+                .collect(),
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,21 +80,6 @@ pub struct ProxyAuthProviderStatus {
     pub error: Option<String>,
 }
 
-impl Default for ProxyAuthProviders {
-    fn default() -> Self {
-        Self {
-            gemini: None,
-            claude: None,
-            openai: None,
-            qwen: None,
-            iflow: None,
-            vertex: None,
-            antigravity: None,
-            copilot: None,
-        }
-    }
-}
-
 impl Default for ProxyAuthStatus {
     fn default() -> Self {
         Self {
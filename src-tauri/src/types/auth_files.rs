@@ -42,4 +42,25 @@ pub struct AuthFile {
     pub success_count: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub failure_count: Option<u64>,
+    /// When the access token expires (Unix ms), read from the credential file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// Whether the credential file holds a refresh token
+    #[serde(default)]
+    pub refreshable: bool,
+}
+
+/// Payload of the `auth-expiring` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthExpiring {
+    /// Credential file name in `~/.cli-proxy-api`
+    pub name: String,
+    pub provider: String,
+    pub expires_at: u64,
+    pub refreshable: bool,
+    /// "refreshed", "refresh_failed" (retried with backoff), "manual" (no refresh
+    /// token, or the proxy didn't refresh it) or "expired"
+    pub action: String,
+    pub message: String,
 }
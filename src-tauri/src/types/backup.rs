@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Aggregate, PricingCatalog, RequestLog};
+
+/// `format` marker of ProxyPal usage backups
+pub const USAGE_BACKUP_FORMAT: &str = "proxypal-usage-backup";
+/// Newest backup version this build reads and the one it writes
+pub const USAGE_BACKUP_VERSION: u32 = 1;
+
+/// What a usage backup holds and where it came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBackupMetadata {
+    pub format: String,
+    pub version: u32,
+    /// ProxyPal version that wrote the backup
+    pub app_version: String,
+    /// When the backup was written (ms since epoch)
+    pub created_at: u64,
+    pub request_count: u64,
+}
+
+/// A ProxyPal-native usage backup: the request log, the aggregate and the user pricing catalog
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBackup {
+    pub metadata: UsageBackupMetadata,
+    /// Every stored request, oldest first
+    #[serde(default)]
+    pub requests: Vec<RequestLog>,
+    pub aggregate: Aggregate,
+    #[serde(default)]
+    pub pricing: PricingCatalog,
+}
+
+/// What importing a usage backup changed
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBackupImportSummary {
+    /// Requests not stored yet (by request ID)
+    pub requests_added: u64,
+    /// Requests already stored, left as they were
+    pub requests_skipped: u64,
+    /// Pricing rules the local catalog didn't have
+    pub pricing_rules_added: u64,
+    pub backup_created_at: u64,
+    pub total_requests: u64,
+    pub total_cost_usd: f64,
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+/// A token or cost limit for one provider, model or client key over a calendar period
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Budget {
+    pub id: String,
+    pub name: String,
+    /// "provider", "model" or "client_key"
+    pub scope: String,
+    /// Provider name, model name (`*`/`?` globs allowed) or client API key
+    pub target: String,
+    /// "daily", "weekly" (from Monday) or "monthly", in the display time zone
+    pub period: String,
+    /// "tokens" (input + output) or "cost" (estimated USD)
+    pub metric: String,
+    pub limit: f64,
+    /// Disable the auth files / API keys that served matching requests until the period resets
+    #[serde(default)]
+    pub enforce: bool,
+    #[serde(default = "default_budget_enabled")]
+    pub enabled: bool,
+}
+
+fn default_budget_enabled() -> bool {
+    true
+}
+
+/// Payload of the `budget-alert` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetAlert {
+    pub budget_id: String,
+    pub name: String,
+    /// Percentage of the limit crossed: 80 or 100
+    pub level: u8,
+    pub metric: String,
+    pub used: f64,
+    pub limit: f64,
+    /// When the period resets (ms since epoch)
+    pub period_end: u64,
+    /// Number of auth files / API keys suspended by this alert
+    pub suspended: u32,
+}
+
+/// An auth file or API key disabled by an enforcing budget
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetSuspension {
+    pub budget_id: String,
+    /// "auth_file" or "api_key"
+    pub kind: String,
+    pub provider: String,
+    /// Auth file name, or an opaque id for an API key (the key itself stays in the backend)
+    pub target: String,
+    /// Account email or masked key, for display
+    pub label: String,
+    /// Restore time (ms since epoch): the end of the budget's period
+    pub until: u64,
+    /// Key entry left by older versions in the state file; moved out on load, never written
+    #[serde(default, skip_serializing)]
+    pub entry: Option<serde_json::Value>,
+}
+
+/// A budget with its usage in the current period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetStatus {
+    pub budget: Budget,
+    pub period_start: u64,
+    pub period_end: u64,
+    pub used: f64,
+    /// `used / limit` (0 when the limit is 0)
+    pub fraction: f64,
+    /// Highest alert level raised this period (0, 80 or 100)
+    pub alert_level: u8,
+    pub suspensions: Vec<BudgetSuspension>,
+}
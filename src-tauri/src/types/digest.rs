@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+
+/// Request, token and cost totals over a digest period
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestTotals {
+    pub requests: u64,
+    pub success_count: u64,
+    pub failure_count: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cached_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// One model's usage in the period, with its request count in the prior period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestModel {
+    pub model: String,
+    pub requests: u64,
+    pub previous_requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestAccount {
+    pub account: String,
+    pub provider: String,
+    pub requests: u64,
+    pub failure_count: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Failures of one category in the period and the prior period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestErrorCount {
+    pub category: String,
+    pub count: u64,
+    pub previous_count: u64,
+}
+
+/// A day on which an error category failed far more often than usual
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorSpike {
+    /// "YYYY-MM-DD" in the display time zone
+    pub day: String,
+    pub category: String,
+    pub count: u64,
+    /// Average failures per day of this category in the prior period
+    pub baseline_per_day: f64,
+}
+
+/// Latency of one provider in the period vs. the prior period (None: no requests then)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyChange {
+    pub provider: String,
+    pub count: u64,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub previous_p50_ms: Option<u64>,
+    pub previous_p90_ms: Option<u64>,
+}
+
+/// A subscription's API-equivalent cost in the period vs. its fee prorated to the period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestSavings {
+    pub provider: String,
+    pub plan_name: String,
+    pub api_cost_usd: f64,
+    pub fee_usd: f64,
+    pub savings_usd: f64,
+}
+
+/// A usage digest for `[from, to)` compared with the period of equal length before it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageDigest {
+    pub from: u64,
+    pub to: u64,
+    /// First and last day covered, e.g. "2026-03-09 – 2026-03-15"
+    pub label: String,
+    pub generated_at: u64,
+    pub totals: DigestTotals,
+    pub previous: DigestTotals,
+    pub top_models: Vec<DigestModel>,
+    pub accounts: Vec<DigestAccount>,
+    pub errors: Vec<DigestErrorCount>,
+    pub error_spikes: Vec<ErrorSpike>,
+    pub latency: Vec<LatencyChange>,
+    pub savings: Vec<DigestSavings>,
+    pub total_savings_usd: f64,
+}
+
+/// A digest and the files it was written to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestFiles {
+    pub markdown_path: String,
+    pub html_path: String,
+    pub digest: UsageDigest,
+}
@@ -1,15 +1,12 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
-// Provider health status
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Provider health status, keyed by provider id
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(transparent)]
 pub struct ProviderHealth {
-    pub claude: HealthStatus,
-    pub openai: HealthStatus,
-    pub gemini: HealthStatus,
-    pub qwen: HealthStatus,
-    pub iflow: HealthStatus,
-    pub vertex: HealthStatus,
-    pub antigravity: HealthStatus,
+    pub providers: BTreeMap<String, HealthStatus>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
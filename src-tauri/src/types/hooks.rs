@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+/// An HTTP webhook or local command run when subscribed backend events are emitted
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Hook {
+    pub id: String,
+    pub name: String,
+    /// "webhook" or "command"
+    pub kind: String,
+    /// Event names, e.g. "proxy-status-changed" or "request-log"
+    pub events: Vec<String>,
+    /// Webhook URL (POST)
+    #[serde(default)]
+    pub url: String,
+    /// Signs webhook bodies with HMAC-SHA256 when set
+    #[serde(default)]
+    pub secret: String,
+    /// Webhook body: "json" (event envelope), "slack" (`{"text": ...}`) or "template"
+    #[serde(default = "default_hook_format")]
+    pub format: String,
+    /// Body for the "template" format; `{{event}}`, `{{summary}}`, `{{timestamp}}`,
+    /// `{{payload}}` and `{{payload.<field>}}` are replaced (JSON-escaped)
+    #[serde(default)]
+    pub template: String,
+    /// Absolute path of the executable to run (no shell); gets the event in
+    /// `PROXYPAL_EVENT` and the payload JSON in `PROXYPAL_PAYLOAD` and on stdin
+    #[serde(default)]
+    pub command: String,
+    /// Arguments passed to the executable as-is
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Extra attempts after a failed delivery, at most 10 (webhooks only)
+    #[serde(default = "default_hook_retries")]
+    pub max_retries: u32,
+    #[serde(default = "default_hook_enabled")]
+    pub enabled: bool,
+}
+
+fn default_hook_format() -> String {
+    "json".to_string()
+}
+
+fn default_hook_retries() -> u32 {
+    3
+}
+
+fn default_hook_enabled() -> bool {
+    true
+}
+
+/// Outcome of running a hook for one event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookDelivery {
+    pub id: String,
+    pub hook_id: String,
+    pub hook_name: String,
+    pub event: String,
+    pub timestamp: u64,
+    pub success: bool,
+    pub attempts: u32,
+    /// HTTP status (webhooks) or exit code (commands), when there was one
+    pub status: Option<i32>,
+    /// Error, or the start of the response / command output
+    pub message: String,
+    pub duration_ms: u64,
+}
@@ -8,3 +8,52 @@ pub struct LogEntry {
     pub level: String,
     pub message: String,
 }
+
+/// Identity of a log file that survives renames (inode on Unix, creation time elsewhere)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileIdentity {
+    #[serde(default)]
+    pub inode: u64,
+    #[serde(default)]
+    pub created_ms: u64,
+}
+
+impl FileIdentity {
+    pub fn same_file(&self, other: &FileIdentity) -> bool {
+        if self.inode != 0 && other.inode != 0 {
+            return self.inode == other.inode;
+        }
+        if self.created_ms != 0 && other.created_ms != 0 {
+            return self.created_ms == other.created_ms;
+        }
+        // Nothing to compare on this platform; assume it's the same file
+        true
+    }
+}
+
+/// Persisted log watcher position, so ingestion resumes across restarts and rotations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogCursor {
+    pub path: String,
+    pub identity: FileIdentity,
+    /// File size when the cursor was saved
+    #[serde(default)]
+    pub size: u64,
+    pub offset: u64,
+}
+
+/// User-defined request path the log watcher should track, in addition to the built-in endpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackedEndpoint {
+    /// Path fragment to match, e.g. "/v1/rerank"
+    pub path: String,
+    /// API protocol: "openai", "claude" or "gemini"
+    #[serde(default)]
+    pub protocol: String,
+    /// Endpoint kind: "generation", "embedding", "count_tokens", "image", ...
+    #[serde(default)]
+    pub kind: String,
+}
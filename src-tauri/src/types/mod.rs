@@ -1,32 +1,46 @@
 pub mod agents;
+pub mod alerts;
 pub mod amp;
 pub mod api_keys;
 pub mod auth;
 pub mod auth_files;
+pub mod backup;
+pub mod budgets;
 pub mod copilot;
+pub mod digest;
 pub mod health;
+pub mod hooks;
 pub mod logs;
 pub mod models;
+pub mod pricing;
 pub mod proxy;
 pub mod quota;
 pub mod settings;
+pub mod subscriptions;
 pub mod usage;
 
 pub mod ssh;
 pub mod cloudflare;
 
 pub use agents::*;
+pub use alerts::*;
 pub use amp::*;
 pub use api_keys::*;
 pub use auth::*;
 pub use auth_files::*;
+pub use backup::*;
+pub use budgets::*;
 pub use copilot::*;
+pub use digest::*;
 pub use health::*;
+pub use hooks::*;
 pub use logs::*;
 pub use models::*;
+pub use pricing::*;
 pub use proxy::*;
 pub use quota::*;
 pub use settings::*;
+pub use subscriptions::*;
 pub use usage::*;
 pub use ssh::*;
 #[allow(unused_imports)]
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+/// Prices (USD per 1M tokens) for the models and/or provider a rule matches.
+/// A rule with a `model` pattern is a model rule, one with only `provider` a
+/// provider rule, and one with neither the catalog's fallback.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceRule {
+    /// Model name glob, matched case-insensitively (`*` any run of characters, `?` one character)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Provider the rule is limited to ("claude", "openai", "gemini", ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    pub input: f64,
+    pub output: f64,
+    /// Cached input tokens; defaults to the input price
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read: Option<f64>,
+    /// Tokens written to the prompt cache; defaults to the input price
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write: Option<f64>,
+    /// First day ("YYYY-MM-DD", UTC) the prices apply; later-dated rules for the same
+    /// models supersede earlier ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_from: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PricingCatalog {
+    #[serde(default)]
+    pub rules: Vec<PriceRule>,
+}
+
+/// The bundled catalog and the user's overrides (consulted first)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PricingCatalogs {
+    pub bundled: PricingCatalog,
+    pub user: PricingCatalog,
+    /// Where the user catalog is read from
+    pub user_path: String,
+}
+
+/// Total estimated cost before and after re-pricing stored usage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CostRecomputeSummary {
+    pub previous_cost_usd: f64,
+    pub cost_usd: f64,
+}
@@ -42,3 +42,180 @@ impl Default for ReasoningEffortSettings {
         }
     }
 }
+
+/// How long usage analytics are kept at each granularity (0 = forever).
+/// Data past its retention is rolled into the next coarser level, not lost.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageRetention {
+    /// Individual request rows in the request store
+    #[serde(default = "default_raw_retention_days")]
+    pub raw_days: u32,
+    /// Hourly series points
+    #[serde(default = "default_hourly_retention_days")]
+    pub hourly_days: u32,
+    /// Daily series points (older days are rolled up by month)
+    #[serde(default = "default_daily_retention_days")]
+    pub daily_days: u32,
+    /// Monthly series points
+    #[serde(default)]
+    pub monthly_months: u32,
+}
+
+fn default_raw_retention_days() -> u32 {
+    90
+}
+
+fn default_hourly_retention_days() -> u32 {
+    7
+}
+
+fn default_daily_retention_days() -> u32 {
+    365
+}
+
+impl Default for UsageRetention {
+    fn default() -> Self {
+        Self {
+            raw_days: 90,
+            hourly_days: 7,
+            daily_days: 365,
+            monthly_months: 0,
+        }
+    }
+}
+
+/// Optional Prometheus metrics endpoint (`GET /metrics`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Address to listen on; use "0.0.0.0" to allow scrapes from other machines
+    #[serde(default = "default_metrics_bind")]
+    pub bind: String,
+    #[serde(default = "default_metrics_port")]
+    pub port: u16,
+    /// Required as `Authorization: Bearer <token>` when set
+    #[serde(default)]
+    pub bearer_token: String,
+}
+
+fn default_metrics_bind() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_metrics_port() -> u16 {
+    9464
+}
+
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: default_metrics_bind(),
+            port: default_metrics_port(),
+            bearer_token: String::new(),
+        }
+    }
+}
+
+/// Export of logged requests to an OpenTelemetry collector (OTLP/HTTP, JSON encoding)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OtlpSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Collector base URL; `/v1/traces` or `/v1/logs` is appended unless already present
+    #[serde(default = "default_otlp_endpoint")]
+    pub endpoint: String,
+    /// "traces" (one span per request) or "logs" (one log record per request)
+    #[serde(default = "default_otlp_signal")]
+    pub signal: String,
+    /// `service.name` resource attribute
+    #[serde(default = "default_otlp_service_name")]
+    pub service_name: String,
+    /// Extra HTTP headers sent with every export (e.g. collector auth)
+    #[serde(default)]
+    pub headers: std::collections::BTreeMap<String, String>,
+}
+
+fn default_otlp_endpoint() -> String {
+    "http://127.0.0.1:4318".to_string()
+}
+
+fn default_otlp_signal() -> String {
+    "traces".to_string()
+}
+
+fn default_otlp_service_name() -> String {
+    "proxypal".to_string()
+}
+
+impl Default for OtlpSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: default_otlp_endpoint(),
+            signal: default_otlp_signal(),
+            service_name: default_otlp_service_name(),
+            headers: std::collections::BTreeMap::new(),
+        }
+    }
+}
+
+/// Scheduled usage digests (Markdown and HTML files)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// "daily" (the previous day) or "weekly" (the previous Monday–Sunday)
+    #[serde(default = "default_digest_frequency")]
+    pub frequency: String,
+    /// Folder the digests are written to; empty = "reports" in the ProxyPal config folder
+    #[serde(default)]
+    pub folder: String,
+}
+
+fn default_digest_frequency() -> String {
+    "weekly".to_string()
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            frequency: default_digest_frequency(),
+            folder: String::new(),
+        }
+    }
+}
+
+/// Proactive refresh of OAuth tokens before they expire
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenRefreshSettings {
+    #[serde(default = "default_token_refresh_enabled")]
+    pub enabled: bool,
+    /// How long before expiry a refresh is triggered (or a warning raised)
+    #[serde(default = "default_token_refresh_lead_minutes")]
+    pub lead_minutes: u64,
+}
+
+fn default_token_refresh_enabled() -> bool {
+    true
+}
+
+fn default_token_refresh_lead_minutes() -> u64 {
+    10
+}
+
+impl Default for TokenRefreshSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_token_refresh_enabled(),
+            lead_minutes: default_token_refresh_lead_minutes(),
+        }
+    }
+}
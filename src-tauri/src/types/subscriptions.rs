@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+/// A flat-rate plan (e.g. Claude Max, ChatGPT Plus) and the accounts it covers
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Subscription {
+    pub id: String,
+    /// Provider name as used elsewhere in ProxyPal ("claude", "openai", "gemini", ...)
+    pub provider: String,
+    pub plan_name: String,
+    pub monthly_fee_usd: f64,
+    /// Day of the month the plan renews (1-31; clamped to the month's length)
+    pub billing_day: u8,
+    /// Accounts (OAuth emails or masked API keys, as shown in account usage) paid for by this plan
+    #[serde(default)]
+    pub accounts: Vec<String>,
+}
+
+/// API-equivalent cost of one subscribed account over one billing period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountSavings {
+    pub subscription_id: String,
+    pub provider: String,
+    pub plan_name: String,
+    /// None for a subscription with no linked accounts, whose row covers its share of the
+    /// provider usage not served by accounts linked to other plans
+    pub account: Option<String>,
+    /// First day of the billing period (inclusive, "YYYY-MM-DD")
+    pub period_start: String,
+    /// Renewal day ending the period (exclusive, "YYYY-MM-DD")
+    pub period_end: String,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cached_tokens: u64,
+    pub api_cost_usd: f64,
+    /// The account's share of the subscription fee
+    pub fee_usd: f64,
+    /// API-equivalent cost minus fee; negative when the plan cost more than its usage was worth
+    pub savings_usd: f64,
+}
+
+/// Subscription fees and API-equivalent cost summed per provider
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderSavings {
+    pub provider: String,
+    pub subscriptions: u32,
+    pub fee_usd: f64,
+    pub api_cost_usd: f64,
+    pub savings_usd: f64,
+}
+
+/// Savings report for the billing periods that start in one month
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionReport {
+    /// "YYYY-MM"
+    pub month: String,
+    /// True while any billing period in the report hasn't ended yet
+    pub in_progress: bool,
+    pub accounts: Vec<AccountSavings>,
+    pub providers: Vec<ProviderSavings>,
+    pub total_fee_usd: f64,
+    pub total_api_cost_usd: f64,
+    pub total_savings_usd: f64,
+}
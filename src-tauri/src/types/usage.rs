@@ -4,6 +4,9 @@ use serde::{Deserialize, Serialize};
 #[serde(rename_all = "camelCase")]
 pub struct RequestLog {
     pub id: String,
+    /// CLIProxyAPI's 8-hex request ID, when the log line carries one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
     pub timestamp: u64,
     pub provider: String,
     pub model: String,
@@ -14,6 +17,28 @@ pub struct RequestLog {
     pub tokens_in: Option<u32>,
     pub tokens_out: Option<u32>,
     pub tokens_cached: Option<u32>,
+    /// Client API key the request was made with, when known (stored as `api_key_fingerprint`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// "generation", "embedding", "count_tokens", "image", ...
+    #[serde(default = "default_endpoint_kind")]
+    pub endpoint_kind: String,
+    /// OAuth account email (or masked API key) that served the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    /// Index of the API key in ProxyPal's key list, for key-based routes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_index: Option<u32>,
+    /// Failure category: "quota", "auth", "timeout", "upstream" or "client"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_category: Option<String>,
+    /// Log line that best explains the failure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_message: Option<String>,
+}
+
+fn default_endpoint_kind() -> String {
+    "generation".to_string()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -85,6 +110,142 @@ pub struct ModelStats {
     pub cached_tokens: u64,
 }
 
+/// Growth factor between latency histogram buckets (~2.5% relative error)
+const LATENCY_BUCKET_GROWTH: f64 = 1.05;
+
+/// Log-bucketed latency histogram. Bucket `i` counts durations up to
+/// `1.05^i` ms, so percentiles stay accurate without keeping raw durations.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyHistogram {
+    pub buckets: std::collections::BTreeMap<u32, u64>,
+    pub count: u64,
+    pub sum_ms: u64,
+    pub max_ms: u64,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, duration_ms: u64) {
+        let bucket = if duration_ms <= 1 {
+            0
+        } else {
+            ((duration_ms as f64).ln() / LATENCY_BUCKET_GROWTH.ln()).ceil() as u32
+        };
+        *self.buckets.entry(bucket).or_insert(0) += 1;
+        self.count += 1;
+        self.sum_ms += duration_ms;
+        self.max_ms = self.max_ms.max(duration_ms);
+    }
+
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (bucket, count) in &other.buckets {
+            *self.buckets.entry(*bucket).or_insert(0) += count;
+        }
+        self.count += other.count;
+        self.sum_ms += other.sum_ms;
+        self.max_ms = self.max_ms.max(other.max_ms);
+    }
+
+    /// Upper bound of the bucket holding the `p`th percentile (0-100), capped at the max
+    pub fn percentile(&self, p: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((p / 100.0) * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (bucket, count) in &self.buckets {
+            seen += count;
+            if seen >= rank {
+                let upper = LATENCY_BUCKET_GROWTH.powi(*bucket as i32).round() as u64;
+                return upper.min(self.max_ms);
+            }
+        }
+        self.max_ms
+    }
+
+    /// Durations in buckets whose upper bound is at most `ms` (for re-bucketing)
+    pub fn count_at_most(&self, ms: u64) -> u64 {
+        self.buckets
+            .iter()
+            .take_while(|(bucket, _)| LATENCY_BUCKET_GROWTH.powi(**bucket as i32).round() as u64 <= ms)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    pub fn summary(&self, key: &str) -> LatencySummary {
+        LatencySummary {
+            key: key.to_string(),
+            count: self.count,
+            mean_ms: if self.count == 0 { 0 } else { self.sum_ms / self.count },
+            p50_ms: self.percentile(50.0),
+            p90_ms: self.percentile(90.0),
+            p99_ms: self.percentile(99.0),
+            max_ms: self.max_ms,
+            provider: None,
+        }
+    }
+}
+
+/// Latency histograms of successful requests per model, per provider and per
+/// model served through a provider
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyStats {
+    #[serde(default)]
+    pub by_model: std::collections::HashMap<String, LatencyHistogram>,
+    #[serde(default)]
+    pub by_provider: std::collections::HashMap<String, LatencyHistogram>,
+    /// Keyed by `route_key(model, provider)`
+    #[serde(default)]
+    pub by_route: std::collections::HashMap<String, LatencyHistogram>,
+}
+
+/// Key of a model and provider pair in `LatencyStats::by_route`
+pub fn route_key(model: &str, provider: &str) -> String {
+    format!("{}|{}", model, provider)
+}
+
+/// The (model, provider) of a `by_route` key
+pub fn split_route_key(key: &str) -> (&str, &str) {
+    key.rsplit_once('|').unwrap_or((key, ""))
+}
+
+impl LatencyStats {
+    pub fn record(&mut self, model: &str, provider: &str, duration_ms: u64) {
+        self.by_model.entry(model.to_string()).or_default().record(duration_ms);
+        self.by_provider.entry(provider.to_string()).or_default().record(duration_ms);
+        self.by_route.entry(route_key(model, provider)).or_default().record(duration_ms);
+    }
+
+    pub fn merge(&mut self, other: &LatencyStats) {
+        for (model, histogram) in &other.by_model {
+            self.by_model.entry(model.clone()).or_default().merge(histogram);
+        }
+        for (provider, histogram) in &other.by_provider {
+            self.by_provider.entry(provider.clone()).or_default().merge(histogram);
+        }
+        for (route, histogram) in &other.by_route {
+            self.by_route.entry(route.clone()).or_default().merge(histogram);
+        }
+    }
+}
+
+/// Latency percentiles for one model, provider or model through a provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencySummary {
+    pub key: String,
+    pub count: u64,
+    pub mean_ms: u64,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+    /// Provider when grouped by route; `key` is then the model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Aggregate {
@@ -105,10 +266,34 @@ pub struct Aggregate {
     pub requests_by_hour: Vec<TimeSeriesPoint>,
     #[serde(default)]
     pub tokens_by_hour: Vec<TimeSeriesPoint>,
+    /// Daily points past their retention, rolled up by month ("YYYY-MM")
+    #[serde(default)]
+    pub requests_by_month: Vec<TimeSeriesPoint>,
+    #[serde(default)]
+    pub tokens_by_month: Vec<TimeSeriesPoint>,
     #[serde(default)]
     pub model_stats: std::collections::HashMap<String, ModelStats>,
     #[serde(default)]
     pub provider_stats: std::collections::HashMap<String, ModelStats>,
+    /// Stats per endpoint kind (generation vs. embeddings, token counting, ...)
+    #[serde(default)]
+    pub endpoint_stats: std::collections::HashMap<String, ModelStats>,
+    /// Stats per account (OAuth email or masked API key)
+    #[serde(default)]
+    pub account_stats: std::collections::HashMap<String, ModelStats>,
+    /// Failure counts per error category
+    #[serde(default)]
+    pub error_stats: std::collections::HashMap<String, u64>,
+    /// All-time latency histograms
+    #[serde(default)]
+    pub latency: LatencyStats,
+    /// Latency histograms per day ("YYYY-MM-DD"), trimmed to recent days
+    #[serde(default)]
+    pub latency_by_day: std::collections::BTreeMap<String, LatencyStats>,
+    /// What each source reported for the headline totals above (see `usage_merge`).
+    /// None for aggregates written before sources were tracked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sources: Option<UsageSources>,
 }
 
 impl Default for Aggregate {
@@ -129,12 +314,80 @@ impl Default for Aggregate {
             tokens_by_day: vec![],
             requests_by_hour: vec![],
             tokens_by_hour: vec![],
+            requests_by_month: vec![],
+            tokens_by_month: vec![],
             model_stats: std::collections::HashMap::new(),
             provider_stats: std::collections::HashMap::new(),
+            endpoint_stats: std::collections::HashMap::new(),
+            account_stats: std::collections::HashMap::new(),
+            error_stats: std::collections::HashMap::new(),
+            latency: LatencyStats::default(),
+            latency_by_day: std::collections::BTreeMap::new(),
+            sources: None,
         }
     }
 }
 
+/// Headline totals as counted by one source
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceTotals {
+    pub requests: u64,
+    pub success_count: u64,
+    pub failure_count: u64,
+    pub tokens_in: u64,
+    pub tokens_out: u64,
+    pub tokens_cached: u64,
+    pub cost_usd: f64,
+}
+
+/// Per-source totals behind the aggregate's headline numbers
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageSources {
+    /// Counted from CLIProxyAPI's log by the watcher (tokens only where backfilled)
+    pub watcher: SourceTotals,
+    /// From the usage ledger fed by CLIProxyAPI's usage API
+    pub proxy: SourceTotals,
+    /// Source each headline field was taken from, by field name: "watcher" or "proxy"
+    #[serde(default)]
+    pub provenance: std::collections::BTreeMap<String, String>,
+}
+
+/// One headline field as each source sees it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldDrift {
+    pub field: String,
+    pub watcher: f64,
+    pub proxy: f64,
+    /// Value in the aggregate
+    pub value: f64,
+    pub source: String,
+}
+
+/// Per-model comparison of logged requests with ledger entries over a time range
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelDrift {
+    pub model: String,
+    pub watcher_requests: u64,
+    pub proxy_requests: u64,
+    pub watcher_failures: u64,
+    pub proxy_failures: u64,
+    pub watcher_tokens: u64,
+    pub proxy_tokens: u64,
+}
+
+/// Where log-derived and proxy-derived usage disagree
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageDriftReport {
+    pub fields: Vec<FieldDrift>,
+    /// Models whose counts differ between the sources, largest difference first
+    pub models: Vec<ModelDrift>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct RequestHistory {
@@ -152,3 +405,211 @@ pub struct RequestHistory {
     #[serde(default)]
     pub total_success_count: u64,  // Successful requests (status < 400) across all history
 }
+
+/// Filters, sorting and pagination for `query_requests`
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestQuery {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub endpoint_kind: Option<String>,
+    #[serde(default)]
+    pub request_id: Option<String>,
+    #[serde(default)]
+    pub account: Option<String>,
+    #[serde(default)]
+    pub error_category: Option<String>,
+    #[serde(default)]
+    pub status: Option<u16>,
+    #[serde(default)]
+    pub failed_only: Option<bool>,
+    /// Inclusive start of the time range (ms since epoch)
+    #[serde(default)]
+    pub from: Option<u64>,
+    /// Exclusive end of the time range (ms since epoch)
+    #[serde(default)]
+    pub to: Option<u64>,
+    /// "timestamp" (default), "duration", "status", "model" or "provider"
+    #[serde(default)]
+    pub sort_by: Option<String>,
+    /// Defaults to true (newest first)
+    #[serde(default)]
+    pub descending: Option<bool>,
+    /// Page size, 1-1000 (default 100)
+    #[serde(default)]
+    pub limit: Option<u32>,
+    /// Opaque cursor from a previous page's `next_cursor`
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestPage {
+    pub requests: Vec<RequestLog>,
+    pub next_cursor: Option<String>,
+    /// Number of requests matching the filters (across all pages)
+    pub total: u64,
+}
+
+/// Usage served by one account (OAuth login or API key)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountUsage {
+    pub account: String,
+    pub provider: String,
+    pub api_key_index: Option<u32>,
+    pub requests: u64,
+    pub success_count: u64,
+    pub failure_count: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cached_tokens: u64,
+    /// Timestamp of the most recent request (ms since epoch)
+    pub last_used: u64,
+}
+
+/// Failures in one time bucket for one group (provider, model or account) and category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorSeriesPoint {
+    pub label: String,
+    pub group: String,
+    pub category: String,
+    pub count: u64,
+}
+
+/// A recurring failure cause
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorCause {
+    pub category: String,
+    pub provider: String,
+    pub model: String,
+    pub count: u64,
+    /// Distinct accounts that hit this failure
+    pub accounts: Vec<String>,
+    pub last_seen: u64,
+    /// Most recent distinct log lines for this cause
+    pub examples: Vec<String>,
+}
+
+/// Query for usage over time broken down by model, provider or account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageSeriesQuery {
+    /// "model", "provider" or "account"
+    pub group_by: String,
+    /// "day" (default) or "hour"
+    #[serde(default)]
+    pub bucket: Option<String>,
+    /// Range start (ms, inclusive)
+    pub from: u64,
+    /// Range end (ms, exclusive)
+    pub to: u64,
+    /// Also return the same-length period immediately before `from`
+    #[serde(default)]
+    pub compare_previous: bool,
+}
+
+/// Usage in one time bucket for one group
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageSeriesPoint {
+    pub label: String,
+    pub group: String,
+    pub requests: u64,
+    pub success_count: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cached_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Totals for one group over the queried period (and the previous one, when compared)
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageGroupTotal {
+    pub group: String,
+    pub requests: u64,
+    pub tokens: u64,
+    pub cost_usd: f64,
+    pub previous_requests: Option<u64>,
+    pub previous_tokens: Option<u64>,
+    pub previous_cost_usd: Option<f64>,
+    /// Change in requests vs. the previous period, in percent (None if it had none)
+    pub requests_change_pct: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageSeries {
+    pub current: Vec<UsageSeriesPoint>,
+    /// Same grouping over `[from - (to - from), from)`
+    pub previous: Option<Vec<UsageSeriesPoint>>,
+    /// Per-group totals, largest first
+    pub totals: Vec<UsageGroupTotal>,
+}
+
+/// Time range of an export; open-ended sides are unbounded
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRange {
+    /// Range start (ms, inclusive)
+    #[serde(default)]
+    pub from: Option<u64>,
+    /// Range end (ms, exclusive)
+    #[serde(default)]
+    pub to: Option<u64>,
+}
+
+/// How `export_aggregate` rolls up usage: by time bucket, by group, or both
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateGrouping {
+    /// "hour", "day" or "month" (display time zone)
+    #[serde(default)]
+    pub bucket: Option<String>,
+    /// "provider", "model", "account" or "endpoint"
+    #[serde(default)]
+    pub by: Option<String>,
+}
+
+/// What an export wrote
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub path: String,
+    /// "csv" or "jsonl"
+    pub format: String,
+    pub rows: u64,
+}
+
+/// Result of matching CLIProxyAPI usage details to logged requests
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReconcileSummary {
+    /// Requests whose token counts were filled in
+    pub matched: u64,
+    /// Requests in the window still without token counts
+    pub unmatched_requests: u64,
+    /// Usage details in the window that matched no request
+    pub unmatched_details: u64,
+}
+
+/// What a compaction pass changed
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactionSummary {
+    /// Request rows folded into the store's baseline and deleted
+    pub requests_rolled_up: u64,
+    pub hourly_points_removed: u64,
+    /// Daily points moved into the monthly series
+    pub daily_points_rolled_up: u64,
+    pub monthly_points_removed: u64,
+}
@@ -0,0 +1,192 @@
+//! Merging the two usage sources into the aggregate.
+//!
+//! The log watcher counts every request CLIProxyAPI logs but sees no tokens,
+//! while the usage API (through the ledger) reports tokens but only for requests
+//! the proxy recorded. Each source keeps its own totals in `Aggregate.sources`;
+//! the headline fields are then derived from them. Fields are taken in
+//! consistent groups, each from the source with the larger total for the group
+//! (neither over-counts once de-duplicated): request/success/failure counts
+//! together, so they always add up, and tokens together with the cost estimated
+//! from them. Merging is idempotent, so syncing the same data twice changes
+//! nothing.
+
+use std::collections::HashMap;
+
+use crate::proxy::usage_snapshot::UsageDetail;
+use crate::types::{Aggregate, FieldDrift, ModelStats, SourceTotals, TimeSeriesPoint, UsageSources};
+use crate::pricing::{self, TokenUsage};
+use crate::utils::detect_provider_from_model;
+
+pub const SOURCE_WATCHER: &str = "watcher";
+pub const SOURCE_PROXY: &str = "proxy";
+
+/// The aggregate's per-source totals. Aggregates written before sources were tracked
+/// start with their current totals attributed to the watcher.
+pub fn sources_mut(agg: &mut Aggregate) -> &mut UsageSources {
+    let seed = SourceTotals {
+        requests: agg.total_requests,
+        success_count: agg.total_success_count,
+        failure_count: agg.total_failure_count,
+        tokens_in: agg.total_tokens_in,
+        tokens_out: agg.total_tokens_out,
+        tokens_cached: agg.total_tokens_cached,
+        cost_usd: agg.total_cost_usd,
+    };
+    agg.sources.get_or_insert_with(|| UsageSources {
+        watcher: seed,
+        ..Default::default()
+    })
+}
+
+/// Per-model stats from usage details, counted the same way as the ledger
+pub fn model_stats_from_details(details: &[UsageDetail]) -> HashMap<String, ModelStats> {
+    let mut stats: HashMap<String, ModelStats> = HashMap::new();
+    for d in details {
+        let entry = stats.entry(d.model.clone()).or_default();
+        entry.requests += 1;
+        if !d.failed {
+            entry.success_count += 1;
+        }
+        entry.tokens += d.total_tokens;
+        entry.input_tokens += d.input_tokens;
+        entry.output_tokens += d.output_tokens;
+        entry.cached_tokens += d.cached_tokens;
+    }
+    stats
+}
+
+/// Proxy-side totals from cumulative per-model stats (the ledger, or a usage snapshot).
+/// Cost is priced separately, since it depends on when each request was made.
+pub fn proxy_totals(models: &HashMap<String, ModelStats>, cost_usd: f64) -> SourceTotals {
+    let mut totals = SourceTotals {
+        cost_usd,
+        ..Default::default()
+    };
+    for stats in models.values() {
+        totals.requests += stats.requests;
+        totals.success_count += stats.success_count;
+        totals.failure_count += stats.requests.saturating_sub(stats.success_count);
+        totals.tokens_in += stats.input_tokens;
+        totals.tokens_out += stats.output_tokens;
+        totals.tokens_cached += stats.cached_tokens;
+    }
+    totals
+}
+
+/// Estimated cost of usage details at current prices
+pub fn details_cost(details: &[UsageDetail]) -> f64 {
+    details
+        .iter()
+        .map(|d| {
+            let usage = TokenUsage {
+                input: d.input_tokens,
+                output: d.output_tokens,
+                cache_read: d.cached_tokens,
+                cache_write: 0,
+            };
+            pricing::cost(&d.model, &detect_provider_from_model(&d.model), d.timestamp, usage)
+        })
+        .sum()
+}
+
+/// The source with the larger total for a group of fields (the watcher on ties),
+/// recorded as the provenance of every field in the group
+fn pick_source(
+    provenance: &mut std::collections::BTreeMap<String, String>,
+    fields: &[&str],
+    watcher: u64,
+    proxy: u64,
+) -> &'static str {
+    let source = if proxy > watcher { SOURCE_PROXY } else { SOURCE_WATCHER };
+    for field in fields {
+        provenance.insert(field.to_string(), source.to_string());
+    }
+    source
+}
+
+/// Replace the proxy totals and re-derive the headline fields
+pub fn set_proxy_totals(agg: &mut Aggregate, totals: SourceTotals) {
+    sources_mut(agg).proxy = totals;
+    merge_sources(agg);
+}
+
+/// Derive the headline totals from the per-source totals, recording where each came from
+pub fn merge_sources(agg: &mut Aggregate) {
+    let mut sources = sources_mut(agg).clone();
+    let (w, p) = (&sources.watcher, &sources.proxy);
+    let provenance = &mut sources.provenance;
+
+    let counts = ["totalRequests", "totalSuccessCount", "totalFailureCount"];
+    let counts_from = match pick_source(provenance, &counts, w.requests, p.requests) {
+        SOURCE_PROXY => p,
+        _ => w,
+    };
+    agg.total_requests = counts_from.requests;
+    agg.total_success_count = counts_from.success_count;
+    agg.total_failure_count = counts_from.failure_count;
+
+    // Cost is estimated from the tokens, so it comes from the same source
+    let tokens = ["totalTokensIn", "totalTokensOut", "totalTokensCached", "totalCostUsd"];
+    let token_total = |t: &SourceTotals| t.tokens_in + t.tokens_out + t.tokens_cached;
+    let tokens_from = match pick_source(provenance, &tokens, token_total(w), token_total(p)) {
+        SOURCE_PROXY => p,
+        _ => w,
+    };
+    agg.total_tokens_in = tokens_from.tokens_in;
+    agg.total_tokens_out = tokens_from.tokens_out;
+    agg.total_tokens_cached = tokens_from.tokens_cached;
+    agg.total_cost_usd = tokens_from.cost_usd;
+
+    agg.sources = Some(sources);
+}
+
+/// Merge proxy-reported series points, keeping the larger value per label
+pub fn merge_series_max(series: &mut Vec<TimeSeriesPoint>, points: &[TimeSeriesPoint]) {
+    for point in points {
+        match series.iter_mut().find(|p| p.label == point.label) {
+            Some(existing) => existing.value = existing.value.max(point.value),
+            None => series.push(point.clone()),
+        }
+    }
+    series.sort_by(|a, b| a.label.cmp(&b.label));
+}
+
+/// Merge proxy-reported per-model stats, keeping the larger value per field
+pub fn merge_model_stats_max(existing: &mut ModelStats, proxy: &ModelStats) {
+    existing.requests = existing.requests.max(proxy.requests);
+    existing.success_count = existing.success_count.max(proxy.success_count);
+    existing.tokens = existing.tokens.max(proxy.tokens);
+    existing.input_tokens = existing.input_tokens.max(proxy.input_tokens);
+    existing.output_tokens = existing.output_tokens.max(proxy.output_tokens);
+    existing.cached_tokens = existing.cached_tokens.max(proxy.cached_tokens);
+}
+
+/// Each headline field with both sources' values
+pub fn field_drift(agg: &Aggregate) -> Vec<FieldDrift> {
+    let sources = agg.sources.clone().unwrap_or_default();
+    let (w, p) = (&sources.watcher, &sources.proxy);
+    let source = |field: &str| {
+        sources
+            .provenance
+            .get(field)
+            .cloned()
+            .unwrap_or_else(|| SOURCE_WATCHER.to_string())
+    };
+    let row = |field: &str, watcher: f64, proxy: f64, value: f64| FieldDrift {
+        field: field.to_string(),
+        watcher,
+        proxy,
+        value,
+        source: source(field),
+    };
+
+    vec![
+        row("totalRequests", w.requests as f64, p.requests as f64, agg.total_requests as f64),
+        row("totalSuccessCount", w.success_count as f64, p.success_count as f64, agg.total_success_count as f64),
+        row("totalFailureCount", w.failure_count as f64, p.failure_count as f64, agg.total_failure_count as f64),
+        row("totalTokensIn", w.tokens_in as f64, p.tokens_in as f64, agg.total_tokens_in as f64),
+        row("totalTokensOut", w.tokens_out as f64, p.tokens_out as f64, agg.total_tokens_out as f64),
+        row("totalTokensCached", w.tokens_cached as f64, p.tokens_cached as f64, agg.total_tokens_cached as f64),
+        row("totalCostUsd", w.cost_usd, p.cost_usd, agg.total_cost_usd),
+    ]
+}
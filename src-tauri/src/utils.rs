@@ -1,29 +1,4 @@
-//! Utility functions for provider detection, model extraction, and cost estimation.
-
-/// Estimate cost based on model and tokens (pricing per 1M tokens)
-pub fn estimate_request_cost(model: &str, tokens_in: u32, tokens_out: u32) -> f64 {
-    let (input_rate, output_rate) = match model.to_lowercase().as_str() {
-        // Claude models
-        m if m.contains("claude") && m.contains("opus") => (15.0, 75.0),
-        m if m.contains("claude") && m.contains("sonnet") => (3.0, 15.0),
-        m if m.contains("claude") && m.contains("haiku") => (0.25, 1.25),
-        // GPT models
-        m if m.contains("gpt-5") => (15.0, 45.0),
-        m if m.contains("gpt-4o") => (2.5, 10.0),
-        m if m.contains("gpt-4-turbo") || m.contains("gpt-4") => (10.0, 30.0),
-        m if m.contains("gpt-3.5") => (0.5, 1.5),
-        // Gemini models
-        m if m.contains("gemini") && m.contains("pro") => (1.25, 5.0),
-        m if m.contains("gemini") && m.contains("flash") => (0.075, 0.30),
-        m if m.contains("gemini-2") => (0.10, 0.40),
-        m if m.contains("qwen") => (0.50, 2.0),
-        _ => (1.0, 3.0),
-    };
-    
-    let input_cost = (tokens_in as f64 / 1_000_000.0) * input_rate;
-    let output_cost = (tokens_out as f64 / 1_000_000.0) * output_rate;
-    input_cost + output_cost
-}
+//! Utility functions for provider detection and model extraction.
 
 /// Detect provider from model name
 pub fn detect_provider_from_model(model: &str) -> String {
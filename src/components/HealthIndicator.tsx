@@ -1,6 +1,8 @@
-import { createSignal, createEffect, onCleanup, Show } from "solid-js";
+import { createSignal, createEffect, onCleanup, onMount, Show } from "solid-js";
 import {
   checkProviderHealth,
+  getProviders,
+  type ProviderDescriptor,
   type ProviderHealth,
   type HealthStatus,
   type Provider,
@@ -123,25 +125,17 @@ export function HealthPanel() {
     }
   });
 
-  const providers = [
-    { id: "claude" as const, name: "Claude", connected: authStatus().claude },
-    { id: "openai" as const, name: "ChatGPT", connected: authStatus().openai },
-    { id: "gemini" as const, name: "Gemini", connected: authStatus().gemini },
-    { id: "qwen" as const, name: "Qwen", connected: authStatus().qwen },
-    { id: "iflow" as const, name: "iFlow", connected: authStatus().iflow },
-    {
-      id: "vertex" as const,
-      name: "Vertex AI",
-      connected: authStatus().vertex,
-    },
-    {
-      id: "antigravity" as const,
-      name: "Antigravity",
-      connected: authStatus().antigravity,
-    },
-  ];
-
-  const connectedProviders = () => providers.filter((p) => p.connected);
+  const [providers, setProviders] = createSignal<ProviderDescriptor[]>([]);
+  onMount(async () => {
+    try {
+      setProviders(await getProviders());
+    } catch (error) {
+      console.error("Failed to load providers:", error);
+    }
+  });
+
+  const connectedProviders = () =>
+    providers().filter((p) => authStatus()[p.id] > 0);
 
   return (
     <Show when={connectedProviders().length > 0}>
@@ -180,7 +174,7 @@ export function HealthPanel() {
             return (
               <div class="flex items-center justify-between py-1">
                 <span class="text-sm text-gray-700 dark:text-gray-300">
-                  {provider.name}
+                  {provider.displayName}
                 </span>
                 <div class="flex items-center gap-2">
                   <Show when={providerHealth()?.latencyMs}>
@@ -72,14 +72,22 @@ export async function importVertexCredential(
 	return invoke("import_vertex_credential", { filePath });
 }
 
-export interface AuthStatus {
-	claude: number;
-	openai: number;
-	gemini: number;
-	qwen: number;
-	iflow: number;
-	vertex: number;
-	antigravity: number;
+// Connected accounts per provider id (every registered provider is present)
+export type AuthStatus = Record<Provider, number>;
+
+// Provider registry entry (see get_providers)
+export interface ProviderDescriptor {
+	id: Provider;
+	displayName: string;
+	auth:
+		| { type: "oauth"; endpoint: string } // Management API endpoint returning the OAuth URL
+		| { type: "import"; hint: string };
+	authFilePrefixes: string[]; // Credential file prefixes in ~/.cli-proxy-api
+	modelOwners: string[]; // owned_by values of its models on /v1/models; check_provider_health needs one listed
+}
+
+export async function getProviders(): Promise<ProviderDescriptor[]> {
+	return invoke("get_providers");
 }
 
 export async function getAuthStatus(): Promise<AuthStatus> {
@@ -307,6 +315,62 @@ export interface AppConfig {
 	sshConfigs?: SshConfig[];
 	cloudflareConfigs?: CloudflareConfig[];
 	disableControlPanel?: boolean; // Hide CLIProxyAPI's web management UI
+	customTrackedEndpoints?: TrackedEndpoint[]; // Extra request paths to include in analytics
+	usageRetention?: UsageRetention;
+	displayTimezone?: string; // IANA zone for day/hour buckets, e.g. "Europe/Berlin"; empty = system
+	privacyMode?: boolean; // Stop recording requests entirely
+	subscriptions?: Subscription[];
+	budgets?: Budget[];
+	metrics?: MetricsSettings;
+	otlp?: OtlpSettings;
+	digest?: DigestSettings;
+	alertRules?: AlertRule[];
+	hooks?: Hook[];
+	tokenRefresh?: TokenRefreshSettings;
+}
+
+// How long usage analytics are kept per granularity (0 = forever)
+export interface UsageRetention {
+	rawDays: number; // Individual requests
+	hourlyDays: number;
+	dailyDays: number; // Older days are rolled up by month
+	monthlyMonths: number;
+}
+
+// Prometheus endpoint served at http://{bind}:{port}/metrics
+export interface MetricsSettings {
+	enabled: boolean;
+	bind: string; // "127.0.0.1" by default; "0.0.0.0" to allow remote scrapes
+	port: number; // 9464 by default
+	bearerToken: string; // Required as "Authorization: Bearer <token>" when set
+}
+
+// Export of logged requests to an OpenTelemetry collector (OTLP/HTTP, JSON)
+export interface OtlpSettings {
+	enabled: boolean;
+	endpoint: string; // Collector base URL, "http://127.0.0.1:4318" by default
+	signal: "traces" | "logs"; // One span or one log record per request
+	serviceName: string; // service.name resource attribute
+	headers: Record<string, string>; // Sent with every export (e.g. collector auth)
+}
+
+// Scheduled usage digests, written as Markdown and HTML
+export interface DigestSettings {
+	enabled: boolean;
+	frequency: "daily" | "weekly"; // Weekly digests cover Monday–Sunday
+	folder: string; // Empty = "reports" in the ProxyPal config folder
+}
+
+// Proactive refresh of OAuth tokens before they expire
+export interface TokenRefreshSettings {
+	enabled: boolean; // On by default
+	leadMinutes: number; // Refresh (or warn) this long before expiry, 10 by default
+}
+
+export interface TrackedEndpoint {
+	path: string;
+	protocol: "openai" | "claude" | "gemini" | string;
+	kind: string;
 }
 
 export async function getConfig(): Promise<AppConfig> {
@@ -429,6 +493,7 @@ export async function showSystemNotification(
 // Request log for live monitoring
 export interface RequestLog {
 	id: string;
+	requestId?: string; // CLIProxyAPI's 8-hex request ID
 	timestamp: number;
 	provider: string;
 	model: string;
@@ -438,8 +503,17 @@ export interface RequestLog {
 	durationMs: number;
 	tokensIn?: number;
 	tokensOut?: number;
+	tokensCached?: number;
+	apiKey?: string; // Stored as a fingerprint: masked key + short SHA-256, e.g. "sk-a...wxyz#3f9c0b1d27e4"
+	endpointKind: string; // "generation" | "embedding" | "count_tokens" | "image" | ...
+	account?: string; // OAuth account email or masked API key
+	apiKeyIndex?: number; // Index in the provider's API key list
+	errorCategory?: ErrorCategory; // Set for failed requests
+	errorMessage?: string; // Log line that best explains the failure
 }
 
+export type ErrorCategory = "quota" | "auth" | "timeout" | "upstream" | "client";
+
 export async function onRequestLog(
 	callback: (log: RequestLog) => void,
 ): Promise<UnlistenFn> {
@@ -455,15 +529,8 @@ export interface HealthStatus {
 	lastChecked: number;
 }
 
-export interface ProviderHealth {
-	claude: HealthStatus;
-	openai: HealthStatus;
-	gemini: HealthStatus;
-	qwen: HealthStatus;
-	iflow: HealthStatus;
-	vertex: HealthStatus;
-	antigravity: HealthStatus;
-}
+// Keyed by provider id
+export type ProviderHealth = Record<Provider, HealthStatus>;
 
 export async function checkProviderHealth(): Promise<ProviderHealth> {
 	return invoke("check_provider_health");
@@ -615,6 +682,262 @@ export async function clearRequestHistory(): Promise<void> {
 	return invoke("clear_request_history");
 }
 
+// Full request log (SQLite-backed, not capped)
+export interface RequestQuery {
+	model?: string;
+	provider?: string;
+	apiKey?: string; // Raw key or its fingerprint
+	endpointKind?: string;
+	requestId?: string;
+	account?: string;
+	errorCategory?: ErrorCategory;
+	status?: number;
+	failedOnly?: boolean;
+	from?: number;
+	to?: number;
+	sortBy?: "timestamp" | "duration" | "status" | "model" | "provider";
+	descending?: boolean;
+	limit?: number;
+	cursor?: string;
+}
+
+export interface RequestPage {
+	requests: RequestLog[];
+	nextCursor?: string;
+	total: number;
+}
+
+export async function queryRequests(query: RequestQuery): Promise<RequestPage> {
+	return invoke("query_requests", { query });
+}
+
+export interface AccountUsage {
+	account: string;
+	provider: string;
+	apiKeyIndex?: number;
+	requests: number;
+	successCount: number;
+	failureCount: number;
+	inputTokens: number;
+	outputTokens: number;
+	cachedTokens: number;
+	lastUsed: number;
+}
+
+export async function getAccountUsage(
+	from?: number,
+	to?: number,
+): Promise<AccountUsage[]> {
+	return invoke("get_account_usage", { from, to });
+}
+
+// Failure analytics
+export interface ErrorSeriesPoint {
+	label: string; // "YYYY-MM-DD" or "YYYY-MM-DDTHH"
+	group: string;
+	category: ErrorCategory;
+	count: number;
+}
+
+export async function getErrorSeries(
+	groupBy: "provider" | "model" | "account",
+	bucket: "day" | "hour" = "day",
+	from?: number,
+	to?: number,
+): Promise<ErrorSeriesPoint[]> {
+	return invoke("get_error_series", { groupBy, bucket, from, to });
+}
+
+export interface ErrorCause {
+	category: ErrorCategory;
+	provider: string;
+	model: string;
+	count: number;
+	accounts: string[];
+	lastSeen: number;
+	examples: string[];
+}
+
+export async function getTopErrors(
+	since?: number,
+	limit?: number,
+): Promise<ErrorCause[]> {
+	return invoke("get_top_errors", { since, limit });
+}
+
+// Usage over time per model, provider or account
+export interface UsageSeriesQuery {
+	groupBy: "model" | "provider" | "account";
+	bucket?: "day" | "hour";
+	from: number; // ms, inclusive
+	to: number; // ms, exclusive
+	comparePrevious?: boolean; // Also fetch the preceding period of the same length
+}
+
+export interface UsageSeriesPoint {
+	label: string;
+	group: string;
+	requests: number;
+	successCount: number;
+	inputTokens: number;
+	outputTokens: number;
+	cachedTokens: number;
+	costUsd: number;
+}
+
+export interface UsageGroupTotal {
+	group: string;
+	requests: number;
+	tokens: number;
+	costUsd: number;
+	previousRequests?: number;
+	previousTokens?: number;
+	previousCostUsd?: number;
+	requestsChangePct?: number;
+}
+
+export interface UsageSeries {
+	current: UsageSeriesPoint[];
+	previous?: UsageSeriesPoint[];
+	totals: UsageGroupTotal[];
+}
+
+export async function getUsageSeries(
+	query: UsageSeriesQuery,
+): Promise<UsageSeries> {
+	return invoke("get_usage_series", { query });
+}
+
+// Retention
+export interface CompactionSummary {
+	requestsRolledUp: number;
+	hourlyPointsRemoved: number;
+	dailyPointsRolledUp: number;
+	monthlyPointsRemoved: number;
+}
+
+export async function compactUsageData(): Promise<CompactionSummary> {
+	return invoke("compact_usage_data");
+}
+
+// Permanently delete usage data older than `before` (ms); returns deleted request count
+export async function purgeUsageData(before: number): Promise<number> {
+	return invoke("purge_usage_data", { before });
+}
+
+// Token backfill: match CLIProxyAPI usage details to logged requests
+export interface UsageReconcileSummary {
+	matched: number;
+	unmatchedRequests: number;
+	unmatchedDetails: number;
+}
+
+export async function backfillRequestTokens(): Promise<UsageReconcileSummary> {
+	return invoke("backfill_request_tokens");
+}
+
+// Usage drift: log watcher counts vs. CLIProxyAPI usage (ledger)
+export type UsageSource = "watcher" | "proxy";
+
+export interface FieldDrift {
+	field: string; // Aggregate field, e.g. "totalRequests"
+	watcher: number;
+	proxy: number;
+	value: number; // Merged value in the aggregate
+	source: UsageSource;
+}
+
+export interface ModelDrift {
+	model: string;
+	watcherRequests: number;
+	proxyRequests: number;
+	watcherFailures: number;
+	proxyFailures: number;
+	watcherTokens: number;
+	proxyTokens: number;
+}
+
+export interface UsageDriftReport {
+	fields: FieldDrift[];
+	models: ModelDrift[];
+}
+
+export async function getUsageDrift(
+	from?: number,
+	to?: number,
+): Promise<UsageDriftReport> {
+	return invoke("get_usage_drift", { from, to });
+}
+
+// Pricing catalog (USD per 1M tokens)
+export interface PriceRule {
+	model?: string; // Glob, e.g. "*claude*sonnet*"
+	provider?: string;
+	input: number;
+	output: number;
+	cacheRead?: number; // Defaults to the input price
+	cacheWrite?: number; // Defaults to the input price
+	effectiveFrom?: string; // "YYYY-MM-DD" (UTC)
+}
+
+export interface PricingCatalog {
+	rules: PriceRule[];
+}
+
+export interface PricingCatalogs {
+	bundled: PricingCatalog;
+	user: PricingCatalog; // Consulted before the bundled catalog
+	userPath: string;
+}
+
+export interface CostRecomputeSummary {
+	previousCostUsd: number;
+	costUsd: number;
+}
+
+export async function getPricingCatalog(): Promise<PricingCatalogs> {
+	return invoke("get_pricing_catalog");
+}
+
+// Saving re-prices all stored usage with the new catalog
+export async function savePricingCatalog(
+	catalog: PricingCatalog,
+): Promise<CostRecomputeSummary> {
+	return invoke("save_pricing_catalog", { catalog });
+}
+
+export async function resolveModelPrice(
+	model: string,
+	provider?: string,
+	timestamp?: number,
+): Promise<PriceRule | null> {
+	return invoke("resolve_model_price", { model, provider, timestamp });
+}
+
+// Latency percentiles of successful requests
+export interface LatencySummary {
+	key: string; // Model or provider; the model when grouped by route
+	provider?: string; // Set when grouped by route (model through a provider)
+	count: number;
+	meanMs: number;
+	p50Ms: number;
+	p90Ms: number;
+	p99Ms: number;
+	maxMs: number;
+}
+
+export async function getLatencyStats(
+	groupBy: "model" | "provider" | "route",
+	from?: string, // "YYYY-MM-DD", inclusive
+	to?: string,
+): Promise<LatencySummary[]> {
+	return invoke("get_latency_stats", { groupBy, from, to });
+}
+
+export async function rebuildAggregate(): Promise<unknown> {
+	return invoke("rebuild_aggregate");
+}
+
 // Sync usage statistics from CLIProxyAPI (fetches real token counts)
 export async function syncUsageFromProxy(): Promise<RequestHistory> {
 	return invoke("sync_usage_from_proxy");
@@ -625,6 +948,42 @@ export async function exportUsageStats(): Promise<unknown> {
 	return invoke("export_usage_stats");
 }
 
+// Analytics exports (CSV or JSON Lines, written straight to disk)
+export type ExportFormat = "csv" | "jsonl";
+
+export interface ExportRange {
+	from?: number;
+	to?: number;
+}
+
+export interface AggregateGrouping {
+	bucket?: "hour" | "day" | "month";
+	by?: "provider" | "model" | "account" | "endpoint";
+}
+
+export interface ExportSummary {
+	path: string;
+	format: ExportFormat;
+	rows: number;
+}
+
+export async function exportRequests(
+	filter: RequestQuery,
+	format: ExportFormat,
+	path: string,
+): Promise<ExportSummary> {
+	return invoke("export_requests", { filter, format, path });
+}
+
+export async function exportAggregate(
+	range: ExportRange,
+	grouping: AggregateGrouping,
+	format: ExportFormat,
+	path: string,
+): Promise<ExportSummary> {
+	return invoke("export_aggregate", { range, grouping, format, path });
+}
+
 // Import usage statistics from backup
 export interface ImportUsageResult {
 	added: number;
@@ -639,6 +998,125 @@ export async function importUsageStats(
 	return invoke("import_usage_stats", { data });
 }
 
+// ProxyPal usage backups (request log, aggregate and pricing), merged on import
+export interface UsageBackupMetadata {
+	format: string;
+	version: number;
+	appVersion: string;
+	createdAt: number;
+	requestCount: number;
+}
+
+export interface UsageBackupImportSummary {
+	requestsAdded: number;
+	requestsSkipped: number;
+	pricingRulesAdded: number;
+	backupCreatedAt: number;
+	totalRequests: number;
+	totalCostUsd: number;
+}
+
+export async function exportUsageBackup(
+	path: string,
+): Promise<UsageBackupMetadata> {
+	return invoke("export_usage_backup", { path });
+}
+
+export async function importUsageBackup(
+	path: string,
+): Promise<UsageBackupImportSummary> {
+	return invoke("import_usage_backup", { path });
+}
+
+// Usage digests: a period's usage compared with the period before it
+export interface DigestTotals {
+	requests: number;
+	successCount: number;
+	failureCount: number;
+	inputTokens: number;
+	outputTokens: number;
+	cachedTokens: number;
+	costUsd: number;
+}
+
+export interface DigestModel {
+	model: string;
+	requests: number;
+	previousRequests: number;
+	inputTokens: number;
+	outputTokens: number;
+	costUsd: number;
+}
+
+export interface DigestAccount {
+	account: string;
+	provider: string;
+	requests: number;
+	failureCount: number;
+	inputTokens: number;
+	outputTokens: number;
+	costUsd: number;
+}
+
+export interface DigestErrorCount {
+	category: string;
+	count: number;
+	previousCount: number;
+}
+
+export interface ErrorSpike {
+	day: string;
+	category: string;
+	count: number;
+	baselinePerDay: number; // Average per day in the prior period
+}
+
+export interface LatencyChange {
+	provider: string;
+	count: number;
+	p50Ms: number;
+	p90Ms: number;
+	previousP50Ms: number | null;
+	previousP90Ms: number | null;
+}
+
+export interface DigestSavings {
+	provider: string;
+	planName: string;
+	apiCostUsd: number;
+	feeUsd: number; // Monthly fee prorated to the period
+	savingsUsd: number;
+}
+
+export interface UsageDigest {
+	from: number;
+	to: number;
+	label: string;
+	generatedAt: number;
+	totals: DigestTotals;
+	previous: DigestTotals;
+	topModels: DigestModel[];
+	accounts: DigestAccount[];
+	errors: DigestErrorCount[];
+	errorSpikes: ErrorSpike[];
+	latency: LatencyChange[];
+	savings: DigestSavings[];
+	totalSavingsUsd: number;
+}
+
+export interface DigestFiles {
+	markdownPath: string;
+	htmlPath: string;
+	digest: UsageDigest;
+}
+
+// Defaults to the last 7 days
+export async function generateUsageReport(
+	range: ExportRange = {},
+): Promise<DigestFiles> {
+	return invoke("generate_usage_report", { range });
+}
+
 // Test agent connection
 export interface AgentTestResult {
 	success: boolean;
@@ -922,6 +1400,30 @@ export interface AuthFile {
 	lastRefresh?: string;
 	successCount?: number;
 	failureCount?: number;
+	expiresAt?: number; // Access token expiry (Unix ms), from the credential file
+	refreshable: boolean; // Holds a refresh token
+}
+
+// Emitted when a token enters the refresh window, and again if it expires unrefreshed
+export interface AuthExpiring {
+	name: string; // Credential file name
+	provider: string;
+	expiresAt: number; // Unix ms; the new expiry when action is "refreshed"
+	refreshable: boolean;
+	action:
+		| "refreshed"
+		| "refresh_failed" // Retried with backoff until it succeeds or the token expires
+		| "manual" // No refresh token, or the proxy didn't refresh it; the account may need reconnecting
+		| "expired";
+	message: string;
+}
+
+export async function onAuthExpiring(
+	callback: (event: AuthExpiring) => void,
+): Promise<UnlistenFn> {
+	return listen<AuthExpiring>("auth-expiring", (event) => {
+		callback(event.payload);
+	});
 }
 
 export async function getAuthFiles(): Promise<AuthFile[]> {
@@ -969,16 +1471,10 @@ export interface ProxyAuthProviderStatus {
 	error?: string;
 }
 
-export interface ProxyAuthProviders {
-	gemini?: ProxyAuthProviderStatus;
-	claude?: ProxyAuthProviderStatus;
-	openai?: ProxyAuthProviderStatus;
-	qwen?: ProxyAuthProviderStatus;
-	iflow?: ProxyAuthProviderStatus;
-	vertex?: ProxyAuthProviderStatus;
-	antigravity?: ProxyAuthProviderStatus;
-	copilot?: ProxyAuthProviderStatus;
-}
+// Keyed by CLIProxyAPI's provider names (may include ones ProxyPal doesn't register, e.g. copilot)
+export type ProxyAuthProviders = Partial<
+	Record<Provider | "copilot" | (string & {}), ProxyAuthProviderStatus>
+>;
 
 export interface ProxyAuthStatus {
 	status: string; // "ok", "error", "unknown", "unsupported"
@@ -1275,6 +1771,290 @@ export async function fetchAntigravityQuota(): Promise<
 	return invoke("fetch_antigravity_quota");
 }
 
+// ============================================
+// Subscriptions
+// ============================================
+
+export interface Subscription {
+	id: string; // Empty to create
+	provider: string; // "claude", "openai", "gemini", ...
+	planName: string;
+	monthlyFeeUsd: number;
+	billingDay: number; // 1-31, clamped to the month's length
+	accounts: string[]; // Account emails or masked API keys, as in account usage
+}
+
+export interface AccountSavings {
+	subscriptionId: string;
+	provider: string;
+	planName: string;
+	account: string | null; // null when the plan has no linked accounts (covers its share of the provider usage outside linked accounts)
+	periodStart: string; // "YYYY-MM-DD", inclusive
+	periodEnd: string; // "YYYY-MM-DD", exclusive
+	requests: number;
+	inputTokens: number;
+	outputTokens: number;
+	cachedTokens: number;
+	apiCostUsd: number;
+	feeUsd: number; // Share of the plan's fee
+	savingsUsd: number; // Negative = the plan cost more than its usage was worth
+}
+
+export interface ProviderSavings {
+	provider: string;
+	subscriptions: number;
+	feeUsd: number;
+	apiCostUsd: number;
+	savingsUsd: number;
+}
+
+export interface SubscriptionReport {
+	month: string; // "YYYY-MM"
+	inProgress: boolean;
+	accounts: AccountSavings[];
+	providers: ProviderSavings[];
+	totalFeeUsd: number;
+	totalApiCostUsd: number;
+	totalSavingsUsd: number;
+}
+
+export async function getSubscriptions(): Promise<Subscription[]> {
+	return invoke("get_subscriptions");
+}
+
+export async function saveSubscription(
+	subscription: Subscription,
+): Promise<Subscription[]> {
+	return invoke("save_subscription", { subscription });
+}
+
+export async function deleteSubscription(id: string): Promise<Subscription[]> {
+	return invoke("delete_subscription", { id });
+}
+
+// Covers billing periods starting in `month` (default: the current month)
+export async function getSubscriptionReport(
+	month?: string,
+): Promise<SubscriptionReport> {
+	return invoke("get_subscription_report", { month });
+}
+
+export async function exportSubscriptionReport(
+	format: "markdown" | "csv",
+	month?: string,
+): Promise<string> {
+	return invoke("export_subscription_report", { month, format });
+}
+
+// ============================================
+// Budgets
+// ============================================
+
+export interface Budget {
+	id: string; // Empty to create
+	name: string;
+	scope: "provider" | "model" | "client_key";
+	target: string; // Provider, model (globs allowed) or client API key
+	period: "daily" | "weekly" | "monthly"; // Weeks start on Monday
+	metric: "tokens" | "cost"; // Input + output tokens, or estimated USD
+	limit: number;
+	enforce: boolean; // Pause the credentials it used until the period resets
+	enabled: boolean;
+}
+
+export interface BudgetSuspension {
+	budgetId: string;
+	kind: "auth_file" | "api_key";
+	provider: string;
+	target: string; // Auth file name, or an opaque id for an API key
+	label: string; // Account email or masked key
+	until: number; // ms since epoch
+}
+
+export interface BudgetStatus {
+	budget: Budget;
+	periodStart: number;
+	periodEnd: number;
+	used: number;
+	fraction: number;
+	alertLevel: 0 | 80 | 100;
+	suspensions: BudgetSuspension[];
+}
+
+// Payload of the "budget-alert" event
+export interface BudgetAlert {
+	budgetId: string;
+	name: string;
+	level: 80 | 100;
+	metric: "tokens" | "cost";
+	used: number;
+	limit: number;
+	periodEnd: number;
+	suspended: number;
+}
+
+export async function getBudgets(): Promise<Budget[]> {
+	return invoke("get_budgets");
+}
+
+export async function saveBudget(budget: Budget): Promise<Budget[]> {
+	return invoke("save_budget", { budget });
+}
+
+export async function deleteBudget(id: string): Promise<Budget[]> {
+	return invoke("delete_budget", { id });
+}
+
+export async function getBudgetStatus(): Promise<BudgetStatus[]> {
+	return invoke("get_budget_status");
+}
+
+export async function onBudgetAlert(
+	callback: (alert: BudgetAlert) => void,
+): Promise<UnlistenFn> {
+	return listen<BudgetAlert>("budget-alert", (event) => {
+		callback(event.payload);
+	});
+}
+
+// ============================================
+// Alerts
+// ============================================
+
+export type AlertRuleKind =
+	| "error_rate"
+	| "auth_failures"
+	| "latency_p95"
+	| "sidecar_restart"
+	| "tunnel_down";
+
+export type AlertSeverity = "info" | "warning" | "critical";
+
+export interface AlertRule {
+	id: string; // Empty to create
+	name: string;
+	kind: AlertRuleKind;
+	provider: string; // Request rules only; empty = each provider
+	threshold: number; // Failure %, 401 count, p95 ms or sidecar exits (unused for tunnel_down)
+	windowSecs: number; // For tunnel_down: how long the tunnel must stay down
+	minRequests: number; // Needed in the window for error_rate and latency_p95
+	severity: AlertSeverity;
+	cooldownSecs: number; // Per provider or tunnel
+	enabled: boolean;
+}
+
+// Also the payload of the "alert" event
+export interface Alert {
+	id: string;
+	ruleId: string;
+	ruleName: string;
+	kind: AlertRuleKind;
+	severity: AlertSeverity;
+	subject: string; // Provider or tunnel ("ssh:<id>", "cloudflare:<id>"); empty for the sidecar
+	message: string;
+	value: number;
+	threshold: number;
+	triggeredAt: number;
+	acknowledgedAt: number | null;
+}
+
+export async function getAlertRules(): Promise<AlertRule[]> {
+	return invoke("get_alert_rules");
+}
+
+export async function saveAlertRule(rule: AlertRule): Promise<AlertRule[]> {
+	return invoke("save_alert_rule", { rule });
+}
+
+export async function deleteAlertRule(id: string): Promise<AlertRule[]> {
+	return invoke("delete_alert_rule", { id });
+}
+
+// Most recent first
+export async function getAlerts(): Promise<Alert[]> {
+	return invoke("get_alerts");
+}
+
+export async function acknowledgeAlert(id: string): Promise<Alert[]> {
+	return invoke("acknowledge_alert", { id });
+}
+
+export async function acknowledgeAllAlerts(): Promise<Alert[]> {
+	return invoke("acknowledge_all_alerts");
+}
+
+export async function onAlert(
+	callback: (alert: Alert) => void,
+): Promise<UnlistenFn> {
+	return listen<Alert>("alert", (event) => {
+		callback(event.payload);
+	});
+}
+
+// ============================================
+// Webhooks and command hooks
+// ============================================
+
+export type HookEvent =
+	| "proxy-status-changed"
+	| "auth-status-changed"
+	| "copilot-status-changed"
+	| "ssh-status-changed"
+	| "cloudflare-status-changed"
+	| "request-log"
+	| "auth-expiring";
+
+export interface Hook {
+	id: string; // Empty to create
+	name: string;
+	kind: "webhook" | "command";
+	events: HookEvent[];
+	url: string; // Webhook URL (POST)
+	secret: string; // Signs bodies: X-ProxyPal-Signature = "sha256=" + HMAC of "<timestamp>.<body>"
+	format: "json" | "slack" | "template";
+	template: string; // {{event}}, {{summary}}, {{timestamp}}, {{payload}}, {{payload.<field>}}
+	command: string; // Absolute path of an executable (no shell); gets PROXYPAL_EVENT, and the payload in PROXYPAL_PAYLOAD and on stdin
+	args: string[]; // Passed to the executable as-is
+	maxRetries: number; // Webhooks only, at most 10
+	enabled: boolean;
+}
+
+export interface HookDelivery {
+	id: string;
+	hookId: string;
+	hookName: string;
+	event: HookEvent | "test";
+	timestamp: number;
+	success: boolean;
+	attempts: number;
+	status: number | null; // HTTP status or exit code
+	message: string;
+	durationMs: number;
+}
+
+export async function getHooks(): Promise<Hook[]> {
+	return invoke("get_hooks");
+}
+
+export async function saveHook(hook: Hook): Promise<Hook[]> {
+	return invoke("save_hook", { hook });
+}
+
+export async function deleteHook(id: string): Promise<Hook[]> {
+	return invoke("delete_hook", { id });
+}
+
+// Most recent first; all hooks when hookId is omitted
+export async function getHookDeliveries(
+	hookId?: string,
+): Promise<HookDelivery[]> {
+	return invoke("get_hook_deliveries", { hookId });
+}
+
+export async function testHook(hook: Hook): Promise<HookDelivery> {
+	return invoke("test_hook", { hook });
+}
+
 // ============================================
 // SSH Management
 // ============================================